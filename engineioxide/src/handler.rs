@@ -60,6 +60,10 @@ pub trait EngineIoHandler: std::fmt::Debug + Send + Sync + 'static {
 
     /// Called when a binary message is received from the client.
     fn on_binary(&self, data: Vec<u8>, socket: Arc<Socket<Self::Data>>);
+
+    /// Called when the transport of a socket is upgraded from polling to websocket.
+    /// A connection is never downgraded, if the websocket connection fails the socket is closed.
+    fn on_upgrade(&self, _socket: Arc<Socket<Self::Data>>) {}
 }
 
 impl<T: EngineIoHandler> EngineIoHandler for Arc<T> {
@@ -80,4 +84,8 @@ impl<T: EngineIoHandler> EngineIoHandler for Arc<T> {
     fn on_binary(&self, data: Vec<u8>, socket: Arc<Socket<Self::Data>>) {
         (**self).on_binary(data, socket)
     }
+
+    fn on_upgrade(&self, socket: Arc<Socket<Self::Data>>) {
+        (**self).on_upgrade(socket)
+    }
 }
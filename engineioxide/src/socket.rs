@@ -55,7 +55,7 @@
 //! ```
 use std::{
     sync::{
-        atomic::{AtomicU8, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
         Arc,
     },
     time::Duration,
@@ -96,6 +96,20 @@ pub enum DisconnectReason {
     ClosingServer,
 }
 
+impl DisconnectReason {
+    /// The websocket close code and reason sent to the client when the server closes the socket
+    fn close_code(&self) -> (u16, &'static str) {
+        match self {
+            DisconnectReason::TransportClose => (1000, "transport close"),
+            DisconnectReason::HeartbeatTimeout => (1000, "heartbeat timeout"),
+            DisconnectReason::ClosingServer => (1001, "server closing"),
+            DisconnectReason::PacketParsingError => (1002, "packet parsing error"),
+            DisconnectReason::MultipleHttpPollingError => (1008, "multiple http polling error"),
+            DisconnectReason::TransportError => (1011, "transport error"),
+        }
+    }
+}
+
 /// Convert an [`Error`] to a [`DisconnectReason`] if possible
 /// This is used to notify the [`Handler`](crate::handler::EngineIoHandler) of the reason why a [`Socket`] was closed
 /// If the error cannot be converted to a [`DisconnectReason`] it means that the error was not fatal and the [`Socket`] can be kept alive
@@ -178,6 +192,8 @@ where
     /// It is represented as a bitfield to allow the use of an [`AtomicU8`] so it can be shared between threads
     /// without any mutex
     transport: AtomicU8,
+    /// Set when the connection was upgraded from polling to websocket
+    upgraded: AtomicBool,
 
     /// Channel to receive [`Packet`] from the connection
     ///
@@ -205,6 +221,12 @@ where
     pub(crate) heartbeat_tx: mpsc::Sender<()>,
     /// Handle to the heartbeat job so that it can be aborted when the socket is closed
     heartbeat_handle: Mutex<Option<JoinHandle<()>>>,
+    /// Last measured round-trip time between a Ping and its Pong, in nanoseconds.
+    /// It is set to 0 as long as no Pong has been received.
+    latency: AtomicU64,
+    /// The close code and reason given when the socket was closed by the server.
+    /// They are sent to the client in the websocket close frame.
+    close_frame: std::sync::Mutex<Option<(u16, &'static str)>>,
 
     /// Function to call when the socket is closed
     close_fn: Box<dyn Fn(Sid, DisconnectReason) + Send + Sync>,
@@ -238,6 +260,7 @@ where
             id: Sid::new(),
             protocol,
             transport: AtomicU8::new(transport as u8),
+            upgraded: AtomicBool::new(false),
 
             internal_rx: Mutex::new(PeekableReceiver::new(internal_rx)),
             internal_tx,
@@ -245,6 +268,8 @@ where
             heartbeat_rx: Mutex::new(heartbeat_rx),
             heartbeat_tx,
             heartbeat_handle: Mutex::new(None),
+            latency: AtomicU64::new(0),
+            close_frame: std::sync::Mutex::new(None),
             close_fn,
 
             data: D::default(),
@@ -336,10 +361,13 @@ where
             self.internal_tx
                 .try_send(Packet::Ping)
                 .map_err(|_| Error::HeartbeatTimeout)?;
+            let ping_instant = tokio::time::Instant::now();
             tokio::time::timeout(timeout, heartbeat_rx.recv())
                 .await
                 .map_err(|_| Error::HeartbeatTimeout)?
                 .ok_or(Error::HeartbeatTimeout)?;
+            let latency = ping_instant.elapsed().as_nanos().max(1) as u64;
+            self.latency.store(latency, Ordering::Relaxed);
             interval_tick.tick().await;
         }
     }
@@ -368,6 +396,18 @@ where
         }
     }
 
+    /// Returns the round-trip time between the last Ping sent by the server and its Pong.
+    ///
+    /// It is updated on each heartbeat.
+    /// Returns `None` if no Pong has been received yet or if the socket uses the v3 protocol,
+    /// where the client is the one sending Pings.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.latency.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
     /// Returns true if the [`Socket`] has a websocket [`TransportType`]
     pub(crate) fn is_ws(&self) -> bool {
         self.transport.load(Ordering::Relaxed) == TransportType::Websocket as u8
@@ -382,6 +422,7 @@ where
     pub(crate) fn upgrade_to_websocket(&self) {
         self.transport
             .store(TransportType::Websocket as u8, Ordering::Relaxed);
+        self.upgraded.store(true, Ordering::Relaxed);
     }
 
     /// Returns the current [`TransportType`] of the [`Socket`]
@@ -389,6 +430,11 @@ where
         TransportType::from(self.transport.load(Ordering::Relaxed))
     }
 
+    /// Returns true if the [`Socket`] was upgraded from the polling transport to the websocket transport
+    pub fn is_upgraded(&self) -> bool {
+        self.upgraded.load(Ordering::Relaxed)
+    }
+
     /// Reserve `n` permits to emit multiple messages and ensure that there is enough
     /// space in the internal chan.
     ///
@@ -400,6 +446,18 @@ where
         Ok(PermitIterator { inner })
     }
 
+    /// Reserve `n` permits to emit multiple messages, waiting for enough space in the internal chan.
+    ///
+    /// If the socket is closed or if `n` is greater than the buffer size,
+    /// the function will return a [`mpsc::error::SendError`] error.
+    pub async fn reserve_wait(
+        &self,
+        n: usize,
+    ) -> Result<PermitIterator<'_>, mpsc::error::SendError<()>> {
+        let inner = self.internal_tx.reserve_many(n).await?;
+        Ok(PermitIterator { inner })
+    }
+
     /// Emits a message to the client.
     ///
     /// If the transport is in websocket mode, the message is directly sent as a text frame.
@@ -416,11 +474,41 @@ where
 
     /// Immediately closes the socket and the underlying connection.
     /// The socket will be removed from the `Engine` and the [`Handler`](crate::handler::EngineIoHandler) will be notified.
+    ///
+    /// With the websocket transport, the close frame sent to the client has a close code and a reason
+    /// matching the [`DisconnectReason`]:
+    ///
+    /// | Reason                     | Close code           |
+    /// |----------------------------|----------------------|
+    /// | `TransportClose`           | 1000 Normal          |
+    /// | `HeartbeatTimeout`         | 1000 Normal          |
+    /// | `ClosingServer`            | 1001 Going away      |
+    /// | `PacketParsingError`       | 1002 Protocol error  |
+    /// | `MultipleHttpPollingError` | 1008 Policy violated |
+    /// | `TransportError`           | 1011 Internal error  |
     pub fn close(&self, reason: DisconnectReason) {
+        let (code, message) = reason.close_code();
+        self.close_with_code(reason, code, message);
+    }
+
+    /// Closes the socket as with [`Socket::close`], but the websocket close frame sent to the client
+    /// has the given close `code` and `message` rather than the ones matching the [`DisconnectReason`].
+    ///
+    /// It lets the protocols built on top of engine.io tell the client why they closed the connection.
+    pub fn close_with_code(&self, reason: DisconnectReason, code: u16, message: &'static str) {
+        self.close_frame
+            .lock()
+            .unwrap()
+            .get_or_insert((code, message));
         (self.close_fn)(self.id, reason);
         self.send(Packet::Close).ok();
     }
 
+    /// Returns the close code and reason given when the socket was first closed by the server, if any.
+    pub(crate) fn close_frame(&self) -> Option<(u16, &'static str)> {
+        *self.close_frame.lock().unwrap()
+    }
+
     /// Returns true if the socket is closed
     /// It means that no more packets can be sent to the client
     pub fn is_closed(&self) -> bool {
@@ -432,6 +520,11 @@ where
         self.internal_tx.closed().await
     }
 
+    /// Returns the number of packets buffered in the internal channel and not yet taken by the transport
+    pub fn buffered_packets(&self) -> usize {
+        self.internal_tx.max_capacity() - self.internal_tx.capacity()
+    }
+
     /// Emits a binary message to the client.
     ///
     /// If the transport is in websocket mode, the message is directly sent as a binary frame.
@@ -463,6 +556,7 @@ impl<D: Default + Send + Sync + 'static> std::fmt::Debug for Socket<D> {
             .field("heartbeat_rx", &self.heartbeat_rx)
             .field("heartbeat_tx", &self.heartbeat_tx)
             .field("heartbeat_handle", &self.heartbeat_handle)
+            .field("latency", &self.latency)
             .field("req_data", &self.req_parts)
             .finish()
     }
@@ -496,6 +590,7 @@ where
             id: sid,
             protocol: ProtocolVersion::V4,
             transport: AtomicU8::new(TransportType::Websocket as u8),
+            upgraded: AtomicBool::new(false),
 
             internal_rx: Mutex::new(PeekableReceiver::new(internal_rx)),
             internal_tx,
@@ -503,6 +598,8 @@ where
             heartbeat_rx: Mutex::new(heartbeat_rx),
             heartbeat_tx,
             heartbeat_handle: Mutex::new(None),
+            latency: AtomicU64::new(0),
+            close_frame: std::sync::Mutex::new(None),
             close_fn,
 
             data: D::default(),
@@ -4,7 +4,7 @@
 //! Other functions are used internally to handle the websocket connection through tasks and channels
 //! and to handle upgrade from polling to ws
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use futures::{
     stream::{SplitSink, SplitStream},
@@ -16,7 +16,11 @@ use tokio::{
     task::JoinHandle,
 };
 use tokio_tungstenite::{
-    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{CloseFrame, Role},
+        Message,
+    },
     WebSocketStream,
 };
 
@@ -33,6 +37,9 @@ use crate::{
     DisconnectReason, Socket,
 };
 
+/// How long a socket closed after a fatal error waits for its close frame to be sent
+const CLOSE_FRAME_TIMEOUT: Duration = Duration::from_millis(200);
+
 /// Create a response for websocket upgrade
 fn ws_response<B>(ws_key: &HeaderValue) -> Result<Response<ResponseBody<B>>, http::Error> {
     let derived = derive_accept_key(ws_key.as_bytes());
@@ -118,6 +125,7 @@ where
             Some(socket) => {
                 let mut ws = ws_init().await;
                 upgrade_handshake::<H, S>(&socket, &mut ws).await?;
+                engine.handler.on_upgrade(socket.clone());
                 (socket, ws)
             }
         }
@@ -139,13 +147,18 @@ where
         (socket, ws)
     };
     let (tx, rx) = ws.split();
-    let rx_handle = forward_to_socket::<H, S>(socket.clone(), tx);
+    let mut rx_handle = forward_to_socket::<H, S>(socket.clone(), tx);
 
     if let Err(ref e) = forward_to_handler(&engine, rx, &socket).await {
         #[cfg(feature = "tracing")]
         tracing::debug!("[sid={}] error when handling packet: {:?}", socket.id, e);
         if let Some(reason) = e.into() {
-            engine.close_session(socket.id, reason);
+            // The socket is closed through the forwarding task so that the client receives a close frame
+            // with the reason, it is aborted if it cannot send it in time
+            socket.close(reason);
+            tokio::time::timeout(CLOSE_FRAME_TIMEOUT, &mut rx_handle)
+                .await
+                .ok();
         }
     } else {
         engine.close_session(socket.id, DisconnectReason::TransportClose);
@@ -216,7 +229,11 @@ where
                         tx.feed(Message::Binary(bin)).await
                     }
                     Packet::Close => {
-                        tx.send(Message::Close(None)).await.ok();
+                        let frame = socket.close_frame().map(|(code, reason)| CloseFrame {
+                            code: code.into(),
+                            reason: reason.into(),
+                        });
+                        tx.send(Message::Close(frame)).await.ok();
                         internal_rx.close();
                         break;
                     },
@@ -11,13 +11,16 @@ use engineioxide::{
     handler::EngineIoHandler,
     socket::{DisconnectReason, Socket},
 };
-use futures::SinkExt;
+use futures::{SinkExt, StreamExt};
 use tokio::sync::mpsc;
 
 mod fixture;
 
 use fixture::{create_server, send_req};
-use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::{
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
 
 use crate::fixture::{create_polling_connection, create_ws_connection};
 
@@ -182,3 +185,49 @@ pub async fn ws_packet_parsing() {
 
     assert_eq!(data, DisconnectReason::PacketParsingError);
 }
+
+/// Reads the websocket stream until the close frame sent by the server
+async fn recv_close_frame(
+    stream: &mut tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+) -> CloseFrame<'static> {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_millis(500), stream.next())
+            .await
+            .expect("timeout waiting for the close frame")
+            .unwrap()
+            .unwrap();
+        if let Message::Close(frame) = msg {
+            return frame
+                .expect("the close frame should have a code")
+                .into_owned();
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn ws_heartbeat_timeout_close_frame() {
+    let (disconnect_tx, _rx) = mpsc::channel(10);
+    create_server(MyHandler { disconnect_tx }, 12352).await;
+    let mut stream = create_ws_connection(12352).await;
+
+    let frame = recv_close_frame(&mut stream).await;
+    assert_eq!(frame.code, CloseCode::Normal);
+    assert_eq!(frame.reason, "heartbeat timeout");
+}
+
+#[tokio::test]
+pub async fn ws_packet_parsing_close_frame() {
+    let (disconnect_tx, _rx) = mpsc::channel(10);
+    create_server(MyHandler { disconnect_tx }, 12353).await;
+    let mut stream = create_ws_connection(12353).await;
+    stream
+        .send(Message::Text("aizdunazidaubdiz".into()))
+        .await
+        .unwrap();
+
+    let frame = recv_close_frame(&mut stream).await;
+    assert_eq!(frame.code, CloseCode::Protocol);
+    assert_eq!(frame.reason, "packet parsing error");
+}
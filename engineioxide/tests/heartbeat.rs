@@ -0,0 +1,92 @@
+//! Tests for the heartbeat mechanism
+
+use std::{sync::Arc, time::Duration};
+
+use engineioxide::{
+    handler::EngineIoHandler,
+    socket::{DisconnectReason, Socket},
+};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+mod fixture;
+
+use fixture::{create_polling_connection, create_server, create_ws_connection, send_req};
+
+#[derive(Debug, Clone)]
+struct MyHandler {
+    connect_tx: mpsc::Sender<Arc<Socket<()>>>,
+}
+
+impl EngineIoHandler for MyHandler {
+    type Data = ();
+
+    fn on_connect(&self, socket: Arc<Socket<()>>) {
+        self.connect_tx.try_send(socket).unwrap();
+    }
+    fn on_disconnect(&self, _socket: Arc<Socket<()>>, _reason: DisconnectReason) {}
+    fn on_message(&self, _msg: String, _socket: Arc<Socket<()>>) {}
+    fn on_binary(&self, _data: Vec<u8>, _socket: Arc<Socket<()>>) {}
+}
+
+#[tokio::test]
+pub async fn ws_latency() {
+    let (connect_tx, mut rx) = mpsc::channel(1);
+    create_server(MyHandler { connect_tx }, 12350).await;
+    let mut stream = create_ws_connection(12350).await;
+
+    let socket = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .expect("timeout waiting for the socket to connect")
+        .unwrap();
+    assert_eq!(socket.latency(), None);
+
+    // Skip the open packet
+    stream.next().await.unwrap().unwrap();
+    let ping = tokio::time::timeout(Duration::from_millis(500), stream.next())
+        .await
+        .expect("timeout waiting for ping")
+        .unwrap()
+        .unwrap();
+    assert_eq!(ping, Message::Text("2".into()));
+    stream.send(Message::Text("3".into())).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let latency = socket.latency().expect("latency should be measured");
+    assert!(latency < Duration::from_millis(200));
+}
+
+#[tokio::test]
+pub async fn polling_latency() {
+    let (connect_tx, mut rx) = mpsc::channel(1);
+    create_server(MyHandler { connect_tx }, 12351).await;
+    let sid = create_polling_connection(12351).await;
+
+    let socket = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .expect("timeout waiting for the socket to connect")
+        .unwrap();
+    assert_eq!(socket.latency(), None);
+
+    // The fixture strips the packet type, so a ping packet results in an empty body
+    let ping = send_req(
+        12351,
+        format!("transport=polling&sid={sid}"),
+        http::Method::GET,
+        None,
+    )
+    .await;
+    assert_eq!(ping, "");
+    send_req(
+        12351,
+        format!("transport=polling&sid={sid}"),
+        http::Method::POST,
+        Some("3".into()),
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let latency = socket.latency().expect("latency should be measured");
+    assert!(latency < Duration::from_millis(200));
+}
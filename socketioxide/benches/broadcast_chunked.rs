@@ -0,0 +1,138 @@
+//! Broadcasts a large payload to 10k sockets, most of them being slow consumers that
+//! didn't flush their previous packets, with [`emit`] and with [`broadcast_chunked`].
+//!
+//! The memory allocated by each broadcast is printed before its timings.
+//!
+//! [`emit`]: socketioxide::SocketIo::emit
+//! [`broadcast_chunked`]: socketioxide::operators::BroadcastOperators::broadcast_chunked
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use socketioxide::SocketIo;
+use tokio::runtime::Runtime;
+
+/// Counts the allocated bytes and keeps track of their peak
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const SOCKETS: usize = 10_000;
+const SLOW_SOCKETS: usize = 9_000;
+/// The number of packets buffered by each slow socket
+const SLOW_BUFFER_LEN: usize = 8;
+const PAYLOAD_SIZE: usize = 4 * 1024;
+const BATCH_SIZE: usize = 500;
+
+fn setup() -> SocketIo {
+    let (_, io) = SocketIo::new_svc();
+    io.ns("/", || {});
+    let sockets = io.connect_dummy_sockets("/", SOCKETS).unwrap();
+    for socket in &sockets[..SLOW_SOCKETS] {
+        for _ in 0..SLOW_BUFFER_LEN {
+            socket.emit("tick", ()).unwrap();
+        }
+    }
+    io
+}
+
+/// Disconnects the sockets so that they are freed along with their buffered packets
+fn teardown(io: SocketIo) {
+    io.disconnect().ok();
+}
+
+/// Broadcasts the payload once and returns the memory allocated by the broadcast that is still
+/// allocated at its end, i.e. buffered by the sockets, and the peak memory allocated during it.
+fn measure(rt: &Runtime, broadcast: &impl Fn(&Runtime, &SocketIo)) -> (usize, usize) {
+    let io = setup();
+    let base = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    broadcast(rt, &io);
+    let retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(base);
+    let peak = PEAK.load(Ordering::Relaxed) - base;
+    teardown(io);
+    (retained, peak)
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+    let payload = "a".repeat(PAYLOAD_SIZE);
+
+    let emit = |_: &Runtime, io: &SocketIo| {
+        io.emit("update", black_box(&payload)).unwrap();
+    };
+    let chunked = |rt: &Runtime, io: &SocketIo| {
+        let op = io.of("/").unwrap().broadcast_chunked(BATCH_SIZE);
+        rt.block_on(op.emit("update", black_box(&payload))).unwrap();
+    };
+    let chunked_skip_slow = |rt: &Runtime, io: &SocketIo| {
+        let op = io
+            .of("/")
+            .unwrap()
+            .broadcast_chunked(BATCH_SIZE)
+            .max_buffer_len(SLOW_BUFFER_LEN);
+        rt.block_on(op.emit("update", black_box(&payload))).unwrap();
+    };
+
+    let mut group = c.benchmark_group("Broadcast 4KiB to 10k sockets");
+    group.sample_size(10);
+    macro_rules! bench {
+        ($name:literal, $broadcast:expr) => {
+            let (retained, peak) = measure(&rt, &$broadcast);
+            println!(
+                "{}: {} KiB retained, {} KiB peak",
+                $name,
+                retained / 1024,
+                peak / 1024
+            );
+            // The sockets are set up and torn down outside of the measured time
+            group.bench_function($name, |b| {
+                b.iter_custom(|iters| {
+                    let mut elapsed = Duration::ZERO;
+                    for _ in 0..iters {
+                        let io = setup();
+                        let start = Instant::now();
+                        $broadcast(&rt, &io);
+                        elapsed += start.elapsed();
+                        teardown(io);
+                    }
+                    elapsed
+                })
+            });
+        };
+    }
+    bench!("emit", emit);
+    bench!("broadcast_chunked", chunked);
+    bench!("broadcast_chunked with max_buffer_len", chunked_skip_slow);
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
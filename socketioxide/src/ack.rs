@@ -4,11 +4,17 @@
 //!
 //! - [`AckStream`]: A [`Stream`]/[`Future`] of [`AckResponse`] received from the client.
 //! - [`AckResponse`]: An acknowledgement sent by the client.
+//!
+//! The [`BroadcastAckSummary`] of an [`AckStream`] tallies the acknowledgements it has yielded.
+//! The [`AckOrderedStream`] yields the acknowledgements of a broadcast with their arrival time.
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     fmt::Debug,
     pin::Pin,
+    sync::{Mutex, Weak},
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use engineioxide::sid::Sid;
@@ -19,9 +25,19 @@ use futures::{
 };
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use tokio::{sync::oneshot::Receiver, time::Timeout};
+use tokio::{
+    sync::oneshot::{Receiver, Sender},
+    time::Timeout,
+};
 
-use crate::{adapter::Adapter, errors::AckError, extract::SocketRef, packet::Packet, SocketError};
+use crate::{
+    adapter::Adapter,
+    client::SocketData,
+    errors::{AckError, SocketError},
+    extract::SocketRef,
+    packet::{Packet, PacketData},
+    socket::CompressCache,
+};
 
 /// An acknowledgement sent by the client.
 /// It contains the data sent by the client and the binary payloads if there are any.
@@ -36,6 +52,144 @@ pub struct AckResponse<T> {
 
 pub(crate) type AckResult<T = Value> = Result<AckResponse<T>, AckError<()>>;
 
+/// The pending acknowledgements of a socket, indexed by their ack id.
+pub(crate) type AckMap = Mutex<HashMap<i64, Sender<AckResult<Value>>>>;
+
+/// Removes a pending acknowledgement from the [`AckMap`] of its socket when dropped,
+/// so that the slot is freed as soon as nobody waits for the acknowledgement anymore
+/// (the acknowledgement was received, timed out or its future was dropped).
+///
+/// If the acknowledgement was still pending, the binary ack with the same id that may be
+/// partially received on the connection is also discarded, so that its payloads are not kept until
+/// the next packet.
+#[derive(Debug)]
+pub(crate) struct AckGuard {
+    map: Weak<AckMap>,
+    id: i64,
+    esocket: Weak<engineioxide::Socket<SocketData>>,
+    ns: Cow<'static, str>,
+}
+
+impl AckGuard {
+    pub fn new(
+        map: Weak<AckMap>,
+        id: i64,
+        esocket: Weak<engineioxide::Socket<SocketData>>,
+        ns: Cow<'static, str>,
+    ) -> Self {
+        Self {
+            map,
+            id,
+            esocket,
+            ns,
+        }
+    }
+}
+
+impl Drop for AckGuard {
+    fn drop(&mut self) {
+        let Some(map) = self.map.upgrade() else {
+            return;
+        };
+        if map.lock().unwrap().remove(&self.id).is_none() {
+            return;
+        }
+        if let Some(esocket) = self.esocket.upgrade() {
+            let mut partial = esocket.data.partial_bin_packet.lock().unwrap();
+            let is_partial_ack = matches!(
+                partial.as_ref(),
+                Some((Packet { inner: PacketData::BinaryAck(_, id), ns }, _)) if *id == self.id && *ns == self.ns
+            );
+            if is_partial_ack {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    "discarding partial binary ack {} of socket {}",
+                    self.id,
+                    esocket.id
+                );
+                partial.take();
+            }
+        }
+    }
+}
+
+/// A tally of the acknowledgements yielded by an [`AckStream`], returned by [`AckStream::summary`].
+///
+/// When the stream is terminated, it is the final summary of the broadcast:
+/// `total` is then equal to `responded + timed_out + disconnected + failed`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastAckSummary {
+    /// The number of sockets the packet was sent to
+    pub total: usize,
+    /// The number of sockets that responded, even if their response could not be deserialized
+    pub responded: usize,
+    /// The number of sockets that did not respond before the timeout
+    pub timed_out: usize,
+    /// The number of sockets that were disconnected before responding
+    pub disconnected: usize,
+    /// The number of sockets the packet could not be delivered to,
+    /// for example because their buffer was full or because of an adapter error
+    pub failed: usize,
+}
+
+impl BroadcastAckSummary {
+    fn count<T>(&mut self, ack: &AckResult<T>) {
+        match ack {
+            Err(AckError::Timeout) => self.timed_out += 1,
+            Err(AckError::Disconnected | AckError::Socket(SocketError::Closed(_))) => {
+                self.disconnected += 1
+            }
+            Err(AckError::Socket(SocketError::InternalChannelFull(_)) | AckError::Adapter(_)) => {
+                self.failed += 1
+            }
+            Ok(_) | Err(AckError::Serde(_)) => self.responded += 1,
+        }
+    }
+}
+
+impl AckResponse<Value> {
+    /// Interprets the data of the acknowledgement following the error-first callback convention
+    /// used by many socket.io clients: `callback(err, data)`.
+    ///
+    /// The data should be an array whose first element is the error.
+    /// If the error is `null` (or if the array is empty), the second element is deserialized as `D`
+    /// (a missing element is considered as `null`). Otherwise the first element is deserialized as `E`.
+    ///
+    /// A [`serde_json::Error`] is returned if the data is not an array or cannot be deserialized.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     // The client responds with `callback(null, 42)` or `callback("error message")`
+    ///     let ack = socket.emit_with_ack::<_, Value>("compute", "data").unwrap().await;
+    ///     match ack.unwrap().into_result::<u32, String>() {
+    ///         Ok(Ok(result)) => println!("result: {result}"),
+    ///         Ok(Err(err)) => println!("client error: {err}"),
+    ///         Err(err) => println!("invalid ack: {err}"),
+    ///     }
+    /// });
+    /// ```
+    pub fn into_result<D: DeserializeOwned, E: DeserializeOwned>(
+        self,
+    ) -> Result<Result<D, E>, serde_json::Error> {
+        let mut args = match self.data {
+            Value::Array(args) => args.into_iter(),
+            _ => {
+                return Err(serde::de::Error::custom(
+                    "expected an error-first array of arguments",
+                ))
+            }
+        };
+        match args.next().unwrap_or(Value::Null) {
+            Value::Null => serde_json::from_value(args.next().unwrap_or(Value::Null)).map(Ok),
+            err => serde_json::from_value(err).map(Err),
+        }
+    }
+}
+
 pin_project_lite::pin_project! {
     /// A [`Future`] of [`AckResponse`] received from the client with its corresponding [`Sid`].
     /// It is used internally by [`AckStream`] and **should not** be used directly.
@@ -43,6 +197,7 @@ pin_project_lite::pin_project! {
         id: Sid,
         #[pin]
         result: Timeout<Receiver<AckResult<T>>>,
+        guard: Option<AckGuard>,
     }
 }
 
@@ -55,9 +210,12 @@ impl<T> Future for AckResultWithId<T> {
             Poll::Ready(v) => {
                 let v = match v {
                     Ok(Ok(Ok(v))) => Ok(v),
+                    // The packet could not be sent or the response could not be deserialized
                     Ok(Ok(Err(e))) => Err(e),
-                    Ok(Err(_)) => Err(AckError::Socket(SocketError::Closed(()))),
-                    Err(_) => Err(AckError::Timeout),
+                    // The ack sender was dropped because the socket was disconnected
+                    Ok(Err(_)) => Err(AckError::Disconnected),
+                    // The timeout elapsed before the client responded
+                    Err(elapsed) => Err(elapsed.into()),
                 };
                 Poll::Ready((*project.id, v))
             }
@@ -80,7 +238,11 @@ pin_project_lite::pin_project! {
     ///
     /// If the client didn't respond before the timeout, the [`AckStream`] will yield
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `T`,
-    /// an [`AckError::Serde`] will be yielded.
+    /// an [`AckError::Serde`] will be yielded. If the socket is disconnected before
+    /// the client responds, an [`AckError::Disconnected`] will be yielded.
+    ///
+    /// Dropping the [`AckStream`] cancels the wait: the pending acknowledgements are removed
+    /// from their sockets and a late response from the client is ignored.
     ///
     /// An [`AckStream`] can be created from:
     /// * The [`SocketRef::emit_with_ack`] method, in this case there will be only one [`AckResponse`].
@@ -115,6 +277,7 @@ pin_project_lite::pin_project! {
     pub struct AckStream<T> {
         #[pin]
         inner: AckInnerStream,
+        summary: BroadcastAckSummary,
         _marker: std::marker::PhantomData<T>,
     }
 }
@@ -128,6 +291,7 @@ pin_project_lite::pin_project! {
         Stream {
             #[pin]
             rxs: FuturesUnordered<AckResultWithId<Value>>,
+            count: usize,
         },
 
         Fut {
@@ -147,6 +311,10 @@ impl AckInnerStream {
     ///
     /// The [`AckInnerStream`] will wait for the default timeout specified in the config
     /// (5s by default) if no custom timeout is specified.
+    ///
+    /// Each socket has its own timeout, starting when the packet is sent to it.
+    /// The acknowledgements are yielded as soon as they arrive and a slow socket only
+    /// yields an [`AckError::Timeout`] for itself, without delaying the others.
     pub fn broadcast<A: Adapter>(
         packet: Packet<'static>,
         sockets: Vec<SocketRef<A>>,
@@ -154,29 +322,62 @@ impl AckInnerStream {
     ) -> Self {
         let rxs = FuturesUnordered::new();
 
+        let count = sockets.len();
         if sockets.is_empty() {
-            return AckInnerStream::Stream { rxs };
+            return AckInnerStream::Stream { rxs, count };
         }
 
         let duration = duration.unwrap_or_else(|| sockets.first().unwrap().config.ack_timeout);
+        let mut cache = CompressCache::default();
         for socket in sockets {
-            let rx = socket.send_with_ack(packet.clone());
+            let (rx, guard) = socket.send_with_ack(packet.clone(), Some(&mut cache));
             rxs.push(AckResultWithId {
                 result: tokio::time::timeout(duration, rx),
                 id: socket.id,
+                guard: Some(guard),
             });
         }
-        AckInnerStream::Stream { rxs }
+        AckInnerStream::Stream { rxs, count }
+    }
+
+    /// Returns the number of sockets the packet was sent to.
+    /// It is also the total number of items that the stream yields.
+    pub fn socket_count(&self) -> usize {
+        match self {
+            AckInnerStream::Stream { count, .. } => *count,
+            AckInnerStream::Fut { .. } => 1,
+        }
     }
 
     /// Creates a new [`AckInnerStream`] from a [`oneshot::Receiver`](tokio) corresponding to the acknowledgement
     /// of a single socket.
     pub fn send(rx: Receiver<AckResult<Value>>, duration: Duration, id: Sid) -> Self {
+        Self::fut(rx, duration, id, None)
+    }
+
+    /// Same as [`AckInnerStream::send`] but the pending acknowledgement is removed from the socket
+    /// when the stream is dropped.
+    pub(crate) fn send_guarded(
+        rx: Receiver<AckResult<Value>>,
+        duration: Duration,
+        id: Sid,
+        guard: AckGuard,
+    ) -> Self {
+        Self::fut(rx, duration, id, Some(guard))
+    }
+
+    fn fut(
+        rx: Receiver<AckResult<Value>>,
+        duration: Duration,
+        id: Sid,
+        guard: Option<AckGuard>,
+    ) -> Self {
         AckInnerStream::Fut {
             polled: false,
             rx: AckResultWithId {
                 id,
                 result: tokio::time::timeout(duration, rx),
+                guard,
             },
         }
     }
@@ -190,7 +391,7 @@ impl Stream for AckInnerStream {
 
         match self.project() {
             Fut { polled, .. } if *polled => Poll::Ready(None),
-            Stream { rxs } => rxs.poll_next(cx),
+            Stream { rxs, .. } => rxs.poll_next(cx),
             Fut { rx, polled } => match rx.poll(cx) {
                 Poll::Ready(val) => {
                     *polled = true;
@@ -246,15 +447,61 @@ impl FusedFuture for AckInnerStream {
 
 // ==== impl AckStream ====
 
+impl<T> AckStream<T> {
+    /// Returns the number of sockets the packet was sent to.
+    /// This is the number of [`AckResponse`] (or errors) that the stream will yield in total.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let stream = socket.broadcast().emit_with_ack::<String>("test", "test").unwrap();
+    ///     println!("waiting for {} acknowledgements", stream.socket_count());
+    /// });
+    /// ```
+    #[inline]
+    pub fn socket_count(&self) -> usize {
+        self.inner.socket_count()
+    }
+
+    /// Returns the tally of the acknowledgements yielded so far.
+    /// Once the stream is terminated, it is the final summary of the broadcast.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// # use futures::StreamExt;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     let stream = socket.to("workers").emit_with_ack::<String>("run", "cmd").unwrap();
+    ///     futures::pin_mut!(stream);
+    ///     while let Some((id, ack)) = stream.next().await {
+    ///         println!("{id}: {ack:?}");
+    ///     }
+    ///     let summary = stream.summary();
+    ///     println!("{}/{} workers responded", summary.responded, summary.total);
+    /// });
+    /// ```
+    #[inline]
+    pub fn summary(&self) -> BroadcastAckSummary {
+        self.summary
+    }
+}
+
 impl<T: DeserializeOwned> Stream for AckStream<T> {
     type Item = (Sid, AckResult<T>);
 
     #[inline]
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.project()
-            .inner
-            .poll_next(cx)
-            .map(|v| v.map(|(s, v)| (s, map_ack_response(v))))
+        let project = self.project();
+        project.inner.poll_next(cx).map(|v| {
+            v.map(|(s, v)| {
+                let v = map_ack_response(v);
+                project.summary.count(&v);
+                (s, v)
+            })
+        })
     }
 
     #[inline(always)]
@@ -275,7 +522,12 @@ impl<T: DeserializeOwned> Future for AckStream<T> {
 
     #[inline]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.project().inner.poll(cx).map(map_ack_response)
+        let project = self.project();
+        project.inner.poll(cx).map(|v| {
+            let v = map_ack_response(v);
+            project.summary.count(&v);
+            v
+        })
     }
 }
 
@@ -289,12 +541,71 @@ impl<T: DeserializeOwned> FusedFuture for AckStream<T> {
 impl<T> From<AckInnerStream> for AckStream<T> {
     fn from(inner: AckInnerStream) -> Self {
         Self {
+            summary: BroadcastAckSummary {
+                total: inner.socket_count(),
+                ..Default::default()
+            },
             inner,
             _marker: std::marker::PhantomData,
         }
     }
 }
 
+pin_project_lite::pin_project! {
+    /// A [`Stream`] of all the acknowledgements of a broadcast, in their arrival order,
+    /// with the [`Sid`] of the socket that sent it and the [`Instant`] it was received at.
+    ///
+    /// It is created with [`BroadcastOperators::emit_with_ack_ordered`]. The arrival time is taken
+    /// when the stream observes the acknowledgement, so the stream should be polled continuously
+    /// (e.g. with a `while let` loop) for the times and the order to be accurate.
+    ///
+    /// The errors are yielded in the same way as with the [`AckStream`], a timed out socket
+    /// yields an [`AckError::Timeout`] when its timeout expires.
+    ///
+    /// Dropping the stream cancels the wait for the remaining acknowledgements.
+    ///
+    /// [`BroadcastOperators::emit_with_ack_ordered`]: crate::operators::BroadcastOperators::emit_with_ack_ordered
+    #[must_use = "streams do nothing unless you poll them"]
+    pub struct AckOrderedStream<T> {
+        #[pin]
+        inner: AckInnerStream,
+        _marker: std::marker::PhantomData<fn() -> T>,
+    }
+}
+
+impl<T> AckOrderedStream<T> {
+    pub(crate) fn new(inner: AckInnerStream) -> Self {
+        Self {
+            inner,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the number of sockets the packet was sent to.
+    /// This is the number of items that the stream will yield in total.
+    #[inline]
+    pub fn socket_count(&self) -> usize {
+        self.inner.socket_count()
+    }
+}
+
+impl<T: DeserializeOwned> Stream for AckOrderedStream<T> {
+    type Item = (Sid, Instant, AckResult<T>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_next(cx).map(|v| {
+            v.map(|(sid, ack)| {
+                let at = tokio::time::Instant::now().into_std();
+                (sid, at, map_ack_response(ack))
+            })
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
 fn map_ack_response<T: DeserializeOwned>(ack: AckResult<Value>) -> AckResult<T> {
     ack.and_then(|v| {
         serde_json::from_value(v.data)
@@ -313,7 +624,7 @@ mod test {
     use engineioxide::sid::Sid;
     use futures::StreamExt;
 
-    use crate::{adapter::LocalAdapter, ns::Namespace, socket::Socket};
+    use crate::{adapter::LocalAdapter, ns::Namespace, socket::Socket, SocketError};
 
     use super::*;
 
@@ -324,6 +635,28 @@ mod test {
         socket.into()
     }
 
+    #[test]
+    fn ack_into_result() {
+        fn ack(data: Value) -> AckResponse<Value> {
+            AckResponse {
+                data,
+                binary: vec![],
+            }
+        }
+        let res = ack(serde_json::json!([null, 42])).into_result::<u32, String>();
+        assert_eq!(res.unwrap(), Ok(42));
+        let res = ack(serde_json::json!(["error"])).into_result::<u32, String>();
+        assert_eq!(res.unwrap(), Err("error".to_string()));
+        let res = ack(serde_json::json!([])).into_result::<Option<u32>, String>();
+        assert_eq!(res.unwrap(), Ok(None));
+        assert!(ack(serde_json::json!(42))
+            .into_result::<u32, String>()
+            .is_err());
+        assert!(ack(serde_json::json!([null, "foo"]))
+            .into_result::<u32, String>()
+            .is_err());
+    }
+
     #[tokio::test]
     async fn broadcast_ack() {
         let socket = create_socket();
@@ -332,6 +665,7 @@ mod test {
         packet.inner.set_ack_id(1);
         let socks = vec![socket.clone().into(), socket2.clone().into()];
         let stream: AckStream<String> = AckInnerStream::broadcast(packet, socks, None).into();
+        assert_eq!(stream.socket_count(), 2);
 
         let res_packet = Packet::ack("test", "test".into(), 1);
         socket.recv(res_packet.inner.clone()).unwrap();
@@ -466,9 +800,38 @@ mod test {
         socket2.disconnect().unwrap();
         let (id, ack) = stream.next().await.unwrap();
         assert_eq!(id, sid);
-        assert!(matches!(ack, Err(AckError::Socket(SocketError::Closed(_)))));
+        assert!(matches!(ack, Err(AckError::Disconnected)));
         assert!(stream.next().await.is_none());
     }
+    #[tokio::test]
+    async fn broadcast_ack_error_variants() {
+        let [bad_data, timeout, closed, disconnected] = [(); 4].map(|_| create_socket());
+        closed.clone().disconnect().unwrap();
+        let mut packet = Packet::event("/", "test", "test".into());
+        packet.inner.set_ack_id(1);
+        let socks = [&bad_data, &timeout, &closed, &disconnected]
+            .into_iter()
+            .map(|s| s.clone().into())
+            .collect();
+        let stream: AckStream<String> =
+            AckInnerStream::broadcast(packet, socks, Some(Duration::from_millis(10))).into();
+
+        bad_data
+            .clone()
+            .recv(Packet::ack("test", 42.into(), 1).inner)
+            .unwrap();
+        disconnected.clone().disconnect().unwrap();
+
+        let res: HashMap<_, _> = stream.collect::<Vec<_>>().await.into_iter().collect();
+        assert!(matches!(res[&bad_data.id], Err(AckError::Serde(_))));
+        assert!(matches!(res[&timeout.id], Err(AckError::Timeout)));
+        assert!(matches!(
+            res[&closed.id],
+            Err(AckError::Socket(SocketError::Closed(())))
+        ));
+        assert!(matches!(res[&disconnected.id], Err(AckError::Disconnected)));
+    }
+
     #[tokio::test]
     async fn ack_stream_with_closed_socket() {
         let (tx, rx) = tokio::sync::oneshot::channel();
@@ -481,7 +844,7 @@ mod test {
 
         assert!(matches!(
             stream.next().await.unwrap().1.unwrap_err(),
-            AckError::Socket(SocketError::Closed(_))
+            AckError::Disconnected
         ));
     }
 
@@ -493,10 +856,7 @@ mod test {
             AckInnerStream::send(rx, Duration::from_secs(1), sid).into();
         drop(tx);
 
-        assert!(matches!(
-            stream.await.unwrap_err(),
-            AckError::Socket(SocketError::Closed(_))
-        ));
+        assert!(matches!(stream.await.unwrap_err(), AckError::Disconnected));
     }
 
     #[tokio::test]
@@ -523,6 +883,114 @@ mod test {
         assert!(stream.next().await.is_none());
     }
 
+    #[tokio::test]
+    async fn broadcast_ack_staggered_responses() {
+        let socket = create_socket();
+        let socket2 = create_socket();
+        let socket3 = create_socket();
+        let mut packet = Packet::event("/", "test", "test".into());
+        packet.inner.set_ack_id(1);
+        let socks = vec![
+            socket.clone().into(),
+            socket2.clone().into(),
+            socket3.clone().into(),
+        ];
+        let stream: AckStream<String> =
+            AckInnerStream::broadcast(packet, socks, Some(Duration::from_millis(50))).into();
+
+        let s3 = socket3.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            s3.recv(Packet::ack("test", "test".into(), 1).inner)
+                .unwrap();
+        });
+        socket2
+            .clone()
+            .recv(Packet::ack("test", "test".into(), 1).inner)
+            .unwrap();
+
+        futures::pin_mut!(stream);
+
+        let (id, res) = stream.next().await.unwrap();
+        assert_eq!(id, socket2.id);
+        assert!(res.is_ok());
+        let (id, res) = stream.next().await.unwrap();
+        assert_eq!(id, socket3.id);
+        assert!(res.is_ok());
+        let (id, res) = stream.next().await.unwrap();
+        assert_eq!(id, socket.id);
+        assert!(matches!(res.unwrap_err(), AckError::Timeout));
+        assert!(stream.next().await.is_none());
+
+        let summary = stream.summary();
+        assert_eq!(
+            summary,
+            BroadcastAckSummary {
+                total: 3,
+                responded: 2,
+                timed_out: 1,
+                disconnected: 0,
+                failed: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn broadcast_ack_summary_count() {
+        let mut summary = BroadcastAckSummary::default();
+        let acks: [AckResult<()>; 6] = [
+            Ok(AckResponse {
+                data: (),
+                binary: vec![],
+            }),
+            Err(serde_json::from_str::<()>("{").unwrap_err().into()),
+            Err(AckError::Timeout),
+            Err(AckError::Disconnected),
+            Err(AckError::Socket(SocketError::Closed(()))),
+            Err(AckError::Socket(SocketError::InternalChannelFull(()))),
+        ];
+        acks.iter().for_each(|ack| summary.count(ack));
+        assert_eq!(
+            summary,
+            BroadcastAckSummary {
+                total: 0,
+                responded: 2,
+                timed_out: 1,
+                disconnected: 2,
+                failed: 1,
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn broadcast_ack_ordered() {
+        let sockets = [create_socket(), create_socket(), create_socket()];
+        let mut packet = Packet::event("/", "test", "test".into());
+        packet.inner.set_ack_id(1);
+        let socks = sockets.iter().map(|s| s.clone().into()).collect();
+        let inner = AckInnerStream::broadcast(packet, socks, Some(Duration::from_millis(100)));
+        let stream = AckOrderedStream::<u64>::new(inner);
+        assert_eq!(stream.socket_count(), 3);
+
+        let start = tokio::time::Instant::now().into_std();
+        for (i, delay) in [(0, 30), (1, 10), (2, 20)] {
+            let socket = sockets[i].clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                socket
+                    .recv(Packet::ack("test", delay.into(), 1).inner)
+                    .unwrap();
+            });
+        }
+        let acks: Vec<_> = stream.collect().await;
+        let ids: Vec<_> = acks.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(ids, [sockets[1].id, sockets[2].id, sockets[0].id]);
+        for (_, at, ack) in acks {
+            let delay = ack.unwrap().data;
+            assert_eq!(at.duration_since(start), Duration::from_millis(delay));
+        }
+    }
+
     #[tokio::test]
     async fn ack_stream_with_timeout() {
         let (_tx, rx) = tokio::sync::oneshot::channel();
@@ -12,6 +12,7 @@ use std::{
 };
 
 use engineioxide::sid::Sid;
+use futures::future::BoxFuture;
 
 use crate::{
     ack::AckInnerStream,
@@ -20,23 +21,86 @@ use crate::{
     ns::Namespace,
     operators::RoomParam,
     packet::Packet,
+    socket::CompressCache,
     DisconnectError,
 };
 
 /// A room identifier
 pub type Room = Cow<'static, str>;
 
+/// A glob pattern used to select rooms by name when broadcasting.
+///
+/// The only special character is `*`, which matches any sequence of characters (including none).
+/// Every other character is matched literally and the whole room name must match the pattern:
+/// * `admin:*` matches every room starting with `admin:` (e.g. `admin:1`, `admin:` but not `my-admin:1`)
+/// * `*:lobby` matches every room ending with `:lobby`
+/// * `game:*:red` matches `game:1:red` or `game:abc:red`
+/// * `room1` only matches the `room1` room
+///
+/// Patterns are expanded by the [`Adapter`] against the known room names at broadcast time.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RoomPattern(Cow<'static, str>);
+
+impl RoomPattern {
+    /// Creates a new [`RoomPattern`] from a glob string.
+    pub fn new(pattern: impl Into<Cow<'static, str>>) -> Self {
+        Self(pattern.into())
+    }
+
+    /// Returns true if the given room name matches this pattern.
+    pub fn matches(&self, room: &str) -> bool {
+        let mut parts = self.0.split('*');
+        // split always yields at least one item
+        let first = parts.next().unwrap();
+        let Some(mut rest) = room.strip_prefix(first) else {
+            return false;
+        };
+        let mut parts: Vec<&str> = parts.collect();
+        let Some(last) = parts.pop() else {
+            // No wildcard, it is an exact match
+            return rest.is_empty();
+        };
+        for part in parts {
+            match rest.find(part) {
+                Some(i) => rest = &rest[i + part.len()..],
+                None => return false,
+            }
+        }
+        rest.ends_with(last)
+    }
+
+    /// Returns the pattern as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<T: Into<Cow<'static, str>>> From<T> for RoomPattern {
+    fn from(pattern: T) -> Self {
+        Self::new(pattern)
+    }
+}
+
 /// Flags that can be used to modify the behavior of the broadcast methods.
+///
+/// New flags may be added in the future, adapters matching on them should handle unknown flags.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BroadcastFlags {
     /// Broadcast only to the current server
     Local,
     /// Broadcast to all clients except the sender
     Broadcast,
+    /// Broadcast only to the other servers, skipping the sockets connected on the current server
+    Remote,
 }
 
 /// Options that can be used to modify the behavior of the broadcast methods.
+///
+/// New options may be added in the future, it can be created with [`BroadcastOptions::new`]
+/// and configured with the `with_*` methods.
 #[derive(Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct BroadcastOptions {
     /// The flags to apply to the broadcast.
     pub flags: HashSet<BroadcastFlags>,
@@ -44,9 +108,97 @@ pub struct BroadcastOptions {
     pub rooms: HashSet<Room>,
     /// The rooms to exclude from the broadcast.
     pub except: HashSet<Room>,
+    /// The room patterns to broadcast to. They are expanded against the known rooms at broadcast time.
+    pub room_patterns: HashSet<RoomPattern>,
+    /// The room patterns to exclude from the broadcast. They are expanded against the known rooms at broadcast time.
+    pub except_patterns: HashSet<RoomPattern>,
+    /// The ids of the sockets to broadcast to, in addition to the sockets of the rooms.
+    pub sids: HashSet<Sid>,
+    /// The ids of the sockets to exclude from the broadcast, in addition to the sockets of the excluded rooms.
+    pub except_sids: HashSet<Sid>,
     /// The socket id of the sender.
     pub sid: Option<Sid>,
 }
+
+impl BroadcastOptions {
+    /// Creates new options for a broadcast sent by the socket `sid`, or by the server if `None`.
+    pub fn new(sid: Option<Sid>) -> Self {
+        Self {
+            sid,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a flag to the options.
+    pub fn with_flag(mut self, flag: BroadcastFlags) -> Self {
+        self.flags.insert(flag);
+        self
+    }
+
+    /// Adds rooms to broadcast to.
+    pub fn with_rooms(mut self, rooms: impl RoomParam) -> Self {
+        self.rooms.extend(rooms.into_room_iter());
+        self
+    }
+
+    /// Adds the ids of sockets to broadcast to.
+    pub fn with_sids(mut self, sids: impl IntoIterator<Item = Sid>) -> Self {
+        self.sids.extend(sids);
+        self
+    }
+
+    /// Adds the ids of sockets to exclude from the broadcast.
+    pub fn with_except_sids(mut self, sids: impl IntoIterator<Item = Sid>) -> Self {
+        self.except_sids.extend(sids);
+        self
+    }
+
+    /// Adds rooms to exclude from the broadcast.
+    pub fn with_except(mut self, rooms: impl RoomParam) -> Self {
+        self.except.extend(rooms.into_room_iter());
+        self
+    }
+
+    /// Adds a room pattern to broadcast to.
+    pub fn with_room_pattern(mut self, pattern: impl Into<RoomPattern>) -> Self {
+        self.room_patterns.insert(pattern.into());
+        self
+    }
+
+    /// Adds a room pattern to exclude from the broadcast.
+    pub fn with_except_pattern(mut self, pattern: impl Into<RoomPattern>) -> Self {
+        self.except_patterns.insert(pattern.into());
+        self
+    }
+
+    /// Returns true if a socket with the given id and rooms is targeted by these options.
+    ///
+    /// It follows the same rules as the [`LocalAdapter`] when selecting sockets.
+    pub(crate) fn targets(&self, sid: Sid, rooms: &[Room]) -> bool {
+        let in_rooms = |set: &HashSet<Room>, patterns: &HashSet<RoomPattern>| {
+            rooms
+                .iter()
+                .any(|room| set.contains(room) || patterns.iter().any(|p| p.matches(room)))
+        };
+        let broadcast = self.flags.contains(&BroadcastFlags::Broadcast);
+        // The sockets of the current server are never targeted by a remote broadcast
+        let remote = self.flags.contains(&BroadcastFlags::Remote);
+
+        // A socket excluded by any rule is not targeted
+        if remote
+            || self.except_sids.contains(&sid)
+            || in_rooms(&self.except, &self.except_patterns)
+            || (broadcast && self.sid == Some(sid))
+        {
+            false
+        } else if !self.rooms.is_empty() || !self.room_patterns.is_empty() || !self.sids.is_empty()
+        {
+            self.sids.contains(&sid) || in_rooms(&self.rooms, &self.room_patterns)
+        } else {
+            broadcast || self.sid == Some(sid)
+        }
+    }
+}
 //TODO: Make an AsyncAdapter trait
 /// An adapter is responsible for managing the state of the server.
 /// This adapter can be implemented to share the state between multiple servers.
@@ -62,23 +214,70 @@ pub trait Adapter: std::fmt::Debug + Send + Sync + 'static {
 
     /// Initializes the adapter.
     fn init(&self) -> Result<(), Self::Error>;
-    /// Closes the adapter.
-    fn close(&self) -> Result<(), Self::Error>;
+    /// Closes the adapter and returns a future that resolves once its resources are released,
+    /// e.g. to unsubscribe from a pub/sub channel and close the connections to a remote store.
+    ///
+    /// It is called when the server is closed with [`SocketIo::close`](crate::SocketIo::close),
+    /// for each namespace, after all the sockets of the namespace have been disconnected
+    /// and their underlying connections closed. The adapter can therefore still handle
+    /// the room removals of the disconnected sockets before closing.
+    /// The error returned is only logged.
+    fn close(&self) -> BoxFuture<'static, Result<(), Self::Error>>;
 
     /// Returns the number of servers.
     fn server_count(&self) -> Result<u16, Self::Error>;
 
     /// Adds the socket to all the rooms.
     fn add_all(&self, sid: Sid, rooms: impl RoomParam) -> Result<(), Self::Error>;
+    /// Adds the socket to all the rooms and returns a future that resolves
+    /// once the membership is confirmed by all the servers of the cluster.
+    ///
+    /// The default implementation calls [`Adapter::add_all`] and resolves immediately,
+    /// which is correct for the adapters whose state is not shared between servers.
+    fn add_all_confirmed(
+        &self,
+        sid: Sid,
+        rooms: impl RoomParam,
+    ) -> BoxFuture<'static, Result<(), Self::Error>> {
+        Box::pin(futures::future::ready(self.add_all(sid, rooms)))
+    }
     /// Removes the socket from the rooms.
     fn del(&self, sid: Sid, rooms: impl RoomParam) -> Result<(), Self::Error>;
     /// Removes the socket from all the rooms.
     fn del_all(&self, sid: Sid) -> Result<(), Self::Error>;
+    /// Replaces the rooms of the socket with the given ones.
+    /// The room named after the socket id is always kept.
+    ///
+    /// The default implementation leaves the rooms that are not in the given set with [`Adapter::del`]
+    /// and joins the new ones with [`Adapter::add_all`].
+    fn sync_rooms(&self, sid: Sid, rooms: Vec<Room>) -> Result<(), Self::Error> {
+        let current = self.socket_rooms(sid)?;
+        let removed: Vec<_> = current
+            .iter()
+            .filter(|room| *room != sid.as_str() && !rooms.contains(room))
+            .cloned()
+            .collect();
+        let added: Vec<_> = rooms
+            .into_iter()
+            .filter(|room| !current.contains(room))
+            .collect();
+        self.del(sid, removed)?;
+        self.add_all(sid, added)
+    }
 
     /// Broadcasts the packet to the sockets that match the [`BroadcastOptions`].
     fn broadcast(&self, packet: Packet<'_>, opts: BroadcastOptions) -> Result<(), BroadcastError>;
 
     /// Broadcasts the packet to the sockets that match the [`BroadcastOptions`] and return a stream of ack responses.
+    ///
+    /// The returned stream must respect the following contract, as the [`LocalAdapter`] does:
+    /// * It yields exactly one item for each selected socket: either the [`AckResponse`](crate::ack::AckResponse)
+    ///   of the client, or an error if the socket could not be reached or if the `timeout`
+    ///   (or the [`ack_timeout`](crate::SocketIoConfig::ack_timeout) if `None`) has elapsed.
+    /// * It ends once every selected socket has yielded an item.
+    /// * Its [`socket_count`](AckInnerStream::socket_count) is the number of selected sockets.
+    ///
+    /// [`AckInnerStream::broadcast`] can be used to build a stream respecting this contract from the selected sockets.
     fn broadcast_with_ack(
         &self,
         packet: Packet<'static>,
@@ -110,6 +309,47 @@ pub trait Adapter: std::fmt::Debug + Send + Sync + 'static {
     /// Returns all the rooms for this adapter.
     fn rooms(&self) -> Result<Vec<Room>, Self::Error>;
 
+    /// Returns a snapshot of the number of sockets in each room of this adapter.
+    ///
+    /// The default implementation calls [`Adapter::sockets`] for each room returned by [`Adapter::rooms`],
+    /// so the snapshot may not be consistent if the rooms are modified concurrently.
+    fn room_sizes(&self) -> Result<HashMap<Room, usize>, Self::Error> {
+        let mut sizes = HashMap::new();
+        for room in self.rooms()? {
+            let len = self.sockets(room.clone())?.len();
+            sizes.insert(room, len);
+        }
+        Ok(sizes)
+    }
+
+    /// Returns true if the room exists and has at least one socket.
+    ///
+    /// The default implementation calls [`Adapter::sockets`] for the room.
+    fn has_room(&self, room: &str) -> Result<bool, Self::Error> {
+        Ok(!self.sockets(room.to_string())?.is_empty())
+    }
+
+    /// Returns true if the socket is in the room.
+    ///
+    /// The default implementation calls [`Adapter::socket_rooms`] for the socket.
+    fn is_in_room(&self, sid: Sid, room: &str) -> Result<bool, Self::Error> {
+        Ok(self.socket_rooms(sid)?.iter().any(|r| r == room))
+    }
+
+    /// Returns a future that resolves with the ids of all the sockets in the room,
+    /// across all the servers of the cluster.
+    ///
+    /// The default implementation calls [`Adapter::sockets`] and resolves immediately,
+    /// which is correct for the adapters whose state is not shared between servers.
+    /// Adapters sharing their state should aggregate the answers of every server
+    /// (e.g. with a request/response over pub/sub).
+    fn room_sids(&self, room: &str) -> BoxFuture<'static, Result<HashSet<Sid>, Self::Error>> {
+        let sids = self
+            .sockets(room.to_string())
+            .map(|sids| sids.into_iter().collect());
+        Box::pin(futures::future::ready(sids))
+    }
+
     //TODO: implement
     // fn server_side_emit(&self, packet: Packet, opts: BroadcastOptions) -> Result<u64, Error>;
     // fn persist_session(&self, sid: i64);
@@ -143,13 +383,13 @@ impl Adapter for LocalAdapter {
         Ok(())
     }
 
-    fn close(&self) -> Result<(), Infallible> {
+    fn close(&self) -> BoxFuture<'static, Result<(), Infallible>> {
         #[cfg(feature = "tracing")]
         tracing::debug!("closing local adapter: {}", self.ns.upgrade().unwrap().path);
         let mut rooms = self.rooms.write().unwrap();
         rooms.clear();
         rooms.shrink_to_fit();
-        Ok(())
+        Box::pin(futures::future::ready(Ok(())))
     }
 
     fn server_count(&self) -> Result<u16, Infallible> {
@@ -179,6 +419,24 @@ impl Adapter for LocalAdapter {
         for room in rooms_map.values_mut() {
             room.remove(&sid);
         }
+        // The room named after the socket id is not kept once the socket has left it
+        if rooms_map.get(sid.as_str()).map_or(false, HashSet::is_empty) {
+            rooms_map.remove(sid.as_str());
+        }
+        Ok(())
+    }
+
+    fn sync_rooms(&self, sid: Sid, rooms: Vec<Room>) -> Result<(), Infallible> {
+        let rooms: HashSet<Room> = rooms.into_iter().collect();
+        let mut rooms_map = self.rooms.write().unwrap();
+        for (room, sockets) in rooms_map.iter_mut() {
+            if room != sid.as_str() && !rooms.contains(room) {
+                sockets.remove(&sid);
+            }
+        }
+        for room in rooms {
+            rooms_map.entry(room).or_default().insert(sid);
+        }
         Ok(())
     }
 
@@ -187,9 +445,10 @@ impl Adapter for LocalAdapter {
 
         #[cfg(feature = "tracing")]
         tracing::debug!("broadcasting packet to {} sockets", sockets.len());
+        let mut cache = CompressCache::default();
         let errors: Vec<_> = sockets
             .into_iter()
-            .filter_map(|socket| socket.send(packet.clone()).err())
+            .filter_map(|socket| socket.send_broadcast(packet.clone(), &mut cache).err())
             .collect();
         if errors.is_empty() {
             Ok(())
@@ -241,7 +500,7 @@ impl Adapter for LocalAdapter {
     fn add_sockets(&self, opts: BroadcastOptions, rooms: impl RoomParam) -> Result<(), Infallible> {
         let rooms: Vec<Room> = rooms.into_room_iter().collect();
         for socket in self.apply_opts(opts) {
-            self.add_all(socket.id, rooms.clone()).unwrap();
+            self.add_all(socket.id, rooms.clone())?;
         }
         Ok(())
     }
@@ -249,7 +508,7 @@ impl Adapter for LocalAdapter {
     fn del_sockets(&self, opts: BroadcastOptions, rooms: impl RoomParam) -> Result<(), Infallible> {
         let rooms: Vec<Room> = rooms.into_room_iter().collect();
         for socket in self.apply_opts(opts) {
-            self.del(socket.id, rooms.clone()).unwrap();
+            self.del(socket.id, rooms.clone())?;
         }
         Ok(())
     }
@@ -273,21 +532,53 @@ impl Adapter for LocalAdapter {
     fn rooms(&self) -> Result<Vec<Room>, Self::Error> {
         Ok(self.rooms.read().unwrap().keys().cloned().collect())
     }
+
+    fn room_sizes(&self) -> Result<HashMap<Room, usize>, Self::Error> {
+        let rooms = self.rooms.read().unwrap();
+        Ok(rooms
+            .iter()
+            .map(|(room, sockets)| (room.clone(), sockets.len()))
+            .collect())
+    }
+
+    fn has_room(&self, room: &str) -> Result<bool, Self::Error> {
+        let rooms = self.rooms.read().unwrap();
+        Ok(rooms.get(room).map_or(false, |sockets| !sockets.is_empty()))
+    }
+
+    fn is_in_room(&self, sid: Sid, room: &str) -> Result<bool, Self::Error> {
+        let rooms = self.rooms.read().unwrap();
+        Ok(rooms
+            .get(room)
+            .map_or(false, |sockets| sockets.contains(&sid)))
+    }
 }
 
 impl LocalAdapter {
     /// Applies the given `opts` and return the sockets that match.
     fn apply_opts(&self, opts: BroadcastOptions) -> Vec<SocketRef<Self>> {
-        let rooms = opts.rooms;
+        // There is no other server with the in-memory adapter
+        if opts.flags.contains(&BroadcastFlags::Remote) {
+            return vec![];
+        }
+        let has_rooms =
+            !opts.rooms.is_empty() || !opts.room_patterns.is_empty() || !opts.sids.is_empty();
+        let rooms = self.expand_patterns(opts.rooms, &opts.room_patterns);
+        let except = self.expand_patterns(opts.except, &opts.except_patterns);
 
-        let except = self.get_except_sids(&opts.except);
+        let mut except = self.get_except_sids(&except);
+        except.extend(opts.except_sids);
         let ns = self.ns.upgrade().unwrap();
-        if !rooms.is_empty() {
+        if has_rooms {
             let rooms_map = self.rooms.read().unwrap();
-            rooms
+            // A socket in several of the rooms is only selected once
+            let sids: HashSet<&Sid> = rooms
                 .iter()
                 .filter_map(|room| rooms_map.get(room))
                 .flatten()
+                .chain(opts.sids.iter())
+                .collect();
+            sids.into_iter()
                 .filter(|sid| {
                     !except.contains(*sid)
                         && (!opts.flags.contains(&BroadcastFlags::Broadcast)
@@ -305,13 +596,35 @@ impl LocalAdapter {
                 })
                 .map(SocketRef::from)
                 .collect()
-        } else if let Some(sock) = opts.sid.and_then(|sid| ns.get_socket(sid).ok()) {
+        } else if let Some(sock) = opts
+            .sid
+            .filter(|sid| !except.contains(sid))
+            .and_then(|sid| ns.get_socket(sid).ok())
+        {
             vec![sock.into()]
         } else {
             vec![]
         }
     }
 
+    /// Adds to `rooms` all the known rooms matching at least one of the given `patterns`.
+    fn expand_patterns(
+        &self,
+        mut rooms: HashSet<Room>,
+        patterns: &HashSet<RoomPattern>,
+    ) -> HashSet<Room> {
+        if !patterns.is_empty() {
+            let rooms_map = self.rooms.read().unwrap();
+            rooms.extend(
+                rooms_map
+                    .keys()
+                    .filter(|room| patterns.iter().any(|p| p.matches(room)))
+                    .cloned(),
+            );
+        }
+        rooms
+    }
+
     fn get_except_sids(&self, except: &HashSet<Room>) -> HashSet<Sid> {
         let mut except_sids = HashSet::new();
         let rooms_map = self.rooms.read().unwrap();
@@ -327,6 +640,7 @@ impl LocalAdapter {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::operators::BroadcastOperators;
     use std::sync::Arc;
 
     macro_rules! hash_set {
@@ -335,6 +649,23 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_broadcast_options_builder() {
+        let sid = Sid::new();
+        let opts = BroadcastOptions::new(Some(sid))
+            .with_flag(BroadcastFlags::Broadcast)
+            .with_rooms(["room1", "room2"])
+            .with_except("room3")
+            .with_room_pattern("room*")
+            .with_except_pattern("*4");
+        assert_eq!(opts.sid, Some(sid));
+        assert_eq!(opts.flags, hash_set![BroadcastFlags::Broadcast]);
+        assert_eq!(opts.rooms, hash_set!["room1".into(), "room2".into()]);
+        assert_eq!(opts.except, hash_set!["room3".into()]);
+        assert_eq!(opts.room_patterns, hash_set![RoomPattern::new("room*")]);
+        assert_eq!(opts.except_patterns, hash_set![RoomPattern::new("*4")]);
+    }
+
     #[tokio::test]
     async fn test_server_count() {
         let ns = Namespace::new_dummy([]);
@@ -380,6 +711,49 @@ mod test {
         assert_eq!(rooms_map.get("room2").unwrap().len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_del_all_sid_room() {
+        let socket = Sid::new();
+        let ns = Namespace::new_dummy([socket]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket, socket).unwrap();
+        adapter.add_all(socket, "room1").unwrap();
+        assert_eq!(adapter.sockets(socket).unwrap(), vec![socket]);
+
+        adapter.del_all(socket).unwrap();
+        let rooms_map = adapter.rooms.read().unwrap();
+        assert_eq!(rooms_map.len(), 1);
+        assert!(rooms_map.get(socket.as_str()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sync_rooms() {
+        let socket = Sid::new();
+        let other = Sid::new();
+        let ns = Namespace::new_dummy([socket, other]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket, socket).unwrap();
+        adapter.add_all(socket, ["room1", "room2"]).unwrap();
+        adapter.add_all(other, ["room1", "room2"]).unwrap();
+
+        adapter
+            .sync_rooms(socket, vec!["room2".into(), "room3".into()])
+            .unwrap();
+        let mut rooms = adapter.socket_rooms(socket).unwrap();
+        rooms.sort();
+        let mut expected: Vec<Room> =
+            vec![socket.to_string().into(), "room2".into(), "room3".into()];
+        expected.sort();
+        assert_eq!(rooms, expected);
+
+        let mut rooms = adapter.socket_rooms(other).unwrap();
+        rooms.sort();
+        assert_eq!(rooms, vec!["room1", "room2"]);
+
+        adapter.sync_rooms(socket, vec![]).unwrap();
+        assert_eq!(adapter.socket_rooms(socket).unwrap(), vec![socket.as_str()]);
+    }
+
     #[tokio::test]
     async fn test_socket_room() {
         let sid1 = Sid::new();
@@ -487,6 +861,40 @@ mod test {
         assert!(sockets.contains(&socket2));
     }
 
+    #[tokio::test]
+    async fn test_room_sizes() {
+        let socket0 = Sid::new();
+        let socket1 = Sid::new();
+        let socket2 = Sid::new();
+        let ns = Namespace::new_dummy([socket0, socket1, socket2]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter
+            .add_all(socket0, ["room1", "room2", "room3"])
+            .unwrap();
+        adapter.add_all(socket1, ["room2", "room3"]).unwrap();
+        adapter.add_all(socket2, ["room3"]).unwrap();
+        adapter.del(socket0, "room1").unwrap();
+
+        let sizes = adapter.room_sizes().unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes["room1"], 0);
+        assert_eq!(sizes["room2"], 2);
+        assert_eq!(sizes["room3"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_has_room() {
+        let socket0 = Sid::new();
+        let ns = Namespace::new_dummy([socket0]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket0, ["room1", "room2"]).unwrap();
+        adapter.del(socket0, "room1").unwrap();
+
+        assert!(!adapter.has_room("room1").unwrap());
+        assert!(adapter.has_room("room2").unwrap());
+        assert!(!adapter.has_room("unknown").unwrap());
+    }
+
     #[tokio::test]
     async fn test_disconnect_socket() {
         let socket0 = Sid::new();
@@ -577,5 +985,320 @@ mod test {
         };
         let sockets = adapter.fetch_sockets(opts).unwrap();
         assert_eq!(sockets.len(), 0);
+
+        // socket 2 is in all the rooms but it is only selected once
+        let opts = BroadcastOptions::new(None).with_rooms(["room1", "room2", "room3"]);
+        let sockets = adapter.fetch_sockets(opts).unwrap();
+        assert_eq!(sockets.len(), 3);
+    }
+
+    #[test]
+    fn room_pattern_matches() {
+        let pattern = RoomPattern::new("admin:*");
+        assert!(pattern.matches("admin:"));
+        assert!(pattern.matches("admin:1"));
+        assert!(pattern.matches("admin:foo:bar"));
+        assert!(!pattern.matches("admin"));
+        assert!(!pattern.matches("my-admin:1"));
+
+        let pattern = RoomPattern::new("*:lobby");
+        assert!(pattern.matches(":lobby"));
+        assert!(pattern.matches("game:lobby"));
+        assert!(!pattern.matches("game:lobby2"));
+
+        let pattern = RoomPattern::new("game:*:red");
+        assert!(pattern.matches("game:1:red"));
+        assert!(pattern.matches("game::red"));
+        assert!(!pattern.matches("game:red"));
+        assert!(!pattern.matches("game:1:blue"));
+
+        let pattern = RoomPattern::new("a*b*a");
+        assert!(pattern.matches("aba"));
+        assert!(pattern.matches("abxba"));
+        assert!(!pattern.matches("ab"));
+
+        let pattern = RoomPattern::new("*");
+        assert!(pattern.matches(""));
+        assert!(pattern.matches("anything"));
+
+        let pattern = RoomPattern::new("room1");
+        assert!(pattern.matches("room1"));
+        assert!(!pattern.matches("room10"));
+        assert!(!pattern.matches("room"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_opts_with_patterns() {
+        let socket0 = Sid::new();
+        let socket1 = Sid::new();
+        let socket2 = Sid::new();
+        let ns = Namespace::new_dummy([socket0, socket1, socket2]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket0, ["admin:1", "lobby"]).unwrap();
+        adapter.add_all(socket1, ["admin:2", "game:1"]).unwrap();
+        adapter.add_all(socket2, ["my-admin:3", "game:2"]).unwrap();
+
+        let opts = BroadcastOptions {
+            room_patterns: hash_set!["admin:*".into()],
+            ..Default::default()
+        };
+        let sockets = adapter.fetch_sockets(opts).unwrap();
+        assert_eq!(sockets.len(), 2);
+        assert!(sockets.iter().all(|s| s.id == socket0 || s.id == socket1));
+
+        let opts = BroadcastOptions {
+            room_patterns: hash_set!["game:*".into()],
+            except_patterns: hash_set!["admin:*".into()],
+            ..Default::default()
+        };
+        let sockets = adapter.fetch_sockets(opts).unwrap();
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets[0].id, socket2);
+
+        // A pattern that matches nothing should not select any socket
+        let opts = BroadcastOptions {
+            room_patterns: hash_set!["unknown:*".into()],
+            ..Default::default()
+        };
+        assert!(adapter.fetch_sockets(opts).unwrap().is_empty());
+
+        let mut opts = BroadcastOptions {
+            sid: Some(socket2),
+            except_patterns: hash_set!["admin:*".into()],
+            ..Default::default()
+        };
+        opts.flags.insert(BroadcastFlags::Broadcast);
+        assert!(adapter.fetch_sockets(opts).unwrap().is_empty());
+
+        let mut opts = BroadcastOptions {
+            except_patterns: hash_set!["*admin:*".into()],
+            ..Default::default()
+        };
+        opts.flags.insert(BroadcastFlags::Broadcast);
+        assert!(adapter.fetch_sockets(opts).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_apply_opts_sids() {
+        let socket0 = Sid::new();
+        let socket1 = Sid::new();
+        let socket2 = Sid::new();
+        let ns = Namespace::new_dummy([socket0, socket1, socket2]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket0, ["room1"]).unwrap();
+        let ids = |opts| {
+            let mut ids: Vec<_> = adapter
+                .fetch_sockets(opts)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut sids: Vec<Sid>| {
+            sids.sort();
+            sids
+        };
+
+        // The sockets are selected without being in any room, unknown ids are ignored
+        let opts = BroadcastOptions::new(None).with_sids([socket1, Sid::new()]);
+        assert!(opts.targets(socket1, &[]));
+        assert!(!opts.targets(socket2, &[]));
+        assert_eq!(ids(opts), [socket1]);
+
+        let opts = BroadcastOptions::new(None)
+            .with_sids([socket0, socket1])
+            .with_rooms("room1");
+        assert_eq!(ids(opts), sorted(vec![socket0, socket1]));
+
+        let opts = BroadcastOptions::new(Some(socket2))
+            .with_sids([socket0, socket1, socket2])
+            .with_except("room1");
+        assert!(!opts.targets(socket0, &["room1".into()]));
+        assert_eq!(ids(opts), sorted(vec![socket1, socket2]));
+    }
+
+    #[tokio::test]
+    async fn test_apply_opts_except_sids() {
+        let sids = [(); 6].map(|_| Sid::new());
+        let ns = Namespace::new_dummy(sids);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        for sid in sids {
+            adapter.add_all(sid, ["room1"]).unwrap();
+        }
+        adapter.add_all(sids[1], ["room2"]).unwrap();
+        adapter.add_all(sids[2], ["room2"]).unwrap();
+        adapter.add_all(sids[5], ["room3"]).unwrap();
+        let ids = |opts| {
+            let mut ids: Vec<_> = adapter
+                .fetch_sockets(opts)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // sids[0] is the sender, sids[1] and sids[2] are in the excluded room,
+        // sids[2] and sids[3] are excluded by id and sids[5] is excluded by a pattern
+        let opts = BroadcastOptions::new(Some(sids[0]))
+            .with_flag(BroadcastFlags::Broadcast)
+            .with_rooms("room1")
+            .with_except("room2")
+            .with_except_pattern("room3")
+            .with_except_sids([sids[0], sids[2], sids[3]]);
+        for (i, sid) in sids.into_iter().enumerate() {
+            let mut rooms: Vec<Room> = vec!["room1".into()];
+            if i == 1 || i == 2 {
+                rooms.push("room2".into());
+            } else if i == 5 {
+                rooms.push("room3".into());
+            }
+            assert_eq!(opts.targets(sid, &rooms), i == 4);
+        }
+        assert_eq!(ids(opts.clone()), [sids[4]]);
+
+        // The sockets selected by id are also filtered out
+        let opts = opts.with_sids(sids);
+        assert_eq!(ids(opts), [sids[4]]);
+
+        // Without any room, all the sockets of the namespace are selected
+        let opts = BroadcastOptions::new(None)
+            .with_flag(BroadcastFlags::Broadcast)
+            .with_except("room2")
+            .with_except_sids([sids[3]]);
+        let mut expected = vec![sids[0], sids[4], sids[5]];
+        expected.sort();
+        assert_eq!(ids(opts), expected);
+
+        // The sender alone is also filtered out
+        let opts = BroadcastOptions::new(Some(sids[0])).with_except_sids([sids[0]]);
+        assert!(!opts.targets(sids[0], &["room1".into()]));
+        assert!(ids(opts).is_empty());
+        let opts = BroadcastOptions::new(Some(sids[1])).with_except("room2");
+        assert!(!opts.targets(sids[1], &["room1".into(), "room2".into()]));
+        assert!(ids(opts).is_empty());
+        let opts = BroadcastOptions::new(Some(sids[0])).with_except("room2");
+        assert_eq!(ids(opts), [sids[0]]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_opts_remote() {
+        let socket0 = Sid::new();
+        let socket1 = Sid::new();
+        let ns = Namespace::new_dummy([socket0, socket1]);
+        let adapter = LocalAdapter::new(Arc::downgrade(&ns));
+        adapter.add_all(socket0, ["room1"]).unwrap();
+
+        // There is no remote server with the local adapter so nobody is targeted
+        let opts = BroadcastOptions::new(Some(socket0))
+            .with_flag(BroadcastFlags::Broadcast)
+            .with_flag(BroadcastFlags::Remote);
+        assert!(!opts.targets(socket1, &[]));
+        assert!(adapter.fetch_sockets(opts).unwrap().is_empty());
+
+        let opts = BroadcastOptions::new(None)
+            .with_flag(BroadcastFlags::Remote)
+            .with_rooms("room1");
+        assert!(!opts.targets(socket0, &["room1".into()]));
+        assert!(adapter.fetch_sockets(opts).unwrap().is_empty());
+    }
+
+    /// An adapter that records the options of the broadcasts it receives and whether it is closed.
+    #[derive(Debug, Default)]
+    struct MockAdapter(
+        std::sync::Mutex<Vec<BroadcastOptions>>,
+        std::sync::atomic::AtomicBool,
+    );
+
+    impl Adapter for MockAdapter {
+        type Error = Infallible;
+
+        fn new(_: Weak<Namespace<Self>>) -> Self {
+            Self::default()
+        }
+        fn init(&self) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn close(&self) -> BoxFuture<'static, Result<(), Infallible>> {
+            self.1.store(true, std::sync::atomic::Ordering::SeqCst);
+            Box::pin(futures::future::ready(Ok(())))
+        }
+        fn server_count(&self) -> Result<u16, Infallible> {
+            Ok(2)
+        }
+        fn add_all(&self, _: Sid, _: impl RoomParam) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn del(&self, _: Sid, _: impl RoomParam) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn del_all(&self, _: Sid) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn broadcast(&self, _: Packet<'_>, opts: BroadcastOptions) -> Result<(), BroadcastError> {
+            self.0.lock().unwrap().push(opts);
+            Ok(())
+        }
+        fn broadcast_with_ack(
+            &self,
+            packet: Packet<'static>,
+            opts: BroadcastOptions,
+            timeout: Option<Duration>,
+        ) -> AckInnerStream {
+            self.0.lock().unwrap().push(opts);
+            AckInnerStream::broadcast::<Self>(packet, vec![], timeout)
+        }
+        fn sockets(&self, _: impl RoomParam) -> Result<Vec<Sid>, Infallible> {
+            Ok(vec![])
+        }
+        fn socket_rooms(&self, _: Sid) -> Result<Vec<Room>, Infallible> {
+            Ok(vec![])
+        }
+        fn fetch_sockets(&self, _: BroadcastOptions) -> Result<Vec<SocketRef<Self>>, Infallible> {
+            Ok(vec![])
+        }
+        fn add_sockets(&self, _: BroadcastOptions, _: impl RoomParam) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn del_sockets(&self, _: BroadcastOptions, _: impl RoomParam) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn disconnect_socket(&self, _: BroadcastOptions) -> Result<(), Vec<DisconnectError>> {
+            Ok(())
+        }
+        fn rooms(&self) -> Result<Vec<Room>, Infallible> {
+            Ok(vec![])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_flag_forwarded_to_adapter() {
+        let ns = Namespace::<MockAdapter>::new_dummy([]);
+        BroadcastOperators::new(ns.clone())
+            .to("room1")
+            .remote()
+            .emit("test", ())
+            .unwrap();
+        BroadcastOperators::new(ns.clone())
+            .local()
+            .emit("test", ())
+            .unwrap();
+
+        let opts = ns.adapter.0.lock().unwrap();
+        assert_eq!(opts.len(), 2);
+        assert!(opts[0].flags.contains(&BroadcastFlags::Remote));
+        assert!(!opts[0].flags.contains(&BroadcastFlags::Local));
+        assert_eq!(opts[0].rooms, hash_set!["room1".into()]);
+        assert!(!opts[1].flags.contains(&BroadcastFlags::Remote));
+    }
+
+    #[tokio::test]
+    async fn test_close_adapter() {
+        let ns = Namespace::<MockAdapter>::new_dummy([]);
+        ns.close().await;
+        assert!(ns.adapter.1.load(std::sync::atomic::Ordering::SeqCst));
     }
 }
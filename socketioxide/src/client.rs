@@ -1,6 +1,9 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex, MutexGuard, RwLock, TryLockError, Weak,
+};
 
 use engineioxide::handler::EngineIoHandler;
 use engineioxide::socket::{DisconnectReason as EIoDisconnectReason, Socket as EIoSocket};
@@ -15,25 +18,65 @@ use crate::ProtocolVersion;
 use crate::{
     errors::Error,
     ns::Namespace,
-    packet::{Packet, PacketData},
-    SocketIoConfig,
+    packet::{declared_attachments, Packet, PacketData},
+    MaxSocketsPolicy, SocketIoConfig,
 };
 
-#[derive(Debug)]
+/// The admission lock of the client, see [`Client::try_lock_admission`]
+pub(crate) struct ClientAdmission<'a, A: Adapter> {
+    client: &'a Client<A>,
+    lock: Option<MutexGuard<'a, ()>>,
+}
+
+impl<A: Adapter> Drop for ClientAdmission<'_, A> {
+    fn drop(&mut self) {
+        self.lock.take();
+        if self.client.readmit.swap(false, Ordering::SeqCst) {
+            let namespaces: Vec<_> = self.client.ns.read().unwrap().values().cloned().collect();
+            for ns in namespaces {
+                ns.admit_queued();
+            }
+        }
+    }
+}
+
+/// Creates a namespace for the given path, used for dynamic namespaces
+type DynamicNsFactory<A> = Box<dyn Fn(Cow<'static, str>) -> Arc<Namespace<A>> + Send + Sync>;
+
 pub struct Client<A: Adapter> {
     pub(crate) config: Arc<SocketIoConfig>,
     ns: RwLock<HashMap<Cow<'static, str>, Arc<Namespace<A>>>>,
+    dynamic_ns: RwLock<Option<DynamicNsFactory<A>>>,
+    /// Read while a connection joins a namespace if dynamic namespaces are enabled,
+    /// so that an idle dynamic namespace is not removed while a socket is joining it
+    dynamic_admission: RwLock<()>,
+    /// Held while a connection is checked against the [`SocketIoConfig::max_connections`] limit and connected,
+    /// so that concurrent connections cannot exceed it
+    admission: Mutex<()>,
+    /// Set when the queued connections of the namespaces must be admitted by the holder of the admission lock
+    readmit: AtomicBool,
+    /// The number of namespaces each connection is connected to,
+    /// only tracked if [`SocketIoConfig::max_connections`] is set
+    connections: Mutex<HashMap<Sid, usize>>,
+    /// A reference to itself given to the namespaces so that sockets can reach the whole server
+    this: Weak<Client<A>>,
 }
 
 impl<A: Adapter> Client<A> {
-    pub fn new(config: Arc<SocketIoConfig>) -> Self {
+    pub fn new(config: Arc<SocketIoConfig>) -> Arc<Self> {
         #[cfg(feature = "state")]
         crate::state::freeze_state();
 
-        Self {
+        Arc::new_cyclic(|this| Self {
             config,
             ns: RwLock::new(HashMap::new()),
-        }
+            dynamic_ns: RwLock::new(None),
+            dynamic_admission: RwLock::new(()),
+            admission: Mutex::new(()),
+            readmit: AtomicBool::new(false),
+            connections: Mutex::new(HashMap::new()),
+            this: this.clone(),
+        })
     }
 
     /// Called when a socket connects to a new namespace
@@ -47,12 +90,47 @@ impl<A: Adapter> Client<A> {
         tracing::debug!("auth: {:?}", auth);
 
         let sid = esocket.id;
-        if let Some(ns) = self.get_ns(ns_path) {
-            ns.connect(sid, esocket.clone(), auth, self.config.clone())?;
+        let dynamic = self.dynamic_ns.read().unwrap().is_some();
+        if dynamic && self.get_ns(ns_path).is_none() {
+            self.remove_idle_dynamic_ns();
+        }
+        let _dynamic_admission = dynamic.then(|| self.dynamic_admission.read().unwrap());
+        if let Some(ns) = self.get_or_create_ns(ns_path) {
+            // The limits are checked and the socket connected or queued under the admission locks,
+            // so that concurrent connections cannot exceed them
+            let _admission = self.config.max_connections.map(|_| self.lock_admission());
+            let _ns_admission = (ns.is_limited() || self.config.max_sockets_per_ns.is_some())
+                .then(|| ns.lock_admission());
+            let policy = ns.full_policy();
+            let message = match ns.check_connect(&esocket.req_parts, auth.as_deref()) {
+                Err(message) => Some(message.into()),
+                Ok(()) if policy == Some(MaxSocketsPolicy::Reject) => {
+                    Some("Namespace is full".into())
+                }
+                Ok(()) => self.check_limits(&ns, sid).map(Cow::Borrowed),
+            };
+            if let Some(message) = message {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("refusing connection to namespace {}: {}", ns_path, message);
+                let packet = Packet::encode_connect_error(ns_path, &message);
+                if let Err(_e) = esocket.emit(packet) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("error while sending connect error packet: {}", _e);
+                }
+                return Ok(());
+            }
 
-            // cancel the connect timeout task for v5
-            if let Some(tx) = esocket.data.connect_recv_tx.lock().unwrap().take() {
-                tx.send(()).ok();
+            match policy {
+                // The connect timeout is kept until the connection is admitted,
+                // so that the session is closed if it is rejected after waiting too long
+                Some(MaxSocketsPolicy::Queue(timeout)) => {
+                    ns.enqueue(esocket.clone(), auth, self.config.clone(), timeout)
+                }
+                _ => {
+                    esocket.data.cancel_connect_timeout();
+                    ns.clone()
+                        .connect(sid, esocket.clone(), auth, self.config.clone())?
+                }
             }
 
             Ok(())
@@ -73,10 +151,99 @@ impl<A: Adapter> Client<A> {
         }
     }
 
-    /// Propagate a packet to a its target namespace
-    fn sock_propagate_packet(&self, packet: Packet<'_>, sid: Sid) -> Result<(), Error> {
+    /// Takes the admission lock of the client, held while a connection is checked against
+    /// the [`SocketIoConfig::max_connections`] limit and connected.
+    ///
+    /// When it is released, the queued connections of the namespaces are admitted
+    /// if they could not be while it was held.
+    fn lock_admission(&self) -> ClientAdmission<'_, A> {
+        // The lock guards no data, it is still usable if a connect handler panicked while it was held
+        let lock = self.admission.lock().unwrap_or_else(|e| e.into_inner());
+        ClientAdmission {
+            client: self,
+            lock: Some(lock),
+        }
+    }
+
+    /// Tries to take the admission lock of the client to admit the queued connections of a namespace.
+    ///
+    /// If it is already held, `None` is returned and they are admitted by its holder once it is released.
+    pub(crate) fn try_lock_admission(&self) -> Option<ClientAdmission<'_, A>> {
+        // The flag is set before trying the lock so that a holder releasing it concurrently sees it
+        self.readmit.store(true, Ordering::SeqCst);
+        let lock = match self.admission.try_lock() {
+            Ok(lock) => lock,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        self.readmit.store(false, Ordering::SeqCst);
+        Some(ClientAdmission {
+            client: self,
+            lock: Some(lock),
+        })
+    }
+
+    /// Checks that a new socket can be connected to the given namespace
+    /// according to [`SocketIoConfig::max_sockets_per_ns`] and [`SocketIoConfig::max_connections`].
+    ///
+    /// Returns the reason of the refusal if a limit is reached.
+    fn check_limits(&self, ns: &Namespace<A>, sid: Sid) -> Option<&'static str> {
+        if matches!(self.config.max_sockets_per_ns, Some(max) if ns.len() >= max) {
+            return Some("Namespace is full");
+        }
+        self.check_connections(sid)
+    }
+
+    /// Checks that the connection `sid` can be connected to a namespace
+    /// according to [`SocketIoConfig::max_connections`].
+    /// It should be called with the admission lock held.
+    ///
+    /// Returns the reason of the refusal if the limit is reached.
+    pub(crate) fn check_connections(&self, sid: Sid) -> Option<&'static str> {
+        if let Some(max) = self.config.max_connections {
+            let connections = self.connections.lock().unwrap();
+            // A connection already connected to another namespace is not a new connection
+            if !connections.contains_key(&sid) && connections.len() >= max {
+                return Some("Too many connections");
+            }
+        }
+        None
+    }
+
+    /// Records a socket connected to a namespace, for the [`SocketIoConfig::max_connections`] limit
+    pub(crate) fn socket_added(&self, sid: Sid) {
+        if self.config.max_connections.is_some() {
+            *self.connections.lock().unwrap().entry(sid).or_default() += 1;
+        }
+    }
+
+    /// Records a socket removed from a namespace, for the [`SocketIoConfig::max_connections`] limit
+    pub(crate) fn socket_removed(&self, sid: Sid) {
+        if self.config.max_connections.is_none() {
+            return;
+        }
+        if let Entry::Occupied(mut entry) = self.connections.lock().unwrap().entry(sid) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Propagate a packet to a its target namespace, with the size of its payload and of its binary attachments
+    fn sock_propagate_packet(
+        &self,
+        packet: Packet<'_>,
+        payload_len: usize,
+        sid: Sid,
+    ) -> Result<(), Error> {
         if let Some(ns) = self.get_ns(&packet.ns) {
-            ns.recv(sid, packet.inner)
+            if !ns.has(sid) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("packet received for a namespace not joined: {}", packet.ns);
+                return Err(Error::NamespaceNotJoined(packet.ns.into_owned()));
+            }
+            ns.recv(sid, packet.inner, payload_len)
         } else {
             #[cfg(feature = "tracing")]
             tracing::debug!("invalid namespace requested: {}", packet.ns);
@@ -109,7 +276,7 @@ impl<A: Adapter> Client<A> {
     {
         #[cfg(feature = "tracing")]
         tracing::debug!("adding namespace {}", path);
-        let ns = Namespace::new(path.clone(), callback);
+        let ns = Namespace::new(path.clone(), self.this.clone(), callback);
         self.ns.write().unwrap().insert(path, ns);
     }
 
@@ -120,10 +287,102 @@ impl<A: Adapter> Client<A> {
         self.ns.write().unwrap().remove(path);
     }
 
+    /// Sets the handler used to create the namespaces that are not registered when a client connects to them
+    pub fn set_dynamic_ns<C, T>(&self, callback: C)
+    where
+        C: ConnectHandler<A, T> + Clone,
+        T: Send + Sync + 'static,
+    {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("enabling dynamic namespaces");
+        let client = self.this.clone();
+        let factory = move |path| {
+            let ns = Namespace::new(path, client.clone(), callback.clone());
+            ns.set_dynamic();
+            ns
+        };
+        self.dynamic_ns.write().unwrap().replace(Box::new(factory));
+    }
+
     pub fn get_ns(&self, path: &str) -> Option<Arc<Namespace<A>>> {
         self.ns.read().unwrap().get(path).cloned()
     }
 
+    /// Gets all the registered namespaces
+    pub(crate) fn namespaces(&self) -> Vec<Arc<Namespace<A>>> {
+        self.ns.read().unwrap().values().cloned().collect()
+    }
+
+    /// Gets the sorted paths of all the namespaces the connection `sid` is connected to
+    pub(crate) fn socket_namespaces(&self, sid: Sid) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .ns
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, ns)| ns.get_socket(sid).is_ok())
+            .map(|(path, _)| path.to_string())
+            .collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    /// Gets a namespace or creates it if dynamic namespaces are enabled
+    /// and the [`SocketIoConfig::max_dynamic_namespaces`] limit is not reached
+    fn get_or_create_ns(&self, path: &str) -> Option<Arc<Namespace<A>>> {
+        if let Some(ns) = self.get_ns(path) {
+            return Some(ns);
+        }
+        let factory = self.dynamic_ns.read().unwrap();
+        let factory = factory.as_ref()?;
+        let mut namespaces = self.ns.write().unwrap();
+        if let Some(ns) = namespaces.get(path) {
+            return Some(ns.clone());
+        }
+        if let Some(max) = self.config.max_dynamic_namespaces {
+            if namespaces.values().filter(|ns| ns.is_dynamic()).count() >= max {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("too many dynamic namespaces, refusing to create {}", path);
+                return None;
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!("creating dynamic namespace {}", path);
+        let path: Cow<'static, str> = Cow::Owned(path.to_string());
+        let ns = factory(path.clone());
+        namespaces.insert(path, ns.clone());
+        Some(ns)
+    }
+
+    /// Removes a dynamic namespace once it is idle.
+    ///
+    /// It is skipped if a connection is joining a dynamic namespace,
+    /// the namespace is then removed by the next [`Client::remove_idle_dynamic_ns`] call.
+    pub(crate) fn remove_idle_ns(&self, ns: &Namespace<A>) {
+        let Ok(_dynamic_admission) = self.dynamic_admission.try_write() else {
+            return;
+        };
+        let Ok(mut namespaces) = self.ns.try_write() else {
+            return;
+        };
+        let registered =
+            matches!(namespaces.get(&ns.path), Some(n) if std::ptr::eq(n.as_ref(), ns));
+        if registered && ns.is_idle() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("removing idle dynamic namespace {}", ns.path);
+            namespaces.remove(&ns.path);
+        }
+    }
+
+    /// Removes all the idle dynamic namespaces, called before a dynamic namespace is created
+    fn remove_idle_dynamic_ns(&self) {
+        let _dynamic_admission = self.dynamic_admission.write().unwrap();
+        self.ns
+            .write()
+            .unwrap()
+            .retain(|_, ns| !ns.is_dynamic() || !ns.is_idle());
+    }
+
     /// Closes all engine.io connections and all clients
     #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
     pub(crate) async fn close(&self) {
@@ -136,14 +395,37 @@ impl<A: Adapter> Client<A> {
     }
 }
 
+impl<A: Adapter> std::fmt::Debug for Client<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("config", &self.config)
+            .field("ns", &self.ns)
+            .field("dynamic_ns", &self.dynamic_ns.read().unwrap().is_some())
+            .finish()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct SocketData {
-    /// Partial binary packet that is being received
+    /// Partial binary packet that is being received, with the size of its payload and of the attachments received
     /// Stored here until all the binary payloads are received
-    pub partial_bin_packet: Mutex<Option<Packet<'static>>>,
+    pub partial_bin_packet: Mutex<Option<(Packet<'static>, usize)>>,
 
     /// Channel used to notify the socket that it has been connected to a namespace for v5
     pub connect_recv_tx: Mutex<Option<oneshot::Sender<()>>>,
+
+    /// A type map shared by all the sockets of the connection
+    #[cfg(feature = "extensions")]
+    pub extensions: crate::extensions::Extensions,
+}
+
+impl SocketData {
+    /// Cancels the connect timeout task for v5, once the connection is connected to a namespace
+    pub(crate) fn cancel_connect_timeout(&self) {
+        if let Some(tx) = self.connect_recv_tx.lock().unwrap().take() {
+            tx.send(()).ok();
+        }
+    }
 }
 
 impl<A: Adapter> EngineIoHandler for Client<A> {
@@ -174,12 +456,20 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
     fn on_disconnect(&self, socket: Arc<EIoSocket<SocketData>>, reason: EIoDisconnectReason) {
         #[cfg(feature = "tracing")]
         tracing::debug!("eio socket disconnected");
-        let _res: Result<Vec<_>, _> = self
-            .ns
-            .read()
-            .unwrap()
-            .values()
-            .filter_map(|ns| ns.get_socket(socket.id).ok())
+        // The sockets are closed once the namespaces lock is released,
+        // so that the idle dynamic namespaces can be removed
+        let sockets: Vec<_> = {
+            let namespaces = self.ns.read().unwrap();
+            for ns in namespaces.values() {
+                ns.remove_queued(socket.id);
+            }
+            namespaces
+                .values()
+                .filter_map(|ns| ns.get_socket(socket.id).ok())
+                .collect()
+        };
+        let _res: Result<Vec<_>, _> = sockets
+            .into_iter()
             .map(|s| s.close(reason.clone().into()))
             .collect();
 
@@ -194,10 +484,34 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, socket), fields(sid = socket.id.to_string())))]
+    fn on_upgrade(&self, socket: Arc<EIoSocket<SocketData>>) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("eio socket upgraded to websocket");
+        let sockets: Vec<_> = self
+            .ns
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|ns| ns.get_socket(socket.id).ok())
+            .collect();
+        for socket in sockets {
+            socket.upgrade();
+        }
+    }
+
     fn on_message(&self, msg: String, socket: Arc<EIoSocket<SocketData>>) {
         #[cfg(feature = "tracing")]
         tracing::debug!("Received message: {:?}", msg);
-        let packet = match Packet::try_from(msg) {
+        if let Some(max) = self.config.max_attachments {
+            if declared_attachments(&msg).map_or(false, |count| count > max) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("socket {} declared too many binary attachments", socket.id);
+                socket.close(EIoDisconnectReason::PacketParsingError);
+                return;
+            }
+        }
+        let (packet, payload_len) = match Packet::decode(msg) {
             Ok(packet) => packet,
             Err(_e) => {
                 #[cfg(feature = "tracing")]
@@ -213,6 +527,17 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
             PacketData::Connect(auth) => self
                 .sock_connect(auth, &packet.ns, &socket)
                 .map_err(Into::into),
+            PacketData::BinaryEvent(_, _, _) | PacketData::BinaryAck(_, _)
+                if self
+                    .config
+                    .max_attachments
+                    .map_or(false, |max| packet.inner.payload_count() > max) =>
+            {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("socket {} sent too many binary attachments", socket.id);
+                socket.close(EIoDisconnectReason::PacketParsingError);
+                Ok(())
+            }
             PacketData::BinaryEvent(_, _, _) | PacketData::BinaryAck(_, _) => {
                 // Cache-in the socket data until all the binary payloads are received
                 socket
@@ -220,10 +545,10 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
                     .partial_bin_packet
                     .lock()
                     .unwrap()
-                    .replace(packet);
+                    .replace((packet, payload_len));
                 Ok(())
             }
-            _ => self.sock_propagate_packet(packet, socket.id),
+            _ => self.sock_propagate_packet(packet, payload_len, socket.id),
         };
         if let Err(ref err) = res {
             #[cfg(feature = "tracing")]
@@ -243,8 +568,10 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
     /// If the packet is complete, it is propagated to the namespace
     fn on_binary(&self, data: Vec<u8>, socket: Arc<EIoSocket<SocketData>>) {
         if apply_payload_on_packet(data, &socket) {
-            if let Some(packet) = socket.data.partial_bin_packet.lock().unwrap().take() {
-                if let Err(ref err) = self.sock_propagate_packet(packet, socket.id) {
+            // The packet is moved out before being dispatched so that the lock is not held by the handlers
+            let packet = socket.data.partial_bin_packet.lock().unwrap().take();
+            if let Some((packet, payload_len)) = packet {
+                if let Err(ref err) = self.sock_propagate_packet(packet, payload_len, socket.id) {
                     #[cfg(feature = "tracing")]
                     tracing::debug!(
                         "error while propagating packet to socket {}: {}",
@@ -267,9 +594,12 @@ impl<A: Adapter> EngineIoHandler for Client<A> {
 fn apply_payload_on_packet(data: Vec<u8>, socket: &EIoSocket<SocketData>) -> bool {
     #[cfg(feature = "tracing")]
     tracing::debug!("[sid={}] applying payload on packet", socket.id);
-    if let Some(ref mut packet) = *socket.data.partial_bin_packet.lock().unwrap() {
+    if let Some((ref mut packet, ref mut payload_len)) =
+        *socket.data.partial_bin_packet.lock().unwrap()
+    {
         match packet.inner {
             PacketData::BinaryEvent(_, ref mut bin, _) | PacketData::BinaryAck(ref mut bin, _) => {
+                *payload_len += data.len();
                 bin.add_payload(data);
                 bin.is_complete()
             }
@@ -289,7 +619,7 @@ mod test {
     use crate::adapter::LocalAdapter;
     const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(10);
 
-    fn create_client() -> super::Client<LocalAdapter> {
+    fn create_client() -> Arc<super::Client<LocalAdapter>> {
         let config = crate::SocketIoConfig {
             connect_timeout: CONNECT_TIMEOUT,
             ..Default::default()
@@ -325,4 +655,256 @@ mod test {
             .await
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn packet_for_namespace_not_joined() {
+        let client = create_client();
+        client.add_ns("/admin".into(), || {});
+        let (tx, mut rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, _| tx.try_send(()).unwrap());
+        let sid = Sid::new();
+        let sock = Arc::new(EIoSocket::new_dummy(sid, close_fn));
+        client.on_connect(sock.clone());
+        client.on_message("0".into(), sock.clone());
+
+        let packet = Packet::event("/admin", "test", serde_json::Value::Null);
+        assert!(matches!(
+            client.sock_propagate_packet(packet, 0, sid),
+            Err(Error::NamespaceNotJoined(ns)) if ns == "/admin"
+        ));
+        let packet = Packet::event("/", "test", serde_json::Value::Null);
+        assert!(client.sock_propagate_packet(packet, 0, sid).is_ok());
+
+        // The packet is dropped without closing the connection
+        client.on_message(r#"2/admin,["test"]"#.into(), sock.clone());
+        tokio::time::timeout(CONNECT_TIMEOUT * 2, rx.recv())
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn max_attachments() {
+        let config = crate::SocketIoConfig {
+            max_attachments: Some(2),
+            ..Default::default()
+        };
+        let client = Client::<LocalAdapter>::new(Arc::new(config));
+        client.add_ns("/".into(), || {});
+        let (tx, mut rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, _| tx.try_send(()).unwrap());
+        let sock = Arc::new(EIoSocket::new_dummy(Sid::new(), close_fn));
+        client.on_connect(sock.clone());
+        client.on_message("0".into(), sock.clone());
+
+        let placeholder = r#"{"_placeholder":true,"num":0}"#;
+        client.on_message(format!(r#"52-["test",{placeholder}]"#), sock.clone());
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_some());
+        sock.data.partial_bin_packet.lock().unwrap().take();
+
+        // The packet is rejected from its header, before anything is buffered
+        client.on_message(
+            format!(r#"54294967295-["test",{placeholder}]"#),
+            sock.clone(),
+        );
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_none());
+        rx.try_recv().unwrap();
+    }
+
+    #[tokio::test]
+    async fn max_attachments_placeholders() {
+        let config = crate::SocketIoConfig {
+            max_attachments: Some(1),
+            ..Default::default()
+        };
+        let client = Client::<LocalAdapter>::new(Arc::new(config));
+        client.add_ns("/".into(), || {});
+        let (tx, mut rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, _| tx.try_send(()).unwrap());
+        let sock = Arc::new(EIoSocket::new_dummy(Sid::new(), close_fn));
+        client.on_connect(sock.clone());
+        client.on_message("0".into(), sock.clone());
+
+        // The header understates the number of placeholders
+        let placeholders = r#"{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}"#;
+        client.on_message(format!(r#"51-["test",{placeholders}]"#), sock.clone());
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_none());
+        rx.try_recv().unwrap();
+    }
+
+    #[tokio::test]
+    async fn partial_bin_ack_timeout() {
+        let config = crate::SocketIoConfig {
+            ack_timeout: std::time::Duration::from_millis(10),
+            ..Default::default()
+        };
+        let client = Client::<LocalAdapter>::new(Arc::new(config));
+        client.add_ns("/".into(), || {});
+        let (tx, mut rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, _| tx.try_send(()).unwrap());
+        let sid = Sid::new();
+        let sock = Arc::new(EIoSocket::new_dummy(sid, close_fn));
+        client.on_connect(sock.clone());
+        client.on_message("0".into(), sock.clone());
+        let socket = client.get_ns("/").unwrap().get_socket(sid).unwrap();
+
+        let ack = socket
+            .emit_with_ack::<_, serde_json::Value>("test", serde_json::Value::Null)
+            .unwrap();
+        let placeholders = r#"{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}"#;
+        client.on_message(format!(r#"62-1[{placeholders}]"#), sock.clone());
+        client.on_binary(vec![1], sock.clone());
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_some());
+
+        // The partial binary ack is discarded when the ack times out
+        assert!(matches!(ack.await, Err(crate::AckError::Timeout)));
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_none());
+        assert_eq!(socket.pending_acks(), 0);
+
+        // The rest of the now stray binary ack is ignored
+        client.on_binary(vec![2], sock.clone());
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_none());
+
+        // A complete stray binary ack is also ignored
+        client.on_message(
+            r#"61-1[{"_placeholder":true,"num":0}]"#.into(),
+            sock.clone(),
+        );
+        client.on_binary(vec![3], sock.clone());
+        assert!(sock.data.partial_bin_packet.lock().unwrap().is_none());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn bin_handler_dropping_pending_ack() {
+        use crate::extract::{Bin, SocketRef};
+        let client = Client::<LocalAdapter>::new(Arc::new(crate::SocketIoConfig::default()));
+        let (tx, mut rx) = mpsc::channel(1);
+        client.add_ns("/".into(), move |socket: SocketRef| {
+            let tx = tx.clone();
+            socket.on("test", move |socket: SocketRef, Bin(_)| {
+                // The pending ack is dropped while the binary packet is dispatched
+                let _ = socket.emit_with_ack::<_, serde_json::Value>("ack", ());
+                tx.try_send(()).unwrap();
+            });
+        });
+        let sock = Arc::new(EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| {})));
+        client.on_connect(sock.clone());
+        client.on_message("0".into(), sock.clone());
+        client.on_message(
+            r#"51-["test",{"_placeholder":true,"num":0}]"#.into(),
+            sock.clone(),
+        );
+
+        // A deadlocked thread is leaked rather than waited for, so that the test fails
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let runtime = tokio::runtime::Handle::current();
+        std::thread::spawn(move || {
+            let _guard = runtime.enter();
+            client.on_binary(vec![1], sock);
+            done_tx.send(()).unwrap();
+        });
+        done_rx
+            .recv_timeout(std::time::Duration::from_secs(1))
+            .expect("on_binary deadlocked");
+        rx.try_recv().unwrap();
+    }
+
+    #[tokio::test]
+    async fn queued_connection_keeps_connect_timeout() {
+        let client = create_client();
+        let policy = MaxSocketsPolicy::Queue(std::time::Duration::from_secs(1));
+        client.get_ns("/").unwrap().set_max_sockets(1, policy);
+        let sock1 = Arc::new(EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| ())));
+        client.on_connect(sock1.clone());
+        client.on_message("0".into(), sock1.clone());
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let close_fn = Box::new(move |_, _| tx.try_send(()).unwrap());
+        let sock2 = Arc::new(EIoSocket::new_dummy(Sid::new(), close_fn));
+        client.on_connect(sock2.clone());
+        client.on_message("0".into(), sock2.clone());
+        // The queued connection is closed once the connect timeout has elapsed
+        tokio::time::timeout(CONNECT_TIMEOUT * 2, rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_connections_over_max_sockets() {
+        let client = create_client();
+        let ns = client.get_ns("/").unwrap();
+        ns.set_max_sockets(1, MaxSocketsPolicy::Reject);
+        connect_concurrently(&client, 8);
+        assert_eq!(ns.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_connections_over_max_connections() {
+        let config = crate::SocketIoConfig {
+            max_connections: Some(1),
+            ..Default::default()
+        };
+        let client = Client::<LocalAdapter>::new(std::sync::Arc::new(config));
+        client.add_ns("/".into(), || {});
+        connect_concurrently(&client, 8);
+        assert_eq!(client.get_ns("/").unwrap().len(), 1);
+        assert_eq!(client.connections.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_connection_over_max_connections() {
+        let config = crate::SocketIoConfig {
+            max_connections: Some(2),
+            ..Default::default()
+        };
+        let client = Client::<LocalAdapter>::new(std::sync::Arc::new(config));
+        client.add_ns("/".into(), || {});
+        client.add_ns("/b".into(), || {});
+        let ns = client.get_ns("/").unwrap();
+        ns.set_max_sockets(
+            1,
+            MaxSocketsPolicy::Queue(std::time::Duration::from_secs(1)),
+        );
+        let connect = |packets: &[&str]| {
+            let sock = Arc::new(EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| ())));
+            client.on_connect(sock.clone());
+            for &packet in packets {
+                client.on_message(packet.to_string(), sock.clone());
+            }
+            sock
+        };
+
+        let sock1 = connect(&["0/b,", "0"]);
+        // Queued while the limit of connections is not reached
+        let sock2 = connect(&["0"]);
+        connect(&["0/b,"]);
+        assert_eq!(client.connections.lock().unwrap().len(), 2);
+
+        // The slot freed in the namespace can't be given to a new connection
+        client.on_message("1".into(), sock1);
+        assert_eq!(ns.len(), 0);
+        assert!(!ns.has(sock2.id));
+        assert_eq!(client.connections.lock().unwrap().len(), 2);
+    }
+
+    /// Connects `n` connections to the root namespace from concurrent threads
+    fn connect_concurrently(client: &Arc<Client<LocalAdapter>>, n: usize) {
+        let handle = tokio::runtime::Handle::current();
+        let threads: Vec<_> = (0..n)
+            .map(|_| {
+                let client = client.clone();
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    let _guard = handle.enter();
+                    let sock = Arc::new(EIoSocket::new_dummy(Sid::new(), Box::new(|_, _| ())));
+                    client.on_connect(sock.clone());
+                    client.on_message("0".into(), sock);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
 }
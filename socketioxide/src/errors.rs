@@ -5,21 +5,35 @@ use tokio::{sync::mpsc::error::TrySendError, time::error::Elapsed};
 /// Error type for socketio
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    /// An error occurred while serializing the JSON packet.
     #[error("error serializing json packet: {0:?}")]
     Serialize(#[from] serde_json::Error),
 
+    /// The received packet type is invalid.
     #[error("invalid packet type")]
     InvalidPacketType,
 
+    /// The received event name is invalid.
     #[error("invalid event name")]
     InvalidEventName,
 
+    /// The namespace is invalid.
     #[error("invalid namespace")]
     InvalidNamespace,
 
+    /// No namespace is registered for the given path.
+    #[error("unknown namespace: {0}")]
+    UnknownNamespace(String),
+
+    /// The client sent a packet to a namespace it has not joined.
+    #[error("namespace not joined: {0}")]
+    NamespaceNotJoined(String),
+
+    /// The socket.io socket is not connected anymore.
     #[error("cannot find socketio socket")]
     SocketGone(Sid),
 
+    /// An error occured in the adapter.
     #[error("adapter error: {0}")]
     Adapter(#[from] AdapterError),
 }
@@ -31,10 +45,17 @@ pub enum AckError<T> {
     #[error("cannot deserialize json packet from ack response: {0:?}")]
     Serde(#[from] serde_json::Error),
 
-    /// The ack response timed out
+    /// The ack response timed out.
+    ///
+    /// The packet was sent but the client didn't respond before the ack timeout elapsed.
+    /// A failure to send the packet is reported as an [`AckError::Socket`] instead.
     #[error("ack timeout error")]
     Timeout,
 
+    /// The socket was disconnected before the ack response was received
+    #[error("socket disconnected before receiving the ack response")]
+    Disconnected,
+
     /// An error happened while broadcasting to other socket.io nodes
     #[error("adapter error: {0}")]
     Adapter(#[from] AdapterError),
@@ -58,6 +79,12 @@ pub enum BroadcastError {
     /// An error occured while broadcasting to other nodes.
     #[error("Adapter error: {0}")]
     Adapter(#[from] AdapterError),
+
+    /// No socket matched the selected rooms, returned by [`BroadcastOperators::emit_or_err`].
+    ///
+    /// [`BroadcastOperators::emit_or_err`]: crate::operators::BroadcastOperators::emit_or_err
+    #[error("no socket matched the broadcast")]
+    NoRecipients,
 }
 /// Error type for sending operations.
 #[derive(thiserror::Error, Debug)]
@@ -118,6 +145,28 @@ impl Display for AdapterError {
     }
 }
 
+impl<T> SendError<T> {
+    /// Drops the value given back by the error
+    pub(crate) fn discard_value(self) -> SendError<()> {
+        match self {
+            Self::Serialize(e) => SendError::Serialize(e),
+            Self::Socket(SocketError::InternalChannelFull(_)) => {
+                SendError::Socket(SocketError::InternalChannelFull(()))
+            }
+            Self::Socket(SocketError::Closed(_)) => SendError::Socket(SocketError::Closed(())),
+        }
+    }
+}
+
+impl SendError<()> {
+    pub(crate) fn with_value<T>(self, value: T) -> SendError<T> {
+        match self {
+            Self::Serialize(e) => SendError::Serialize(e),
+            Self::Socket(e) => SendError::Socket(e.with_value(value)),
+        }
+    }
+}
+
 impl SocketError<()> {
     pub(crate) fn with_value<T>(self, value: T) -> SocketError<T> {
         match self {
@@ -168,7 +217,10 @@ impl From<&Error> for Option<EIoDisconnectReason> {
             Error::Serialize(_) | Error::InvalidPacketType | Error::InvalidEventName => {
                 Some(PacketParsingError)
             }
-            Error::Adapter(_) | Error::InvalidNamespace => None,
+            Error::Adapter(_)
+            | Error::InvalidNamespace
+            | Error::UnknownNamespace(_)
+            | Error::NamespaceNotJoined(_) => None,
         }
     }
 }
@@ -268,6 +268,18 @@ impl Extensions {
     pub fn len(&self) -> usize {
         self.map.len()
     }
+
+    /// Moves all the extensions to a new extension set, leaving this one empty.
+    pub(crate) fn take(&self) -> Extensions {
+        let ext = Extensions::new();
+        let keys: Vec<TypeId> = self.map.iter().map(|e| *e.key()).collect();
+        for key in keys {
+            if let Some((key, val)) = self.map.remove(&key) {
+                ext.map.insert(key, val);
+            }
+        }
+        ext
+    }
 }
 
 impl fmt::Debug for Extensions {
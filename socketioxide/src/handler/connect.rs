@@ -6,6 +6,18 @@
 //!
 //! Handlers can be _optionally_ async.
 //!
+//! ## Ordering guarantee
+//! Events sent by the client are only dispatched to the [message handlers](super::message) once the connect handler
+//! has completed. If the handler is async, its future is awaited first. Therefore the rooms joined and the
+//! message handlers registered in the connect handler are effective for the first event received from the client.
+//!
+//! Acknowledgements are not delayed, so the connect handler can wait for the acknowledgement of an emitted message.
+//! However, long-running tasks (e.g. a loop emitting messages periodically) should be spawned
+//! in a separate task to not delay the events of the socket indefinitely.
+//! The events received in the meantime are buffered up to a bound,
+//! see [`SocketIoBuilder::connect_buffer`](crate::SocketIoBuilder::connect_buffer).
+//! If the future of the handler panics, the buffered events are still dispatched.
+//!
 //! ## Example with sync closures
 //! ```rust
 //! # use socketioxide::SocketIo;
@@ -52,9 +64,10 @@
 //! io.ns("/", handler);
 //! io.ns("/admin", handler);
 //! ```
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use futures::Future;
+use futures::{future::BoxFuture, Future};
+use serde_json::Value;
 
 use crate::{adapter::Adapter, socket::Socket};
 
@@ -81,9 +94,26 @@ where
     H: ConnectHandler<A, T> + Send + Sync + 'static,
     T: Send + Sync + 'static,
 {
-    #[inline(always)]
     fn call(&self, s: Arc<Socket<A>>, auth: Option<String>) {
-        self.handler.call(s, auth);
+        match self.handler.call_with_completion(s.clone(), auth) {
+            Some(fut) => {
+                let spawner = s.config.spawner.clone();
+                spawner.spawn(async move {
+                    // The socket is set ready even if the future panics or is cancelled
+                    let _ready = ReadyGuard(s);
+                    fut.await;
+                });
+            }
+            None => s.set_ready(),
+        }
+    }
+}
+
+/// Marks the socket as ready when dropped, once the connect handler has completed.
+struct ReadyGuard<A: Adapter>(Arc<Socket<A>>);
+impl<A: Adapter> Drop for ReadyGuard<A> {
+    fn drop(&mut self) {
+        self.0.set_ready();
     }
 }
 
@@ -102,6 +132,38 @@ pub trait FromConnectParts<A: Adapter>: Sized {
     fn from_connect_parts(s: &Arc<Socket<A>>, auth: &Option<String>) -> Result<Self, Self::Error>;
 }
 
+/// The context of a connection given to the [connect middlewares](crate::SocketIo::use_connect).
+///
+/// It bundles the data of the HTTP handshake request and the socket.io auth payload,
+/// so that a middleware can authenticate the clients with a cookie, a query param or a token.
+#[derive(Debug)]
+pub struct ConnectContext<'a> {
+    /// The headers of the HTTP handshake request
+    pub headers: &'a http::HeaderMap,
+    /// The query string of the HTTP handshake request
+    pub query: Option<&'a str>,
+    /// The auth payload sent by the client in its connect packet.
+    /// It is [`Value::Null`] if there is no payload or if it can't be deserialized.
+    pub auth: Value,
+    /// The remote address of the client, if a [`SocketAddr`] was added
+    /// to the extensions of the handshake request (e.g. by a tower layer of the HTTP server).
+    pub address: Option<SocketAddr>,
+}
+
+impl<'a> ConnectContext<'a> {
+    pub(crate) fn new(req_parts: &'a http::request::Parts, auth: Option<&str>) -> Self {
+        let auth = auth
+            .and_then(|auth| serde_json::from_str(auth).ok())
+            .unwrap_or(Value::Null);
+        Self {
+            headers: &req_parts.headers,
+            query: req_parts.uri.query(),
+            auth,
+            address: req_parts.extensions.get::<SocketAddr>().copied(),
+        }
+    }
+}
+
 /// Define a handler for the connect event.
 /// It is implemented for closures with up to 16 arguments. They must implement the [`FromConnectParts`] trait.
 ///
@@ -111,6 +173,18 @@ pub trait ConnectHandler<A: Adapter, T>: Send + Sync + 'static {
     /// Call the handler with the given arguments.
     fn call(&self, s: Arc<Socket<A>>, auth: Option<String>);
 
+    /// Call the handler with the given arguments and return the future to await
+    /// before dispatching the events received from the client, if the handler is async.
+    #[doc(hidden)]
+    fn call_with_completion(
+        &self,
+        s: Arc<Socket<A>>,
+        auth: Option<String>,
+    ) -> Option<BoxFuture<'static, ()>> {
+        self.call(s, auth);
+        None
+    }
+
     #[doc(hidden)]
     fn phantom(&self) -> std::marker::PhantomData<T> {
         std::marker::PhantomData
@@ -137,20 +211,29 @@ macro_rules! impl_handler_async {
             $( $ty: FromConnectParts<A> + Send, )*
         {
             fn call(&self, s: Arc<Socket<A>>, auth: Option<String>) {
+                let spawner = s.config.spawner.clone();
+                if let Some(fut) = self.call_with_completion(s, auth) {
+                    spawner.spawn(fut);
+                }
+            }
+
+            fn call_with_completion(
+                &self,
+                s: Arc<Socket<A>>,
+                auth: Option<String>,
+            ) -> Option<BoxFuture<'static, ()>> {
                 $(
                     let $ty = match $ty::from_connect_parts(&s, &auth) {
                         Ok(v) => v,
                         Err(_e) => {
                             #[cfg(feature = "tracing")]
                             tracing::error!("Error while extracting data: {}", _e);
-                            return;
+                            return None;
                         },
                     };
                 )*
 
-                let fut = (self.clone())($($ty,)*);
-                tokio::spawn(fut);
-
+                Some(Box::pin((self.clone())($($ty,)*)))
             }
         }
     };
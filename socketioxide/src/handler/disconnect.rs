@@ -157,7 +157,7 @@ macro_rules! impl_handler_async {
                 )*
 
                 let fut = (self.clone())($($ty,)*);
-                tokio::spawn(fut);
+                s.config.spawner.spawn(fut);
 
             }
         }
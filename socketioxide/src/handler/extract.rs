@@ -10,6 +10,9 @@
 //!     - for [`MessageHandler`](super::MessageHandler): extracts and deserialize to json the message data
 //! * [`SocketRef`]: extracts a reference to the [`Socket`]
 //! * [`Bin`]: extract a binary payload for a given message. Because it consumes the event it should be the last argument
+//! * [`Binary`]: same as [`Bin`] but with bounds checked accessors to the attachments
+//! * [`BinaryMeta`]: extracts the number of binary attachments declared by the message and the number actually received
+//! * [`IdempotencyKey`]: extracts the idempotency key attached to the message
 //! * [`AckSender`]: Can be used to send an ack response to the current message event
 //! * [`ProtocolVersion`](crate::ProtocolVersion): extracts the protocol version
 //! * [`TransportType`](crate::TransportType): extracts the transport type
@@ -84,16 +87,17 @@
 //! // Use the service with your favorite http server
 use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::message::FromMessageParts;
+use super::message::{FromMessageParts, MessageMeta};
 use super::FromDisconnectParts;
 use super::{connect::FromConnectParts, message::FromMessage};
 use crate::errors::{DisconnectError, SendError};
 use crate::socket::DisconnectReason;
 use crate::{
     adapter::{Adapter, LocalAdapter},
-    packet::Packet,
-    socket::Socket,
+    packet::{take_idempotency_key, Packet},
+    socket::{OwedAck, Socket},
 };
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
@@ -102,8 +106,12 @@ use serde_json::Value;
 #[cfg_attr(docsrs, doc(cfg(feature = "state")))]
 pub use state_extract::*;
 
-/// Utility function to unwrap an array with a single element
-fn upwrap_array(v: &mut Value) {
+/// Utility function to unwrap an array with a single element,
+/// unless it is disabled for the namespace of the socket
+fn upwrap_array<A: Adapter>(s: &Socket<A>, v: &mut Value) {
+    if !s.ns.unwrap_single_element() {
+        return;
+    }
     match v {
         Value::Array(vec) if vec.len() == 1 => {
             *v = vec.pop().unwrap();
@@ -112,9 +120,38 @@ fn upwrap_array(v: &mut Value) {
     }
 }
 
+/// Utility function to deserialize the data of an event after unwrapping the single element arrays.
+///
+/// If the unwrapped data is still an array with a single element that can't be deserialized,
+/// its element is deserialized instead. It allows the types that can't be deserialized from a sequence,
+/// such as tagged enums, to be sent wrapped in an array by the client, e.g. `socket.emit("msg", [{ type: "ping" }])`.
+fn deserialize_data<T: DeserializeOwned, A: Adapter>(
+    s: &Socket<A>,
+    v: &mut Value,
+) -> Result<T, serde_json::Error> {
+    upwrap_array(s, v);
+    match serde_json::from_value(v.clone()) {
+        Err(e) if s.ns.unwrap_single_element() => match v {
+            Value::Array(vec) if vec.len() == 1 => {
+                serde_json::from_value(vec[0].clone()).map_err(|_| e)
+            }
+            _ => Err(e),
+        },
+        res => res,
+    }
+}
+
 /// An Extractor that returns the serialized auth data without checking errors.
 /// If a deserialization error occurs, the [`ConnectHandler`](super::ConnectHandler) won't be called
 /// and an error log will be print if the `tracing` feature is enabled.
+///
+/// For a [`MessageHandler`](super::MessageHandler), if the message has a single argument
+/// the array of arguments is unwrapped and the argument is deserialized directly.
+/// It can be disabled with [`SocketIo::set_unwrap_single_element`](crate::SocketIo::set_unwrap_single_element).
+///
+/// A single argument that is itself an array with a single element is also unwrapped if it can't be
+/// deserialized as is. Therefore tagged enums (e.g. `#[serde(tag = "type")]`) are deserialized
+/// whether the client sends them as the argument or wrapped in an array.
 pub struct Data<T: DeserializeOwned>(pub T);
 impl<T, A> FromConnectParts<A> for Data<T>
 where
@@ -136,17 +173,18 @@ where
 {
     type Error = serde_json::Error;
     fn from_message_parts(
-        _: &Arc<Socket<A>>,
+        s: &Arc<Socket<A>>,
         v: &mut serde_json::Value,
         _: &mut Vec<Vec<u8>>,
         _: &Option<i64>,
     ) -> Result<Self, Self::Error> {
-        upwrap_array(v);
-        serde_json::from_value(v.clone()).map(Data)
+        deserialize_data(s, v).map(Data)
     }
 }
 
 /// An Extractor that returns the deserialized data related to the event.
+///
+/// The single element arrays are unwrapped in the same way as with the [`Data`] extractor.
 pub struct TryData<T: DeserializeOwned>(pub Result<T, serde_json::Error>);
 
 impl<T, A> FromConnectParts<A> for TryData<T>
@@ -170,13 +208,12 @@ where
 {
     type Error = Infallible;
     fn from_message_parts(
-        _: &Arc<Socket<A>>,
+        s: &Arc<Socket<A>>,
         v: &mut serde_json::Value,
         _: &mut Vec<Vec<u8>>,
         _: &Option<i64>,
     ) -> Result<Self, Infallible> {
-        upwrap_array(v);
-        Ok(TryData(serde_json::from_value(v.clone())))
+        Ok(TryData(deserialize_data(s, v)))
     }
 }
 /// An Extractor that returns a reference to a [`Socket`].
@@ -220,6 +257,13 @@ impl<A: Adapter> PartialEq for SocketRef<A> {
         self.0.id == other.0.id
     }
 }
+impl<A: Adapter> Eq for SocketRef<A> {}
+impl<A: Adapter> std::hash::Hash for SocketRef<A> {
+    #[inline(always)]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.id.hash(state);
+    }
+}
 impl<A: Adapter> From<Arc<Socket<A>>> for SocketRef<A> {
     #[inline(always)]
     fn from(socket: Arc<Socket<A>>) -> Self {
@@ -235,6 +279,31 @@ impl<A: Adapter> SocketRef<A> {
     pub fn disconnect(self) -> Result<(), DisconnectError> {
         self.0.disconnect()
     }
+
+    /// Disconnect the socket from the current namespace with a custom [`DisconnectReason`],
+    ///
+    /// It will also call the disconnect handler with this reason if it is set.
+    #[inline(always)]
+    pub fn disconnect_with(self, reason: DisconnectReason) -> Result<(), DisconnectError> {
+        self.0.disconnect_with(reason)
+    }
+
+    /// Resumes the dispatch of the events received from the client after a [`Socket::pause`].
+    ///
+    /// See [`Socket::resume`] for more details.
+    #[inline(always)]
+    pub fn resume(&self) {
+        self.0.clone().resume()
+    }
+
+    /// Gracefully disconnect the socket from the current namespace,
+    /// after flushing its outbound queue for at most the given `timeout`.
+    ///
+    /// See [`Socket::drain_and_close`] for more details.
+    #[inline(always)]
+    pub async fn drain_and_close(self, timeout: Duration) -> Result<(), DisconnectError> {
+        self.0.drain_and_close(timeout).await
+    }
 }
 
 /// An Extractor that returns the binary data of the message.
@@ -252,6 +321,198 @@ impl<A: Adapter> FromMessage<A> for Bin {
     }
 }
 
+/// An Extractor that returns the binary attachments of the message, with bounds checked accessors.
+/// Because it consumes the event it should be the last argument.
+/// If there is no binary data, it is empty.
+///
+/// It derefs to a slice of attachments and the raw attachments can be taken with [`Binary::into_inner`].
+///
+/// ## Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::*};
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     socket.on("upload", |ack: AckSender, bin: Binary| {
+///         match bin.get(0) {
+///             Some(file) => println!("received a file of {} bytes", file.len()),
+///             None => println!("missing file"),
+///         }
+///         ack.bin(bin.into_inner()).send("ok").ok();
+///     });
+/// });
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Binary(Vec<Vec<u8>>);
+
+impl Binary {
+    /// Returns the attachment at the given index, or `None` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.0.get(index).map(Vec::as_slice)
+    }
+
+    /// Returns the number of attachments.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if there is no attachment.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the attachments.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.0.iter().map(Vec::as_slice)
+    }
+
+    /// Returns the raw attachments.
+    #[inline]
+    pub fn into_inner(self) -> Vec<Vec<u8>> {
+        self.0
+    }
+}
+
+impl std::ops::Deref for Binary {
+    type Target = [Vec<u8>];
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<Vec<u8>>> for Binary {
+    fn from(bin: Vec<Vec<u8>>) -> Self {
+        Self(bin)
+    }
+}
+impl From<Binary> for Vec<Vec<u8>> {
+    fn from(bin: Binary) -> Self {
+        bin.0
+    }
+}
+
+impl<A: Adapter> FromMessage<A> for Binary {
+    type Error = Infallible;
+    fn from_message(
+        _: Arc<Socket<A>>,
+        _: serde_json::Value,
+        bin: Vec<Vec<u8>>,
+        _: Option<i64>,
+    ) -> Result<Self, Infallible> {
+        Ok(Binary(bin))
+    }
+}
+
+/// An Extractor that returns the number of binary attachments declared in the message header
+/// and the number of attachments actually received.
+///
+/// It can be used to validate the binary payload before indexing it or to detect truncated uploads.
+/// If there is no binary data, both counts are 0.
+///
+/// ## Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::*};
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     socket.on("upload", |meta: BinaryMeta, Bin(bin)| {
+///         if meta.declared != meta.received {
+///             println!("truncated upload: {}/{} attachments", meta.received, meta.declared);
+///         }
+///     });
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryMeta {
+    /// The number of attachments declared in the message header
+    pub declared: usize,
+    /// The number of attachments received
+    pub received: usize,
+}
+impl BinaryMeta {
+    pub(crate) fn new(count: usize) -> Self {
+        Self {
+            declared: count,
+            received: count,
+        }
+    }
+}
+impl<A: Adapter> FromMessageParts<A> for BinaryMeta {
+    type Error = Infallible;
+    fn from_message_parts(
+        _: &Arc<Socket<A>>,
+        _: &mut serde_json::Value,
+        bin: &mut Vec<Vec<u8>>,
+        _: &Option<i64>,
+    ) -> Result<Self, Infallible> {
+        Ok(BinaryMeta::new(bin.len()))
+    }
+    fn from_message_parts_with_meta(
+        _: &Arc<Socket<A>>,
+        _: &mut serde_json::Value,
+        _: &mut Vec<Vec<u8>>,
+        _: &Option<i64>,
+        meta: &MessageMeta,
+    ) -> Result<Self, Infallible> {
+        Ok(meta.binary)
+    }
+}
+
+/// An Extractor that returns the idempotency key attached to the message,
+/// if its last argument is an object with a single `_idempotencyKey` string field.
+/// Keys can be attached to the emitted messages with [`ConfOperators::with_id`](crate::operators::ConfOperators::with_id).
+///
+/// The key argument is removed from the message data before any extractor is called,
+/// so the other extractors (e.g. [`Data`]) never see it wherever this extractor is placed.
+///
+/// Dropping the duplicated messages is the responsibility of the application.
+///
+/// ## Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::*};
+/// # use std::{collections::HashSet, sync::{Arc, Mutex}};
+/// # use serde_json::Value;
+/// let (_, io) = SocketIo::new_svc();
+/// let seen = Arc::new(Mutex::new(HashSet::new()));
+/// io.ns("/", move |socket: SocketRef| {
+///     let seen = seen.clone();
+///     socket.on("order", move |IdempotencyKey(key), Data::<Value>(order)| {
+///         if let Some(key) = key {
+///             if !seen.lock().unwrap().insert(key) {
+///                 // This order was already received
+///                 return;
+///             }
+///         }
+///         println!("new order: {order}");
+///     });
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey(pub Option<String>);
+impl<A: Adapter> FromMessageParts<A> for IdempotencyKey {
+    type Error = Infallible;
+    fn from_message_parts(
+        _: &Arc<Socket<A>>,
+        v: &mut serde_json::Value,
+        _: &mut Vec<Vec<u8>>,
+        _: &Option<i64>,
+    ) -> Result<Self, Infallible> {
+        Ok(IdempotencyKey(take_idempotency_key(v)))
+    }
+    fn from_message_parts_with_meta(
+        _: &Arc<Socket<A>>,
+        _: &mut serde_json::Value,
+        _: &mut Vec<Vec<u8>>,
+        _: &Option<i64>,
+        meta: &MessageMeta,
+    ) -> Result<Self, Infallible> {
+        Ok(IdempotencyKey(meta.idempotency_key.clone()))
+    }
+}
+
 /// An Extractor to send an ack response corresponding to the current event.
 /// If the client sent a normal message without expecting an ack, the ack callback will do nothing.
 #[derive(Debug)]
@@ -259,6 +520,7 @@ pub struct AckSender<A: Adapter = LocalAdapter> {
     binary: Vec<Vec<u8>>,
     socket: Arc<Socket<A>>,
     ack_id: Option<i64>,
+    _owed: Option<OwedAck<A>>,
 }
 impl<A: Adapter> FromMessageParts<A> for AckSender<A> {
     type Error = Infallible;
@@ -275,20 +537,38 @@ impl<A: Adapter> AckSender<A> {
     pub(crate) fn new(socket: Arc<Socket<A>>, ack_id: Option<i64>) -> Self {
         Self {
             binary: vec![],
+            _owed: ack_id.map(|_| socket.owe_ack()),
             socket,
             ack_id,
         }
     }
 
     /// Add binary data to the ack response.
+    ///
+    /// Use [`AckSender::send_binary`] to send the binary payloads as the only ack arguments.
     pub fn bin(mut self, bin: Vec<Vec<u8>>) -> Self {
         self.binary = bin;
         self
     }
 
     /// Send the ack response to the client.
+    ///
+    /// If the data can't be serialized, an error ack `[{ "message": err.to_string() }]` is sent instead
+    /// so that the client doesn't wait for the ack until its timeout,
+    /// and a [`SendError::Serialize`] is returned.
     pub fn send<T: Serialize>(self, data: T) -> Result<(), SendError<T>> {
-        use crate::socket::PermitIteratorExt;
+        self.send_ack(data, false)
+    }
+
+    /// Send the ack response to the client with the [binary payloads](AckSender::bin) as its only arguments,
+    /// as the reference client does for an ack with only binary arguments.
+    ///
+    /// Unlike `send(())` which sends a `null` argument before the binary payloads.
+    pub fn send_binary(self) -> Result<(), SendError<()>> {
+        self.send_ack((), true)
+    }
+
+    fn send_ack<T: Serialize>(self, data: T, binary_only: bool) -> Result<(), SendError<T>> {
         if let Some(ack_id) = self.ack_id {
             let permits = match self.socket.reserve(1 + self.binary.len()) {
                 Ok(permits) => permits,
@@ -299,14 +579,29 @@ impl<A: Adapter> AckSender<A> {
                 }
             };
             let ns = self.socket.ns();
-            let data = serde_json::to_value(data)?;
-            let packet = if self.binary.is_empty() {
+            let (data, res) = match serde_json::to_value(&data) {
+                // A binary only ack is sent without any other argument than the binary payloads
+                Ok(Value::Null) if binary_only => (Value::Array(vec![]), Ok(())),
+                Ok(data) => (data, Ok(())),
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error serializing ack {ack_id}, sending an error ack: {e}");
+                    let payload = serde_json::json!([{ "message": e.to_string() }]);
+                    let err = SendError::Serialize(e);
+                    self.socket.notify_error(ack_id, &err);
+                    (payload, Err(err.with_value(data)))
+                }
+            };
+            let data = self.socket.ns.transform_ack(data);
+            let mut packet = if self.binary.is_empty() || res.is_err() {
                 Packet::ack(ns, data, ack_id)
             } else {
                 Packet::bin_ack(ns, data, self.binary, ack_id)
             };
+            self.socket.prepare_packet(&mut packet);
+            self.socket.notify_ack_sent(&packet);
             permits.emit(packet);
-            Ok(())
+            res
         } else {
             Ok(())
         }
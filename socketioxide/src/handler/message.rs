@@ -71,21 +71,66 @@
 //!     s.on("event_2", on_event);
 //! });
 //! ```
+//!
+//! ## Example with a handler returning a [`Result`]
+//! If the client requested an acknowledgement, the returned [`Result`] is sent back with the error-first convention:
+//! `Ok(data)` is acknowledged with `[null, data]` and `Err(err)` with `[{ "message": err.to_string() }]`.
+//! Any type implementing [`IntoAck`] can be returned.
+//! ```rust
+//! # use socketioxide::SocketIo;
+//! # use socketioxide::extract::*;
+//! async fn on_event(Data(data): Data<String>) -> Result<String, std::num::ParseIntError> {
+//!     let n: u32 = data.parse()?;
+//!     Ok(format!("received {n}"))
+//! }
+//! let (svc, io) = SocketIo::new_svc();
+//! io.ns("/", |s: SocketRef| {
+//!     s.on("event", on_event);
+//! });
+//! ```
 use std::sync::Arc;
 
 use futures::Future;
+use serde::Serialize;
 use serde_json::Value;
 
 use crate::adapter::Adapter;
+use crate::errors::SendError;
+use crate::extract::{AckSender, BinaryMeta};
+use crate::packet::take_idempotency_key;
 use crate::socket::Socket;
 
 use super::MakeErasedHandler;
 
-/// A Type Erased [`MessageHandler`] so it can be stored in a HashMap
-pub(crate) type BoxedMessageHandler<A> = Box<dyn ErasedMessageHandler<A>>;
+/// A Type Erased [`MessageHandler`] so it can be stored in a HashMap, possibly under several event names
+pub(crate) type BoxedMessageHandler<A> = Arc<dyn ErasedMessageHandler<A>>;
 
 pub(crate) trait ErasedMessageHandler<A: Adapter>: Send + Sync + 'static {
-    fn call(&self, s: Arc<Socket<A>>, v: Value, p: Vec<Vec<u8>>, ack_id: Option<i64>);
+    fn call(
+        &self,
+        s: Arc<Socket<A>>,
+        v: Value,
+        p: Vec<Vec<u8>>,
+        ack_id: Option<i64>,
+        meta: MessageMeta,
+    );
+}
+
+/// The metadata of a received message, passed to the extractors along with its arguments.
+#[doc(hidden)]
+#[derive(Debug, Clone)]
+pub struct MessageMeta {
+    pub(crate) binary: BinaryMeta,
+    pub(crate) idempotency_key: Option<String>,
+}
+impl MessageMeta {
+    /// Creates the metadata of a message, removing its idempotency key argument from the data if there is one.
+    pub(crate) fn new(v: &mut Value, binary: BinaryMeta) -> Self {
+        Self {
+            binary,
+            idempotency_key: take_idempotency_key(v),
+        }
+    }
 }
 
 /// Define a handler for the connect event.
@@ -103,6 +148,19 @@ pub trait MessageHandler<A: Adapter, T>: Send + Sync + 'static {
     /// Call the handler with the given arguments
     fn call(&self, s: Arc<Socket<A>>, v: Value, p: Vec<Vec<u8>>, ack_id: Option<i64>);
 
+    /// Call the handler with the given arguments and the [`MessageMeta`] of the received packet
+    #[doc(hidden)]
+    fn call_with_meta(
+        &self,
+        s: Arc<Socket<A>>,
+        v: Value,
+        p: Vec<Vec<u8>>,
+        ack_id: Option<i64>,
+        _meta: MessageMeta,
+    ) {
+        self.call(s, v, p, ack_id);
+    }
+
     #[doc(hidden)]
     fn phantom(&self) -> std::marker::PhantomData<T> {
         std::marker::PhantomData
@@ -115,8 +173,8 @@ where
     H: MessageHandler<A, T>,
     A: Adapter,
 {
-    pub fn new_message_boxed(inner: H) -> Box<dyn ErasedMessageHandler<A>> {
-        Box::new(MakeErasedHandler::new(inner))
+    pub fn new_message_boxed(inner: H) -> Arc<dyn ErasedMessageHandler<A>> {
+        Arc::new(MakeErasedHandler::new(inner))
     }
 }
 
@@ -127,8 +185,67 @@ where
     A: Adapter,
 {
     #[inline(always)]
-    fn call(&self, s: Arc<Socket<A>>, v: Value, p: Vec<Vec<u8>>, ack_id: Option<i64>) {
-        self.handler.call(s, v, p, ack_id);
+    fn call(
+        &self,
+        s: Arc<Socket<A>>,
+        v: Value,
+        p: Vec<Vec<u8>>,
+        ack_id: Option<i64>,
+        meta: MessageMeta,
+    ) {
+        self.handler.call_with_meta(s, v, p, ack_id, meta);
+    }
+}
+
+/// A value returned by a [`MessageHandler`] that is sent back to the client
+/// when it requested an acknowledgement.
+///
+/// It is implemented for `()`, which doesn't send anything, and for [`Result`],
+/// which is sent with the error-first convention:
+/// * `Ok(data)` is acknowledged with `[null, data]`
+/// * `Err(err)` is acknowledged with `[{ "message": err.to_string() }]`
+///
+/// If the handler also sends an ack with the [`AckSender`] extractor, only the first one is
+/// considered by the client.
+pub trait IntoAck: Send + 'static {
+    /// Whether an acknowledgement is sent by [`IntoAck::into_ack`].
+    /// A closing socket waits for the results of the running async handlers that send one.
+    const SENDS_ACK: bool = true;
+
+    /// Send the acknowledgement to the client through the given socket.
+    /// It is only called when the client requested an acknowledgement.
+    fn into_ack<A: Adapter>(self, s: Arc<Socket<A>>, ack_id: i64);
+}
+
+impl IntoAck for () {
+    const SENDS_ACK: bool = false;
+
+    fn into_ack<A: Adapter>(self, _: Arc<Socket<A>>, _: i64) {}
+}
+
+impl<T, E> IntoAck for Result<T, E>
+where
+    T: Serialize + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    fn into_ack<A: Adapter>(self, s: Arc<Socket<A>>, ack_id: i64) {
+        let ack = AckSender::new(s.clone(), Some(ack_id));
+        let res = match self {
+            Ok(data) => ack
+                .send((Value::Null, data))
+                .map_err(SendError::discard_value),
+            Err(err) => ack
+                .send(serde_json::json!([{ "message": err.to_string() }]))
+                .map_err(SendError::discard_value),
+        };
+        if let Err(e) = res {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error while sending the handler result ack: {e:?}");
+            // The serialization errors are already reported by the ack sender
+            if !matches!(e, SendError::Serialize(_)) {
+                s.notify_error(ack_id, &e);
+            }
+        }
     }
 }
 
@@ -168,6 +285,18 @@ pub trait FromMessageParts<A: Adapter>: Sized {
         p: &mut Vec<Vec<u8>>,
         ack_id: &Option<i64>,
     ) -> Result<Self, Self::Error>;
+
+    /// Extract the arguments from the message event with the [`MessageMeta`] of the received packet.
+    #[doc(hidden)]
+    fn from_message_parts_with_meta(
+        s: &Arc<Socket<A>>,
+        v: &mut Value,
+        p: &mut Vec<Vec<u8>>,
+        ack_id: &Option<i64>,
+        _meta: &MessageMeta,
+    ) -> Result<Self, Self::Error> {
+        Self::from_message_parts(s, v, p, ack_id)
+    }
 }
 
 /// A trait used to extract and **consume** arguments from the message event.
@@ -193,6 +322,18 @@ pub trait FromMessage<A: Adapter, M = private::ViaRequest>: Sized {
         p: Vec<Vec<u8>>,
         ack_id: Option<i64>,
     ) -> Result<Self, Self::Error>;
+
+    /// Extract the arguments from the message event with the [`MessageMeta`] of the received packet.
+    #[doc(hidden)]
+    fn from_message_with_meta(
+        s: Arc<Socket<A>>,
+        v: Value,
+        p: Vec<Vec<u8>>,
+        ack_id: Option<i64>,
+        _meta: &MessageMeta,
+    ) -> Result<Self, Self::Error> {
+        Self::from_message(s, v, p, ack_id)
+    }
 }
 
 /// All the types that implement [`FromMessageParts`] also implement [`FromMessage`]
@@ -210,29 +351,57 @@ where
     ) -> Result<Self, Self::Error> {
         Self::from_message_parts(&s, &mut v, &mut p, &ack_id)
     }
+
+    fn from_message_with_meta(
+        s: Arc<Socket<A>>,
+        mut v: Value,
+        mut p: Vec<Vec<u8>>,
+        ack_id: Option<i64>,
+        meta: &MessageMeta,
+    ) -> Result<Self, Self::Error> {
+        Self::from_message_parts_with_meta(&s, &mut v, &mut p, &ack_id, meta)
+    }
 }
 
 /// Empty Async handler
 impl<A, F, Fut> MessageHandler<A, (private::Async,)> for F
 where
     F: FnOnce() -> Fut + Send + Sync + Clone + 'static,
-    Fut: Future<Output = ()> + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: IntoAck,
     A: Adapter,
 {
-    fn call(&self, _: Arc<Socket<A>>, _: Value, _: Vec<Vec<u8>>, _: Option<i64>) {
+    fn call(&self, s: Arc<Socket<A>>, _: Value, _: Vec<Vec<u8>>, ack_id: Option<i64>) {
+        let spawner = s.config.spawner.clone();
+        // The ack is owed to the client until the result of the handler is sent
+        let ack = ack_id
+            .filter(|_| Fut::Output::SENDS_ACK)
+            .map(|id| (s.owe_ack(), id));
         let fut = (self.clone())();
-        tokio::spawn(fut);
+        let fut = async move {
+            let res = fut.await;
+            if let Some((_owed, ack_id)) = ack {
+                res.into_ack(s, ack_id);
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::in_current_span(fut);
+        spawner.spawn(fut);
     }
 }
 
 /// Empty Sync handler
-impl<A, F> MessageHandler<A, (private::Sync,)> for F
+impl<A, F, R> MessageHandler<A, (private::Sync,)> for F
 where
-    F: FnOnce() + Send + Sync + Clone + 'static,
+    F: FnOnce() -> R + Send + Sync + Clone + 'static,
+    R: IntoAck,
     A: Adapter,
 {
-    fn call(&self, _: Arc<Socket<A>>, _: Value, _: Vec<Vec<u8>>, _: Option<i64>) {
-        (self.clone())();
+    fn call(&self, s: Arc<Socket<A>>, _: Value, _: Vec<Vec<u8>>, ack_id: Option<i64>) {
+        let res = (self.clone())();
+        if let Some(ack_id) = ack_id {
+            res.into_ack(s, ack_id);
+        }
     }
 }
 
@@ -244,14 +413,20 @@ macro_rules! impl_async_handler {
         impl<A, F, M, $($ty,)* $last, Fut> MessageHandler<A, (private::Async, M, $($ty,)* $last,)> for F
         where
             F: FnOnce($($ty,)* $last,) -> Fut + Send + Sync + Clone + 'static,
-            Fut: Future<Output = ()> + Send + 'static,
+            Fut: Future + Send + 'static,
+            Fut::Output: IntoAck,
             A: Adapter,
             $( $ty: FromMessageParts<A> + Send, )*
             $last: FromMessage<A, M> + Send,
         {
-            fn call(&self, s: Arc<Socket<A>>, mut v: Value, mut p: Vec<Vec<u8>>, ack_id: Option<i64>) {
+            fn call(&self, s: Arc<Socket<A>>, mut v: Value, p: Vec<Vec<u8>>, ack_id: Option<i64>) {
+                let meta = MessageMeta::new(&mut v, BinaryMeta::new(p.len()));
+                self.call_with_meta(s, v, p, ack_id, meta);
+            }
+
+            fn call_with_meta(&self, s: Arc<Socket<A>>, mut v: Value, mut p: Vec<Vec<u8>>, ack_id: Option<i64>, meta: MessageMeta) {
                 $(
-                    let $ty = match $ty::from_message_parts(&s, &mut v, &mut p, &ack_id) {
+                    let $ty = match $ty::from_message_parts_with_meta(&s, &mut v, &mut p, &ack_id, &meta) {
                         Ok(v) => v,
                         Err(_e) => {
                             #[cfg(feature = "tracing")]
@@ -260,7 +435,12 @@ macro_rules! impl_async_handler {
                         },
                     };
                 )*
-                let last = match $last::from_message(s, v, p, ack_id) {
+                // The ack is owed to the client until the result of the handler is sent
+                let ack = ack_id
+                    .filter(|_| Fut::Output::SENDS_ACK)
+                    .map(|id| (s.owe_ack(), s.clone(), id));
+                let spawner = s.config.spawner.clone();
+                let last = match $last::from_message_with_meta(s, v, p, ack_id, &meta) {
                     Ok(v) => v,
                     Err(_e) => {
                         #[cfg(feature = "tracing")]
@@ -270,7 +450,15 @@ macro_rules! impl_async_handler {
                 };
 
                 let fut = (self.clone())($($ty,)* last);
-                tokio::spawn(fut);
+                let fut = async move {
+                    let res = fut.await;
+                    if let Some((_owed, s, ack_id)) = ack {
+                        res.into_ack(s, ack_id);
+                    }
+                };
+                #[cfg(feature = "tracing")]
+                let fut = tracing::Instrument::in_current_span(fut);
+                spawner.spawn(fut);
             }
         }
     };
@@ -280,26 +468,36 @@ macro_rules! impl_handler {
         [$($ty:ident),*], $last:ident
     ) => {
         #[allow(non_snake_case, unused)]
-        impl<A, F, M, $($ty,)* $last> MessageHandler<A, (private::Sync, M, $($ty,)* $last,)> for F
+        impl<A, F, M, R, $($ty,)* $last> MessageHandler<A, (private::Sync, M, $($ty,)* $last,)> for F
         where
-            F: FnOnce($($ty,)* $last,) + Send + Sync + Clone + 'static,
+            F: FnOnce($($ty,)* $last,) -> R + Send + Sync + Clone + 'static,
+            R: IntoAck,
             A: Adapter,
             $( $ty: FromMessageParts<A> + Send, )*
             $last: FromMessage<A, M> + Send,
         {
-            fn call(&self, s: Arc<Socket<A>>, mut v: Value, mut p: Vec<Vec<u8>>, ack_id: Option<i64>) {
+            fn call(&self, s: Arc<Socket<A>>, mut v: Value, p: Vec<Vec<u8>>, ack_id: Option<i64>) {
+                let meta = MessageMeta::new(&mut v, BinaryMeta::new(p.len()));
+                self.call_with_meta(s, v, p, ack_id, meta);
+            }
+
+            fn call_with_meta(&self, s: Arc<Socket<A>>, mut v: Value, mut p: Vec<Vec<u8>>, ack_id: Option<i64>, meta: MessageMeta) {
                 $(
-                    let $ty = match $ty::from_message_parts(&s, &mut v, &mut p, &ack_id) {
+                    let $ty = match $ty::from_message_parts_with_meta(&s, &mut v, &mut p, &ack_id, &meta) {
                         Ok(v) => v,
                         Err(_) => return,
                     };
                 )*
-                let last = match $last::from_message(s, v, p, ack_id) {
+                let ack = ack_id.map(|id| (s.clone(), id));
+                let last = match $last::from_message_with_meta(s, v, p, ack_id, &meta) {
                     Ok(v) => v,
                     Err(_) => return,
                 };
 
-                (self.clone())($($ty,)* last);
+                let res = (self.clone())($($ty,)* last);
+                if let Some((s, ack_id)) = ack {
+                    res.into_ack(s, ack_id);
+                }
             }
         }
     };
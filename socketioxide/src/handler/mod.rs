@@ -7,11 +7,48 @@ pub mod extract;
 pub mod message;
 
 pub(crate) use connect::BoxedConnectHandler;
-pub use connect::{ConnectHandler, FromConnectParts};
+pub use connect::{ConnectContext, ConnectHandler, FromConnectParts};
 pub(crate) use disconnect::BoxedDisconnectHandler;
 pub use disconnect::{DisconnectHandler, FromDisconnectParts};
 pub(crate) use message::BoxedMessageHandler;
-pub use message::{FromMessage, FromMessageParts, MessageHandler};
+pub use message::{FromMessage, FromMessageParts, IntoAck, MessageHandler};
+
+use std::sync::Arc;
+
+use futures::{future::BoxFuture, Future};
+
+/// The function used to spawn the futures of the async handlers,
+/// set with [`SocketIoBuilder::spawner`](crate::SocketIoBuilder::spawner).
+///
+/// Defaults to [`tokio::spawn`] on the ambient runtime.
+#[derive(Clone)]
+pub struct Spawner(Arc<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>);
+
+impl Spawner {
+    /// Creates a new [`Spawner`] from a function that runs the given futures to completion.
+    pub fn new(spawner: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(spawner))
+    }
+
+    /// Spawns a future with the spawner.
+    pub fn spawn(&self, fut: impl Future<Output = ()> + Send + 'static) {
+        (self.0)(Box::pin(fut))
+    }
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Self::new(|fut| {
+            tokio::spawn(fut);
+        })
+    }
+}
+
+impl std::fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Spawner").finish()
+    }
+}
 /// A struct used to erase the type of a [`ConnectHandler`] or [`MessageHandler`] so it can be stored in a map
 pub(crate) struct MakeErasedHandler<H, A, T> {
     handler: H,
@@ -1,4 +1,9 @@
-use std::{borrow::Cow, sync::Arc, time::Duration};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use engineioxide::{
     config::{EngineIoConfig, EngineIoConfigBuilder},
@@ -6,17 +11,22 @@ use engineioxide::{
     sid::Sid,
     TransportType,
 };
+use serde_json::Value;
 
 use crate::{
     ack::AckStream,
-    adapter::{Adapter, LocalAdapter, Room},
+    adapter::{Adapter, BroadcastOptions, LocalAdapter, Room, RoomPattern},
     client::Client,
     extract::SocketRef,
-    handler::ConnectHandler,
+    handler::{ConnectContext, ConnectHandler, Spawner},
     layer::SocketIoLayer,
+    offline::{NoopOfflineStore, OfflineStore},
     operators::{BroadcastOperators, RoomParam},
+    packet::Packet,
+    recovery::ConnectionRecoveryConfig,
     service::SocketIoService,
-    BroadcastError, DisconnectError,
+    socket::{ConnectionEvents, OverflowPolicy},
+    BroadcastError, DisconnectError, Error,
 };
 
 /// Configuration for Socket.IO & Engine.IO
@@ -30,10 +40,91 @@ pub struct SocketIoConfig {
     /// Defaults to 5 seconds.
     pub ack_timeout: Duration,
 
+    /// The maximum amount of time a closing socket waits for the running handlers
+    /// to send the acknowledgements owed to the client, before closing the connection.
+    ///
+    /// Defaults to 500 milliseconds.
+    pub ack_flush_timeout: Duration,
+
     /// The amount of time before disconnecting a client that has not successfully joined a namespace.
     ///
     /// Defaults to 45 seconds.
     pub connect_timeout: Duration,
+
+    /// The amount of time a socket can stay without sending any event before being disconnected
+    /// with a [`DisconnectReason::IdleTimeout`](crate::socket::DisconnectReason::IdleTimeout).
+    ///
+    /// Defaults to `None` (disabled).
+    pub idle_timeout: Option<Duration>,
+
+    /// The minimum size of the binary attachments compressed with gzip
+    /// when they are sent over the polling transport to a client that supports it.
+    /// See [`SocketIoBuilder::compress_binary`] for more details.
+    ///
+    /// Defaults to `None` (disabled).
+    #[cfg_attr(docsrs, doc(cfg(feature = "compress-binary")))]
+    #[cfg(feature = "compress-binary")]
+    pub compress_binary: Option<usize>,
+
+    /// The connection state recovery configuration.
+    /// See the [`recovery`](crate::recovery) module for more details.
+    ///
+    /// Defaults to `None` (disabled).
+    pub connection_recovery: Option<ConnectionRecoveryConfig>,
+
+    /// The maximum number of sockets that can be connected to a single namespace.
+    /// New connections over this limit are rejected with a `connect_error` packet.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_sockets_per_ns: Option<usize>,
+
+    /// The maximum number of connections that can be connected to the namespaces.
+    /// A connection connected to several namespaces is counted once.
+    /// New connections over this limit are rejected with a `connect_error` packet,
+    /// the connections already connected can still join other namespaces.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_connections: Option<usize>,
+
+    /// The maximum number of namespaces created by the [`dynamic_ns`](SocketIo::dynamic_ns) handler.
+    /// Connections to new namespaces over this limit are rejected as connections to an unknown namespace.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_dynamic_namespaces: Option<usize>,
+
+    /// The maximum number of binary attachments of a packet received from a client.
+    /// Packets declaring or carrying more attachments are rejected and the connection is closed.
+    ///
+    /// Defaults to `None` (unlimited).
+    pub max_attachments: Option<usize>,
+
+    /// The store used to keep the messages emitted to disconnected sockets.
+    /// See the [`offline`](crate::offline) module for more details.
+    ///
+    /// Defaults to [`NoopOfflineStore`] (messages are dropped).
+    pub offline_store: Arc<dyn OfflineStore>,
+
+    /// The policy applied to the packets emitted to a socket whose connection buffer is full.
+    ///
+    /// Defaults to [`OutboundPolicy::Error`].
+    pub outbound_policy: OutboundPolicy,
+
+    /// The maximum number of events received from a client that are buffered while the connect handler is running.
+    /// See [`SocketIoBuilder::connect_buffer`] for more details.
+    ///
+    /// Defaults to 1024.
+    pub max_pending_events: usize,
+
+    /// The policy applied to the events received while the connect handler is running
+    /// once [`max_pending_events`](Self::max_pending_events) events are buffered.
+    ///
+    /// Defaults to [`OverflowPolicy::Disconnect`].
+    pub pending_overflow: OverflowPolicy,
+
+    /// The [`Spawner`] used to run the futures of the async handlers.
+    ///
+    /// Defaults to [`tokio::spawn`].
+    pub spawner: Spawner,
 }
 
 impl Default for SocketIoConfig {
@@ -44,11 +135,73 @@ impl Default for SocketIoConfig {
                 ..Default::default()
             },
             ack_timeout: Duration::from_secs(5),
+            ack_flush_timeout: Duration::from_millis(500),
             connect_timeout: Duration::from_secs(45),
+            idle_timeout: None,
+            #[cfg(feature = "compress-binary")]
+            compress_binary: None,
+            connection_recovery: None,
+            max_sockets_per_ns: None,
+            max_connections: None,
+            max_dynamic_namespaces: None,
+            max_attachments: None,
+            offline_store: Arc::new(NoopOfflineStore),
+            outbound_policy: OutboundPolicy::Error,
+            max_pending_events: 1024,
+            pending_overflow: OverflowPolicy::Disconnect,
+            spawner: Spawner::default(),
         }
     }
 }
 
+/// The policy applied to the connections over the limit of a namespace,
+/// set with [`SocketIo::set_max_sockets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MaxSocketsPolicy {
+    /// The connections over the limit are rejected with a `connect_error` packet.
+    Reject,
+    /// The connections over the limit are queued and admitted in their arrival order
+    /// as the connected sockets leave the namespace.
+    /// A connection still queued after the given duration is rejected with a `connect_error` packet.
+    ///
+    /// As any connection that is not connected to a namespace, a queued connection is closed
+    /// after the [`connect_timeout`](SocketIoBuilder::connect_timeout).
+    Queue(Duration),
+}
+
+/// The policy applied to the packets emitted to a socket whose connection buffer is full,
+/// set with [`SocketIoBuilder::outbound_policy`].
+///
+/// The buffer is bounded by [`max_buffer_size`](SocketIoBuilder::max_buffer_size) packets
+/// and shared by all the namespaces of a connection.
+/// Every packet that is dropped or that cannot be buffered is counted in [`Socket::dropped_packets`].
+///
+/// [`Socket::dropped_packets`]: crate::socket::Socket::dropped_packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutboundPolicy {
+    /// The emission fails with a [`SocketError::InternalChannelFull`](crate::SocketError::InternalChannelFull) error.
+    Error,
+    /// The new packet is silently dropped, as a volatile message.
+    DropNewest,
+    /// The packets are queued by the socket and sent in order as soon as there is room in the buffer.
+    /// The queue is bounded by [`max_buffer_size`](SocketIoBuilder::max_buffer_size) packets:
+    /// once it is full, its oldest event is dropped to make room for the new packet.
+    ///
+    /// Only the events of the socket are dropped, never its acknowledgements nor the packets
+    /// of the other namespaces of the connection. The packets already buffered are never dropped.
+    /// [`Socket::send_raw`](crate::socket::Socket::send_raw) frames are dropped while packets are queued.
+    DropOldest,
+    /// The emission fails with a [`SocketError::InternalChannelFull`](crate::SocketError::InternalChannelFull) error
+    /// and the socket is disconnected from the namespace with a
+    /// [`DisconnectReason::ServerNSDisconnect`](crate::socket::DisconnectReason::ServerNSDisconnect).
+    ///
+    /// The disconnect handler is called from a separate task, not while emitting.
+    /// The client is notified with a disconnect packet as soon as there is room in the buffer.
+    Disconnect,
+}
+
 /// A builder to create a [`SocketIo`] instance.
 /// It contains everything to configure the socket.io server with a [`SocketIoConfig`].
 /// It can be used to build either a Tower [`Layer`](tower::layer::Layer) or a [`Service`](tower::Service).
@@ -136,6 +289,24 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The maximum amount of time a closing socket waits for the running handlers
+    /// to send the acknowledgements owed to the client, before closing the connection.
+    ///
+    /// It applies when the server is closed with [`SocketIo::close`]: an [`AckSender`]
+    /// extracted by a handler that is still running delays the closing of the connection
+    /// until it is used or dropped, up to this duration.
+    /// Use [`Socket::drain_and_close`] to wait for a single socket.
+    ///
+    /// Defaults to 500 milliseconds.
+    ///
+    /// [`AckSender`]: crate::extract::AckSender
+    /// [`Socket::drain_and_close`]: crate::socket::Socket::drain_and_close
+    #[inline]
+    pub fn ack_flush_timeout(mut self, ack_flush_timeout: Duration) -> Self {
+        self.config.ack_flush_timeout = ack_flush_timeout;
+        self
+    }
+
     /// The amount of time before disconnecting a client that has not successfully joined a namespace.
     ///
     /// Defaults to 45 seconds.
@@ -145,6 +316,195 @@ impl<A: Adapter> SocketIoBuilder<A> {
         self
     }
 
+    /// The amount of time a socket can stay without sending any event before being disconnected
+    /// from its namespace with a [`DisconnectReason::IdleTimeout`](crate::socket::DisconnectReason::IdleTimeout).
+    ///
+    /// Unlike the [`ping_timeout`](Self::ping_timeout) that only checks that the transport is alive,
+    /// it reclaims the sockets of connected clients that stay silent.
+    /// Acknowledgements sent by the client are not considered as activity.
+    ///
+    /// Defaults to disabled.
+    #[inline]
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Compresses with gzip the binary attachments of at least `min_size` bytes
+    /// sent over the polling transport, where they are otherwise base64 encoded.
+    ///
+    /// The compression is negotiated with the client: it is only applied
+    /// to the connections opened with the `binaryCompression=gzip` query parameter.
+    /// The placeholder of each compressed attachment is flagged with `"gzip": true`,
+    /// e.g. `{"_placeholder":true,"gzip":true,"num":0}`.
+    ///
+    /// The reference socket.io client doesn't decompress the attachments,
+    /// so the client must use a custom parser that gunzips the flagged attachments
+    /// when reconstructing the packet.
+    ///
+    /// Defaults to disabled.
+    #[cfg_attr(docsrs, doc(cfg(feature = "compress-binary")))]
+    #[cfg(feature = "compress-binary")]
+    #[inline]
+    pub fn compress_binary(mut self, min_size: usize) -> Self {
+        self.config.compress_binary = Some(min_size);
+        self
+    }
+
+    /// Enables the connection state recovery.
+    ///
+    /// A socket that reconnects less than `max_disconnection_duration` after being disconnected
+    /// will rejoin its rooms and receive the packets it has missed.
+    /// At most `max_buffered_packets` packets are kept for each socket, the oldest ones being evicted first.
+    /// See the [`recovery`](crate::recovery) module for more details.
+    ///
+    /// Defaults to disabled.
+    #[inline]
+    pub fn connection_recovery(
+        mut self,
+        max_disconnection_duration: Duration,
+        max_buffered_packets: usize,
+    ) -> Self {
+        self.config.connection_recovery = Some(ConnectionRecoveryConfig {
+            max_disconnection_duration,
+            max_buffered_packets,
+        });
+        self
+    }
+
+    /// The maximum number of sockets that can be connected to a single namespace.
+    /// New connections over this limit are rejected with a `connect_error` packet.
+    ///
+    /// Defaults to unlimited.
+    #[inline]
+    pub fn max_sockets_per_ns(mut self, max_sockets_per_ns: usize) -> Self {
+        self.config.max_sockets_per_ns = Some(max_sockets_per_ns);
+        self
+    }
+
+    /// The maximum number of connections that can be connected to the namespaces.
+    /// A connection connected to several namespaces is counted once.
+    /// New connections over this limit are rejected with a `connect_error` packet,
+    /// the connections already connected can still join other namespaces.
+    ///
+    /// Defaults to unlimited.
+    #[inline]
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = Some(max_connections);
+        self
+    }
+
+    /// The maximum number of namespaces that can be created by the [`dynamic_ns`](SocketIo::dynamic_ns) handler
+    /// at the same time. Connections to new namespaces over this limit are rejected
+    /// with an `Invalid namespace` connect error, as if dynamic namespaces were disabled.
+    ///
+    /// The dynamic namespaces without any socket are removed, which frees their slot.
+    ///
+    /// Defaults to unlimited.
+    #[inline]
+    pub fn max_dynamic_namespaces(mut self, max: usize) -> Self {
+        self.config.max_dynamic_namespaces = Some(max);
+        self
+    }
+
+    /// The maximum number of binary attachments of a packet received from a client.
+    ///
+    /// The attachment count declared in the header of a binary packet is checked
+    /// before the packet is parsed and before any of its payloads is buffered,
+    /// so that a client can't make the server allocate buffers for an absurd count.
+    /// Packets over this limit are rejected and the connection is closed.
+    ///
+    /// Defaults to unlimited.
+    #[inline]
+    pub fn max_attachments(mut self, max_attachments: usize) -> Self {
+        self.config.max_attachments = Some(max_attachments);
+        self
+    }
+
+    /// Sets the store used to keep the messages emitted to disconnected sockets
+    /// until they reconnect and recover their session.
+    /// See the [`offline`](crate::offline) module for more details.
+    ///
+    /// Defaults to [`NoopOfflineStore`] (messages are dropped).
+    #[inline]
+    pub fn offline_store(mut self, store: impl OfflineStore) -> Self {
+        self.config.offline_store = Arc::new(store);
+        self
+    }
+
+    /// Sets the [`OutboundPolicy`] applied to the packets emitted to a socket
+    /// whose connection buffer is full (see [`max_buffer_size`](Self::max_buffer_size)).
+    ///
+    /// Defaults to [`OutboundPolicy::Error`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, OutboundPolicy, extract::*};
+    /// let (_, io) = SocketIo::builder()
+    ///     .max_buffer_size(64)
+    ///     .outbound_policy(OutboundPolicy::DropOldest)
+    ///     .build_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("tick", |socket: SocketRef| {
+    ///         socket.emit("tock", ()).ok();
+    ///         println!("{} packets dropped", socket.dropped_packets());
+    ///     });
+    /// });
+    /// ```
+    #[inline]
+    pub fn outbound_policy(mut self, policy: OutboundPolicy) -> Self {
+        self.config.outbound_policy = policy;
+        self
+    }
+
+    /// Bounds the buffer of the events received from a client while the connect handler is running.
+    ///
+    /// These events are dispatched once the connect handler has completed.
+    /// At most `max_events` events are buffered, the next ones are handled according to the [`OverflowPolicy`]:
+    /// * [`OverflowPolicy::Drop`]: they are dropped and therefore never acknowledged.
+    /// * [`OverflowPolicy::Disconnect`]: the socket is disconnected and the buffered events are dropped.
+    ///
+    /// Defaults to 1024 events and [`OverflowPolicy::Disconnect`].
+    #[inline]
+    pub fn connect_buffer(mut self, max_events: usize, overflow: OverflowPolicy) -> Self {
+        self.config.max_pending_events = max_events;
+        self.config.pending_overflow = overflow;
+        self
+    }
+
+    /// Sets the function used to spawn the futures of the async connect, message and disconnect handlers.
+    ///
+    /// It can be used to run the application handlers on a dedicated runtime or on a bounded task pool,
+    /// so that a flood of events can't starve the tasks of the transports.
+    /// The sync handlers are still called directly on the task receiving the packets.
+    ///
+    /// Defaults to [`tokio::spawn`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::SocketIo;
+    /// let rt = tokio::runtime::Builder::new_multi_thread()
+    ///     .worker_threads(2)
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    /// let handle = rt.handle().clone();
+    /// let (_, io) = SocketIo::builder()
+    ///     .spawner(move |fut| {
+    ///         handle.spawn(fut);
+    ///     })
+    ///     .build_svc();
+    /// # drop(rt.shutdown_background());
+    /// ```
+    #[inline]
+    pub fn spawner(
+        mut self,
+        spawner: impl Fn(futures::future::BoxFuture<'static, ()>) + Send + Sync + 'static,
+    ) -> Self {
+        self.config.spawner = Spawner::new(spawner);
+        self
+    }
+
     /// Sets a custom [`SocketIoConfig`] created previously for this [`SocketIoBuilder`]
     #[inline]
     pub fn with_config(mut self, config: SocketIoConfig) -> Self {
@@ -214,7 +574,7 @@ impl Default for SocketIoBuilder {
 /// The [`SocketIo`] instance can be cheaply cloned and moved around everywhere in your program.
 /// It can be used as the main handle to access the whole socket.io context.
 #[derive(Debug)]
-pub struct SocketIo<A: Adapter = LocalAdapter>(Arc<Client<A>>);
+pub struct SocketIo<A: Adapter = LocalAdapter>(pub(crate) Arc<Client<A>>);
 
 impl SocketIo<LocalAdapter> {
     /// Creates a new [`SocketIoBuilder`] with a default config
@@ -332,6 +692,35 @@ impl<A: Adapter> SocketIo<A> {
     /// });
     ///
     /// ```
+    /// #### Typed namespaces:
+    /// The namespace paths are strings, but they can be given a type to avoid typos.
+    /// The [`SocketIo::ns`] fn takes any type that converts into a `Cow<'static, str>`
+    /// and the other fns any type that converts into a `&str`, so a unit struct can be used everywhere
+    /// by implementing these two conversions:
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// # use std::borrow::Cow;
+    /// #[derive(Debug, Clone, Copy)]
+    /// struct Chat;
+    /// impl Chat {
+    ///     const PATH: &'static str = "/chat";
+    /// }
+    /// impl From<Chat> for &'static str {
+    ///     fn from(_: Chat) -> Self {
+    ///         Chat::PATH
+    ///     }
+    /// }
+    /// impl From<Chat> for Cow<'static, str> {
+    ///     fn from(_: Chat) -> Self {
+    ///         Cow::Borrowed(Chat::PATH)
+    ///     }
+    /// }
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns(Chat, |socket: SocketRef| {});
+    /// io.of(Chat).unwrap().emit("hello", "world").ok();
+    /// assert!(io.of("/chat").is_ok());
+    /// ```
     #[inline]
     pub fn ns<C, T>(&self, path: impl Into<Cow<'static, str>>, callback: C)
     where
@@ -341,6 +730,34 @@ impl<A: Adapter> SocketIo<A> {
         self.0.add_ns(path.into(), callback);
     }
 
+    /// Enables dynamic namespaces: when a client connects to a namespace that is not registered,
+    /// it is created on the fly with the given connect handler.
+    /// The path of the namespace can be retrieved with [`Socket::ns`](crate::socket::Socket::ns).
+    ///
+    /// Without dynamic namespaces, the connection to an unknown namespace is rejected with a `connect_error` packet.
+    ///
+    /// A dynamic namespace is removed when its last socket leaves, along with any state
+    /// attached to it, such as its middlewares or its recoverable sessions.
+    /// Its number can be limited with [`SocketIoBuilder::max_dynamic_namespaces`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// // Each document gets its own namespace (e.g. "/doc-1234")
+    /// io.dynamic_ns(|socket: SocketRef| {
+    ///     println!("Socket {} joined the document {}", socket.id, socket.ns());
+    /// });
+    /// ```
+    #[inline]
+    pub fn dynamic_ns<C, T>(&self, callback: C)
+    where
+        C: ConnectHandler<A, T> + Clone,
+        T: Send + Sync + 'static,
+    {
+        self.0.set_dynamic_ns(callback);
+    }
+
     /// Deletes the namespace with the given path
     #[inline]
     pub fn delete_ns<'a>(&self, path: impl Into<&'a str>) {
@@ -350,15 +767,369 @@ impl<A: Adapter> SocketIo<A> {
     /// Gracefully closes all the connections and drops every sockets
     ///
     /// Any `on_disconnect` handler will called with [`DisconnectReason::ClosingServer`](crate::socket::DisconnectReason::ClosingServer)
+    ///
+    /// The [adapter](crate::adapter::Adapter::close) of each namespace is closed once all its sockets are disconnected.
     #[inline]
     pub async fn close(&self) {
         self.0.close().await;
     }
 
+    /// Adds a connect middleware to the given namespace.
+    ///
+    /// It is called with the [`ConnectContext`] of each connection to the namespace,
+    /// before the connect handler and before the [connection limits](Self::set_max_sockets) are checked.
+    /// The context bundles the headers, the query and the remote address of the handshake request,
+    /// as well as the auth payload of the connect packet.
+    ///
+    /// * Middlewares are called in their registration order.
+    /// * If a middleware returns an error, the next ones are not called, the socket is not connected
+    ///   and the client receives a `connect_error` with the error message.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, handler::ConnectContext};
+    /// # fn is_valid(token: &str) -> bool { token == "secret" }
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// // Accept the browsers authenticated with a session cookie
+    /// // and the other clients sending a bearer token in their auth payload
+    /// io.use_connect("/", |ctx: &ConnectContext<'_>| {
+    ///     let cookie = ctx
+    ///         .headers
+    ///         .get("cookie")
+    ///         .and_then(|c| c.to_str().ok())
+    ///         .and_then(|c| c.split("; ").find_map(|c| c.strip_prefix("session=")));
+    ///     let token = ctx
+    ///         .auth
+    ///         .get("token")
+    ///         .and_then(|t| t.as_str())
+    ///         .and_then(|t| t.strip_prefix("Bearer "));
+    ///
+    ///     match cookie.or(token) {
+    ///         Some(credential) if is_valid(credential) => Ok(()),
+    ///         _ => Err("Unauthorized"),
+    ///     }
+    /// })
+    /// .unwrap();
+    /// ```
+    #[inline]
+    pub fn use_connect<'a, E: std::fmt::Display>(
+        &self,
+        path: impl Into<&'a str>,
+        middleware: impl Fn(&ConnectContext<'_>) -> Result<(), E> + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.use_connect(middleware))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Adds an outbound middleware to the given namespace.
+    ///
+    /// It is called with each packet sent to the sockets of the namespace, before it is serialized.
+    /// It can be used to add some metadata to every event, without modifying each emit call.
+    ///
+    /// * Middlewares are called in their registration order.
+    /// * They are called for every packet: events, acknowledgements, but also connect and disconnect packets.
+    ///   Use the [`PacketData`](crate::packet::PacketData) of the packet to only modify some of them.
+    /// * For broadcasts, they are called once for each targeted socket.
+    /// * They are called before the packet is tagged for the [connection state recovery](crate::recovery).
+    ///   Replayed packets are not modified again.
+    /// * They must not add or remove binary payloads, because the space for the payloads is reserved before.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, packet::{Packet, PacketData}};
+    /// # use serde_json::Value;
+    /// # use std::time::{SystemTime, UNIX_EPOCH};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// // Add the server timestamp as a last argument of every event
+    /// io.use_outbound("/", |packet: &mut Packet<'_>| {
+    ///     if let PacketData::Event(_, data, _) = &mut packet.inner {
+    ///         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    ///         let now = Value::from(now.as_millis() as u64);
+    ///         match data {
+    ///             Value::Array(args) => args.push(now),
+    ///             data => *data = Value::Array(vec![data.take(), now]),
+    ///         }
+    ///     }
+    /// })
+    /// .unwrap();
+    /// ```
+    #[inline]
+    pub fn use_outbound<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        middleware: impl Fn(&mut Packet<'_>) + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.use_outbound(middleware))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Sets a prefix for the event names of the given namespace, for example to version a protocol.
+    ///
+    /// The prefix is added to the name of every event emitted to the sockets of the namespace
+    /// and stripped from the name of the events received, before the handlers are matched.
+    /// Handlers are therefore registered and events emitted without the prefix.
+    /// Received events whose name doesn't start with the prefix are ignored.
+    ///
+    /// * Only events are affected: connect, disconnect and acknowledgement packets are not named
+    ///   and are unchanged. The `connect`, `connect_error` and `disconnect` names reserved by the client
+    ///   are not events either, so a prefixed `v2:disconnect` event is an ordinary event.
+    /// * The prefix is added after the [outbound middlewares](Self::use_outbound) are called,
+    ///   so they see the event names without the prefix.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // Receives the "v2:chat" events and replies with a "v2:chat" event
+    ///     socket.on("chat", |socket: SocketRef, Data::<Value>(data)| {
+    ///         socket.emit("chat", data).ok();
+    ///     });
+    /// });
+    /// io.set_event_prefix("/", "v2:").unwrap();
+    /// ```
+    #[inline]
+    pub fn set_event_prefix<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        prefix: impl Into<Cow<'static, str>>,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.set_event_prefix(prefix.into()))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Sets a transformer applied to the data of every ack response sent with an [`AckSender`]
+    /// by the sockets of the given namespace, for example to wrap it in a standard envelope.
+    ///
+    /// The transformer receives the serialized data, before the binary payloads are attached,
+    /// and is called before the [outbound middlewares](Self::use_outbound).
+    /// Setting a new transformer replaces the previous one.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::{json, Value};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // The client receives `{ "status": "ok", "body": ... }`
+    ///     socket.on("echo", |Data::<Value>(data), ack: AckSender| {
+    ///         ack.send(data).ok();
+    ///     });
+    /// });
+    /// io.set_ack_transformer("/", |data| json!({ "status": "ok", "body": data }))
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`AckSender`]: crate::extract::AckSender
+    #[inline]
+    pub fn set_ack_transformer<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        transformer: impl Fn(Value) -> Value + Send + Sync + 'static,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.set_ack_transformer(transformer))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Sets whether the [`Data`] and [`TryData`] extractors unwrap the array of arguments
+    /// of the events with a single argument, for the given namespace.
+    ///
+    /// When it is enabled, an event sent with `socket.emit("test", 1)` is deserialized from `1`
+    /// rather than from `[1]`. When it is disabled, the arguments are always deserialized as an array,
+    /// so a handler expecting a `Vec<T>` of arguments also works with a single argument.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // Works with `socket.emit("sum", 1)` as well as `socket.emit("sum", 1, 2, 3)`
+    ///     socket.on("sum", |Data::<Vec<u32>>(args), ack: AckSender| {
+    ///         ack.send(args.iter().sum::<u32>()).ok();
+    ///     });
+    /// });
+    /// io.set_unwrap_single_element("/", false).unwrap();
+    /// ```
+    ///
+    /// [`Data`]: crate::extract::Data
+    /// [`TryData`]: crate::extract::TryData
+    #[inline]
+    pub fn set_unwrap_single_element<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.set_unwrap_single_element(enabled))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Sets the maximum number of sockets that can be connected to the given namespace,
+    /// and the [`MaxSocketsPolicy`] applied to the connections over this limit.
+    ///
+    /// With the [`MaxSocketsPolicy::Queue`] policy, the connections over the limit are held
+    /// without being connected, e.g. for a matchmaking queue. Each time a socket leaves the namespace,
+    /// the first queued connection is admitted and receives its connect packet.
+    /// A connection still queued after the timeout is rejected with a `connect_error` packet.
+    ///
+    /// It applies in addition to the [`max_sockets_per_ns`](SocketIoBuilder::max_sockets_per_ns)
+    /// and [`max_connections`](SocketIoBuilder::max_connections) limits.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, MaxSocketsPolicy, extract::*};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/lobby", |socket: SocketRef| {
+    ///     socket.emit("welcome", ()).ok();
+    /// });
+    /// // At most 8 players, the next ones wait up to 30 seconds for a slot
+    /// let policy = MaxSocketsPolicy::Queue(Duration::from_secs(30));
+    /// io.set_max_sockets("/lobby", 8, policy).unwrap();
+    /// ```
+    pub fn set_max_sockets<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        max: usize,
+        policy: MaxSocketsPolicy,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.set_max_sockets(max, policy))
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
+    /// Sets custom data added to the payload of the connect packet sent to each socket
+    /// connecting to the given namespace, for example feature flags that the client should know immediately.
+    ///
+    /// The data must serialize to a JSON object. Its fields are merged with the ones set by the server
+    /// (`sid` and `pid`), which cannot be overridden. Setting new data replaces the previous one.
+    ///
+    /// **Note**: The connect packet is sent before the connect handler is called, so the data
+    /// is the same for all the sockets of the namespace. It is only sent with the v5 protocol,
+    /// the v4 connect packet has no payload. The official JavaScript client only reads the `sid` and
+    /// `pid` fields, so the data is meant for clients that parse the connect packet payload.
+    ///
+    /// ## Errors
+    /// * If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    /// * If the data doesn't serialize to a JSON object, an [`Error::Serialize`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::json;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    /// // The client receives `{ "sid": "...", "features": ["chat"] }` in the connect packet
+    /// io.set_connect_data("/", json!({ "features": ["chat"] })).unwrap();
+    /// ```
+    pub fn set_connect_data<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        data: impl serde::Serialize,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        let ns = self
+            .0
+            .get_ns(path)
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))?;
+        match serde_json::to_value(data)? {
+            Value::Object(data) => ns.set_connect_data(data),
+            _ => {
+                let err = serde::ser::Error::custom("connect data must be a JSON object");
+                return Err(Error::Serialize(err));
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a [`Stream`](futures::Stream) of the [`ConnectionEvent`](crate::socket::ConnectionEvent)s of the given namespace.
+    ///
+    /// It yields an event each time a socket connects to or disconnects from the namespace.
+    /// The stream is bounded and events are dropped if it is not consumed fast enough,
+    /// see [`ConnectionEvents`] for more details.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, socket::ConnectionEvent};
+    /// # use futures::StreamExt;
+    /// # async fn doc_test() {
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// let mut events = io.connection_events("/").unwrap();
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         ConnectionEvent::Connected(sid) => println!("{sid} is online"),
+    ///         ConnectionEvent::Reconnected(sid, old) => println!("{old} is back online as {sid}"),
+    ///         ConnectionEvent::Disconnected(sid, reason) => println!("{sid} is offline: {reason}"),
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[inline]
+    pub fn connection_events<'a>(
+        &self,
+        path: impl Into<&'a str>,
+    ) -> Result<ConnectionEvents, Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .map(|ns| ns.events())
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
+    }
+
     // Chaining operators fns
 
     /// Selects a specific namespace to perform operations on
     ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    ///
     /// ## Example
     /// ```
     /// # use socketioxide::{SocketIo, extract::SocketRef};
@@ -374,7 +1145,7 @@ impl<A: Adapter> SocketIo<A> {
     ///    println!("found socket on /custom_ns namespace with id: {}", socket.id);
     /// }
     #[inline]
-    pub fn of<'a>(&self, path: impl Into<&'a str>) -> Option<BroadcastOperators<A>> {
+    pub fn of<'a>(&self, path: impl Into<&'a str>) -> Result<BroadcastOperators<A>, Error> {
         self.get_op(path.into())
     }
 
@@ -400,15 +1171,164 @@ impl<A: Adapter> SocketIo<A> {
     ///   println!("found socket on / ns in room1 with id: {}", socket.id);
     /// }
     #[inline]
-    pub fn to(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
-        self.get_default_op().to(rooms)
+    pub fn to(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
+        self.get_default_op().to(rooms)
+    }
+
+    /// Selects the sockets of the root namespace with reusable [`BroadcastOptions`].
+    ///
+    /// Alias for `io.of("/").unwrap().with_opts(opts)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, adapter::BroadcastOptions};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// let unmuted_team = BroadcastOptions::new(None)
+    ///     .with_rooms("team")
+    ///     .with_except("muted");
+    /// io.with_opts(unmuted_team.clone()).emit("score", 1).ok();
+    /// io.with_opts(unmuted_team).emit("cheer", ()).ok();
+    /// ```
+    #[inline]
+    pub fn with_opts(&self, opts: BroadcastOptions) -> BroadcastOperators<A> {
+        self.get_default_op().with_opts(opts)
+    }
+
+    /// Selects the socket with the given id on the root namespace.
+    ///
+    /// Alias for `io.of("/").unwrap().to_sid(sid)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let io2 = io.clone();
+    /// io.ns("/", move |socket: SocketRef| {
+    ///     io2.to_sid(socket.id).emit("welcome", "hello").ok();
+    /// });
+    #[inline]
+    pub fn to_sid(&self, sid: Sid) -> BroadcastOperators<A> {
+        self.get_default_op().to_sid(sid)
+    }
+
+    /// Selects the sockets of the root namespace with the given ids.
+    ///
+    /// Alias for `io.of("/").unwrap().to_sids(sids)`,
+    /// see [`BroadcastOperators::to_sids`] for more details.
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, socket::Sid};
+    /// # fn query_subscribers() -> Vec<Sid> { vec![] }
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// // Later in your code you can notify a precomputed set of sockets
+    /// io.to_sids(query_subscribers()).emit("news", "hello").ok();
+    #[inline]
+    pub fn to_sids(&self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        self.get_default_op().to_sids(sids)
+    }
+
+    /// Selects all sockets in the given rooms on the root namespace.
+    ///
+    /// Alias for :
+    /// * `io.of("/").unwrap().within(rooms)`
+    /// * `io.to(rooms)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     println!("Socket connected on / namespace with id: {}", socket.id);
+    /// });
+    ///
+    /// // Later in your code you can select all sockets in the room "room1"
+    /// // and for example show all sockets connected to it
+    /// let sockets = io.within("room1").sockets().unwrap();
+    /// for socket in sockets {
+    ///   println!("found socket on / ns in room1 with id: {}", socket.id);
+    /// }
+    #[inline]
+    pub fn within(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
+        self.get_default_op().within(rooms)
+    }
+
+    /// Filters out all sockets selected with the previous operators which are in the given rooms.
+    ///
+    /// Alias for `io.of("/").unwrap().except(rooms)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     println!("Socket connected on / namespace with id: {}", socket.id);
+    ///     socket.on("register1", |socket: SocketRef| {
+    ///         socket.join("room1");
+    ///     });
+    ///     socket.on("register2", |socket: SocketRef| {
+    ///         socket.join("room2");
+    ///     });
+    /// });
+    ///
+    ///
+    /// // Later in your code you can select all sockets in the root namespace that are not in the room1
+    /// // and for example show all sockets connected to it
+    /// let sockets = io.except("room1").sockets().unwrap();
+    /// for socket in sockets {
+    ///   println!("found socket on / ns in room1 with id: {}", socket.id);
+    /// }
+    #[inline]
+    pub fn except(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
+        self.get_default_op().except(rooms)
+    }
+
+    /// Selects all sockets of the root namespace except the ones with the given ids.
+    ///
+    /// Alias for `io.of("/").unwrap().except_sids(sids)`,
+    /// see [`BroadcastOperators::except_sids`] for more details.
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef, socket::Sid};
+    /// # fn unsubscribed() -> Vec<Sid> { vec![] }
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// // Later in your code you can notify everyone except a precomputed set of sockets
+    /// io.except_sids(unsubscribed()).emit("news", "hello").ok();
+    #[inline]
+    pub fn except_sids(&self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        self.get_default_op().except_sids(sids)
     }
 
-    /// Selects all sockets in the given rooms on the root namespace.
+    /// Selects all sockets in the rooms matching the given glob pattern on the root namespace.
     ///
-    /// Alias for :
-    /// * `io.of("/").unwrap().within(rooms)`
-    /// * `io.to(rooms)`
+    /// Alias for `io.of("/").unwrap().to_pattern(pattern)`
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
     ///
     /// ## Panics
     /// If the **default namespace "/" is not found** this fn will panic!
@@ -421,20 +1341,19 @@ impl<A: Adapter> SocketIo<A> {
     ///     println!("Socket connected on / namespace with id: {}", socket.id);
     /// });
     ///
-    /// // Later in your code you can select all sockets in the room "room1"
-    /// // and for example show all sockets connected to it
-    /// let sockets = io.within("room1").sockets().unwrap();
-    /// for socket in sockets {
-    ///   println!("found socket on / ns in room1 with id: {}", socket.id);
-    /// }
+    /// // Later in your code you can emit a message to all the rooms starting with "game:"
+    /// io.to_pattern("game:*").emit("hello", ());
     #[inline]
-    pub fn within(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
-        self.get_default_op().within(rooms)
+    pub fn to_pattern(&self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        self.get_default_op().to_pattern(pattern)
     }
 
-    /// Filters out all sockets selected with the previous operators which are in the given rooms.
+    /// Filters out all sockets selected with the previous operators which are in the rooms
+    /// matching the given glob pattern.
     ///
-    /// Alias for `io.of("/").unwrap().except(rooms)`
+    /// Alias for `io.of("/").unwrap().except_pattern(pattern)`
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
     ///
     /// ## Panics
     /// If the **default namespace "/" is not found** this fn will panic!
@@ -445,24 +1364,14 @@ impl<A: Adapter> SocketIo<A> {
     /// let (_, io) = SocketIo::new_svc();
     /// io.ns("/", |socket: SocketRef| {
     ///     println!("Socket connected on / namespace with id: {}", socket.id);
-    ///     socket.on("register1", |socket: SocketRef| {
-    ///         socket.join("room1");
-    ///     });
-    ///     socket.on("register2", |socket: SocketRef| {
-    ///         socket.join("room2");
-    ///     });
     /// });
     ///
-    ///
-    /// // Later in your code you can select all sockets in the root namespace that are not in the room1
-    /// // and for example show all sockets connected to it
-    /// let sockets = io.except("room1").sockets().unwrap();
-    /// for socket in sockets {
-    ///   println!("found socket on / ns in room1 with id: {}", socket.id);
-    /// }
+    /// // Later in your code you can emit a message to all sockets
+    /// // except for ones in rooms starting with "admin:"
+    /// io.except_pattern("admin:*").emit("hello", ());
     #[inline]
-    pub fn except(&self, rooms: impl RoomParam) -> BroadcastOperators<A> {
-        self.get_default_op().except(rooms)
+    pub fn except_pattern(&self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        self.get_default_op().except_pattern(pattern)
     }
 
     /// Broadcasts to all sockets only connected on this node (when using multiple nodes).
@@ -492,6 +1401,34 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().local()
     }
 
+    /// Broadcasts to all sockets connected on the other nodes only (when using multiple nodes).
+    /// It is the inverse of [`local`](Self::local): the sockets of the current node are skipped.
+    ///
+    /// It is useful in a cluster when the current node has already handled its own sockets
+    /// and only needs to forward the message to the rest of the cluster.
+    /// When using the default in-memory adapter there is no other node and nobody receives the message.
+    ///
+    /// Alias for `io.of("/").unwrap().remote()`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     println!("Socket connected on / namespace with id: {}", socket.id);
+    /// });
+    ///
+    /// // Later in your code you can emit a message to the sockets of room1
+    /// // that are connected on the other nodes
+    /// io.remote().to("room1").emit("hello", ());
+    #[inline]
+    pub fn remote(&self) -> BroadcastOperators<A> {
+        self.get_default_op().remote()
+    }
+
     /// Sets a custom timeout when broadcasting a message with an acknowledgement.
     ///
     /// Alias for `io.of("/").unwrap().timeout(duration)`
@@ -594,6 +1531,78 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().emit(event, data)
     }
 
+    /// Emits the same event to several rooms of the default namespace with a different data for each of them.
+    ///
+    /// The entries with identical data are serialized once and grouped into a single broadcast.
+    /// See [`BroadcastOperators::emit_many`] for more details.
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    ///
+    /// let leaderboard = [("room1", vec![1, 2]), ("room2", vec![3]), ("room3", vec![1, 2])];
+    /// io.emit_many("leaderboard", leaderboard).ok();
+    /// ```
+    #[inline]
+    pub fn emit_many<R: RoomParam, T: serde::Serialize>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        entries: impl IntoIterator<Item = (R, T)>,
+    ) -> Result<(), BroadcastError> {
+        self.get_default_op().emit_many(event, entries)
+    }
+
+    /// Emits a message to all the sockets of all the namespaces.
+    ///
+    /// Unlike [`emit()`](#method.emit) that only targets the root namespace, it iterates over every
+    /// registered namespace and broadcasts to all of its sockets, without excluding any of them.
+    /// It can be used for server-wide announcements.
+    ///
+    /// The data is serialized once. If some sockets could not be reached, the emission still continues
+    /// on the other namespaces and all the socket errors are returned in a single [`BroadcastError::Socket`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {});
+    /// io.ns("/admin", |socket: SocketRef| {});
+    ///
+    /// io.emit_all("maintenance", "server restarting in 5 minutes").ok();
+    /// ```
+    pub fn emit_all<T: serde::Serialize>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<(), BroadcastError> {
+        let event = event.into();
+        let data = serde_json::to_value(data)?;
+        let mut socket_errors = Vec::new();
+        let mut error = None;
+        for ns in self.0.namespaces() {
+            match BroadcastOperators::new(ns)
+                .broadcast()
+                .emit(event.clone(), &data)
+            {
+                Ok(()) => (),
+                Err(BroadcastError::Socket(errors)) => socket_errors.extend(errors),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None if !socket_errors.is_empty() => Err(BroadcastError::Socket(socket_errors)),
+            None => Ok(()),
+        }
+    }
+
     /// Emits a message to all sockets selected with the previous operators and
     /// waits for the acknowledgement(s).
     ///
@@ -612,7 +1621,8 @@ impl<A: Adapter> SocketIo<A> {
     ///
     /// If the client didn't respond before the timeout, the [`AckStream`] will yield
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `V`,
-    /// an [`AckError::Serde`] will be yielded.
+    /// an [`AckError::Serde`] will be yielded. If the socket is disconnected before
+    /// the client responds, an [`AckError::Disconnected`] will be yielded.
     ///
     /// [`timeout()`]: #method.timeout
     /// [`Stream`]: futures::stream::Stream
@@ -620,6 +1630,7 @@ impl<A: Adapter> SocketIo<A> {
     /// [`AckResponse`]: crate::ack::AckResponse
     /// [`AckError::Serde`]: crate::AckError::Serde
     /// [`AckError::Timeout`]: crate::AckError::Timeout
+    /// [`AckError::Disconnected`]: crate::AckError::Disconnected
     /// [`AckError::Socket`]: crate::AckError::Socket
     /// [`AckError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
     /// [`io::get_socket()`]: crate::SocketIo#method.get_socket
@@ -712,6 +1723,46 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().disconnect()
     }
 
+    /// Disconnects all the sockets of the given namespace with a custom [`DisconnectReason`].
+    ///
+    /// The `on_disconnect` handler of each socket is called with this reason.
+    /// Unlike [`close()`](#method.close), only this namespace is affected:
+    /// the underlying connections stay open for the other namespaces.
+    ///
+    /// All the sockets are disconnected, even if a socket buffer is full.
+    ///
+    /// ## Errors
+    /// * If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    /// * If the adapter fails to remove a socket, the first [`Error::Adapter`] is returned
+    ///   once all the sockets are disconnected.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, socket::DisconnectReason, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/game", |socket: SocketRef| {
+    ///     socket.on_disconnect(|reason: DisconnectReason| {
+    ///         println!("disconnected: {reason}");
+    ///     });
+    /// });
+    ///
+    /// // Later, for a maintenance of the game namespace
+    /// io.disconnect_all("/game", DisconnectReason::ServerNSDisconnect).unwrap();
+    /// ```
+    ///
+    /// [`DisconnectReason`]: crate::socket::DisconnectReason
+    pub fn disconnect_all<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        reason: crate::socket::DisconnectReason,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        self.0
+            .get_ns(path)
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))?
+            .disconnect_all(reason)
+    }
+
     /// Makes all sockets selected with the previous operators join the given room(s).
     ///
     /// Alias for `io.of("/").unwrap().join(rooms)`
@@ -755,6 +1806,72 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().rooms()
     }
 
+    /// Gets the number of sockets in each room on the current namespace.
+    /// Rooms named after a socket id are not included.
+    ///
+    /// Alias for `io.of("/").unwrap().room_sizes()`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let io2 = io.clone();
+    /// io.ns("/", move |socket: SocketRef| async move {
+    ///     socket.join("room1").unwrap();
+    ///     let sizes = io2.room_sizes().unwrap();
+    ///     println!("Room sizes on / namespace: {:?}", sizes);
+    /// });
+    pub fn room_sizes(&self) -> Result<HashMap<Room, usize>, A::Error> {
+        self.get_default_op().room_sizes()
+    }
+
+    /// Returns true if the given room exists and has at least one socket on the current namespace.
+    ///
+    /// Alias for `io.of("/").unwrap().has_room(room)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let io2 = io.clone();
+    /// io.ns("/", move |socket: SocketRef| async move {
+    ///     if io2.has_room("room1").unwrap() {
+    ///         io2.to("room1").emit("new user", socket.id).ok();
+    ///     }
+    ///     socket.join("room1").unwrap();
+    /// });
+    pub fn has_room(&self, room: &str) -> Result<bool, A::Error> {
+        self.get_default_op().has_room(room)
+    }
+
+    /// Gets the ids of all the sockets in the given room of the current namespace,
+    /// across all the nodes of the cluster.
+    ///
+    /// Alias for `io.of("/").unwrap().room_sids(room)`
+    ///
+    /// ## Panics
+    /// If the **default namespace "/" is not found** this fn will panic!
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let io2 = io.clone();
+    /// io.ns("/", move |socket: SocketRef| async move {
+    ///     socket.join("lobby").unwrap();
+    ///     let sids = io2.room_sids("lobby").await.unwrap();
+    ///     println!("Sockets in the lobby: {:?}", sids);
+    /// });
+    pub async fn room_sids(&self, room: &str) -> Result<HashSet<Sid>, A::Error> {
+        self.get_default_op().room_sids(room).await
+    }
+
     /// Makes all sockets selected with the previous operators leave the given room(s).
     ///
     /// Alias for `io.of("/").unwrap().join(rooms)`
@@ -783,12 +1900,46 @@ impl<A: Adapter> SocketIo<A> {
         self.get_default_op().get_socket(sid)
     }
 
+    /// Connects `count` sockets without any transport to the given namespace and returns them.
+    /// The connect handler of the namespace is called for each of them.
+    ///
+    /// The packets emitted to these sockets are buffered and never flushed,
+    /// it is useful to measure the cost of an emission in tests and benchmarks.
+    ///
+    /// It is only available with the `test-utils` feature.
+    ///
+    /// ## Errors
+    /// If the namespace is not registered, an [`Error::UnknownNamespace`] is returned.
+    #[cfg(any(test, feature = "test-utils"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+    pub fn connect_dummy_sockets<'a>(
+        &self,
+        path: impl Into<&'a str>,
+        count: usize,
+    ) -> Result<Vec<SocketRef<A>>, Error> {
+        let path = path.into();
+        let ns = self
+            .0
+            .get_ns(path)
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))?;
+        (0..count)
+            .map(|_| {
+                let sid = Sid::new();
+                let esocket = engineioxide::Socket::new_dummy(sid, Box::new(|_, _| {})).into();
+                ns.clone()
+                    .connect(sid, esocket, None, self.0.config.clone())?;
+                Ok(ns.get_socket(sid)?.into())
+            })
+            .collect()
+    }
+
     /// Returns a new operator on the given namespace
     #[inline(always)]
-    fn get_op(&self, path: &str) -> Option<BroadcastOperators<A>> {
+    fn get_op(&self, path: &str) -> Result<BroadcastOperators<A>, Error> {
         self.0
             .get_ns(path)
             .map(|ns| BroadcastOperators::new(ns).broadcast())
+            .ok_or_else(|| Error::UnknownNamespace(path.to_string()))
     }
 
     /// Returns a new operator on the default namespace "/" (root namespace)
@@ -830,8 +1981,24 @@ mod tests {
     fn get_op() {
         let (_, io) = SocketIo::builder().build_svc();
         io.ns("test", || {});
-        assert!(io.get_op("test").is_some());
-        assert!(io.get_op("test2").is_none());
+        assert!(io.get_op("test").is_ok());
+        assert!(matches!(
+            io.get_op("test2"),
+            Err(Error::UnknownNamespace(path)) if path == "test2"
+        ));
+    }
+
+    #[test]
+    fn emit_serialize_error() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        // Maps with non-string keys cannot be serialized to json
+        let data = std::collections::HashMap::from([((1, 2), 3)]);
+        assert!(matches!(
+            io.emit("test", data),
+            Err(BroadcastError::Serialize(_))
+        ));
+        assert!(io.emit("test", "data").is_ok());
     }
 
     #[test]
@@ -851,4 +2018,320 @@ mod tests {
         assert!(io.get_socket(sid).is_some());
         assert!(io.get_socket(Sid::new()).is_none());
     }
+
+    #[test]
+    fn room_sizes_exclude_sid_rooms() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 2).unwrap();
+        sockets[0].join(["room1", "room2"]).unwrap();
+        sockets[1].join("room2").unwrap();
+        // A room named after the id of a socket that is not connected is a user room
+        let other = Sid::new();
+        sockets[1].join(other.to_string()).unwrap();
+
+        let sizes = io.room_sizes().unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes["room1"], 1);
+        assert_eq!(sizes["room2"], 2);
+        assert_eq!(sizes[other.as_str()], 1);
+    }
+
+    #[test]
+    fn sockets_in_room() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sid = io.connect_dummy_sockets("/", 1).unwrap()[0].id;
+        let ns = io.0.get_ns("/").unwrap();
+        ns.adapter.add_all(sid, "room1").unwrap();
+
+        let sockets = io.within("room1").sockets().unwrap();
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets[0].id, sid);
+        assert!(io.within("unknown").sockets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn emit_with_opts() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 3).unwrap();
+        sockets[0].join("team").unwrap();
+        sockets[1].join(["team", "muted"]).unwrap();
+        let before = [0, 1, 2].map(|i| sockets[i].send_buffer_len());
+
+        let opts = BroadcastOptions::new(None)
+            .with_rooms("team")
+            .with_except("muted");
+        io.with_opts(opts.clone()).emit("score", 1).unwrap();
+        io.with_opts(opts).emit("cheer", ()).unwrap();
+
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [2, 0, 0]);
+    }
+
+    #[test]
+    fn emit_all() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        io.ns("/admin", || {});
+        let sockets: Vec<_> = ["/", "/admin", "/admin"]
+            .into_iter()
+            .flat_map(|path| io.connect_dummy_sockets(path, 1).unwrap())
+            .collect();
+        let before = [0, 1, 2].map(|i| sockets[i].send_buffer_len());
+
+        io.emit_all("maintenance", "restarting").unwrap();
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [1, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn disconnect_all() {
+        use crate::socket::DisconnectReason;
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        io.ns("/admin", || {});
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for path in ["/admin", "/admin", "/"] {
+            let tx = tx.clone();
+            io.connect_dummy_sockets(path, 1).unwrap()[0].on_disconnect(
+                move |reason: DisconnectReason| {
+                    tx.send((path, reason)).unwrap();
+                },
+            );
+        }
+
+        io.disconnect_all("/admin", DisconnectReason::ClosingServer)
+            .unwrap();
+        for _ in 0..2 {
+            assert_eq!(
+                rx.try_recv().unwrap(),
+                ("/admin", DisconnectReason::ClosingServer)
+            );
+        }
+        assert!(rx.try_recv().is_err());
+        assert!(io.0.get_ns("/admin").unwrap().get_sockets().is_empty());
+        assert_eq!(io.0.get_ns("/").unwrap().get_sockets().len(), 1);
+
+        assert!(matches!(
+            io.disconnect_all("/unknown", DisconnectReason::ClosingServer),
+            Err(Error::UnknownNamespace(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn room_sids() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 3).unwrap();
+        let sids: Vec<_> = sockets.iter().map(|s| s.id).collect();
+        io.to_sid(sids[0]).join("lobby").unwrap();
+        io.to_sid(sids[2]).join("lobby").unwrap();
+
+        let room = io.room_sids("lobby").await.unwrap();
+        assert_eq!(room, HashSet::from([sids[0], sids[2]]));
+        assert!(io.room_sids("unknown").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn to_sids() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 3).unwrap();
+        let before = [0, 1, 2].map(|i| sockets[i].send_buffer_len());
+
+        io.to_sids([sockets[0].id, sockets[2].id, Sid::new()])
+            .emit("test", "data")
+            .unwrap();
+        sockets[0]
+            .to_sids([sockets[0].id])
+            .emit("test", "data")
+            .unwrap();
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [2, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn except_sids() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 4).unwrap();
+        sockets[1].join("muted").unwrap();
+        let before = [0, 1, 2, 3].map(|i| sockets[i].send_buffer_len());
+
+        io.except_sids([sockets[2].id])
+            .except("muted")
+            .emit("test", "data")
+            .unwrap();
+        sockets[0]
+            .except_sids([sockets[1].id, sockets[2].id])
+            .emit("test", "data")
+            .unwrap();
+        let received = [0, 1, 2, 3].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [1, 0, 0, 2]);
+    }
+
+    #[tokio::test]
+    async fn broadcast_chunked() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 3).unwrap();
+        // The first socket is a slow client
+        for _ in 0..5 {
+            sockets[0].emit("backlog", ()).unwrap();
+        }
+        let before = [0, 1, 2].map(|i| sockets[i].send_buffer_len());
+
+        io.of("/")
+            .unwrap()
+            .broadcast_chunked(2)
+            .max_buffer_len(4)
+            .emit("test", "data")
+            .await
+            .unwrap();
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [0, 1, 1]);
+
+        io.of("/")
+            .unwrap()
+            .broadcast_chunked(0)
+            .emit("test", "data")
+            .await
+            .unwrap();
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [1, 2, 2]);
+    }
+
+    #[test]
+    fn emit_or_err_no_recipients() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let socket = io.connect_dummy_sockets("/", 1).unwrap().remove(0);
+        socket.join("room1").unwrap();
+
+        io.to("room1").emit_or_err("test", "data").unwrap();
+        assert!(matches!(
+            io.to("unknown").emit_or_err("test", "data"),
+            Err(BroadcastError::NoRecipients)
+        ));
+        assert!(matches!(
+            socket.broadcast().emit_or_err("test", "data"),
+            Err(BroadcastError::NoRecipients)
+        ));
+    }
+
+    #[test]
+    fn emit_many() {
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let sockets = io.connect_dummy_sockets("/", 3).unwrap();
+        sockets[0].join(["room1", "room3"]).unwrap();
+        sockets[1].join("room2").unwrap();
+        let before = [0, 1, 2].map(|i| sockets[i].send_buffer_len());
+
+        let entries = [("room1", 1), ("room2", 2), ("room3", 1)];
+        io.emit_many("score", entries).unwrap();
+
+        let received = [0, 1, 2].map(|i| sockets[i].send_buffer_len() - before[i]);
+        assert_eq!(received, [1, 1, 0]);
+
+        let data = std::collections::HashMap::from([((1, 2), 3)]);
+        assert!(matches!(
+            io.emit_many("score", [("room1", data)]),
+            Err(BroadcastError::Serialize(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack_no_recipients() {
+        use futures::{FutureExt, StreamExt};
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", || {});
+        let socket = io.connect_dummy_sockets("/", 1).unwrap().remove(0);
+        let before = socket.send_buffer_len();
+
+        let stream = io
+            .to("empty")
+            .emit_with_ack::<Value>("test", "data")
+            .unwrap();
+        assert_eq!(stream.socket_count(), 0);
+        futures::pin_mut!(stream);
+        assert!(matches!(stream.next().now_or_never(), Some(None)));
+        // Nothing is sent to the socket outside of the room
+        assert_eq!(socket.send_buffer_len(), before);
+    }
+
+    #[tokio::test]
+    async fn custom_spawner() {
+        use crate::{packet::PacketData, socket::DisconnectReason};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let spawned_clone = spawned.clone();
+        let config = SocketIoConfig {
+            spawner: Spawner::new(move |fut| {
+                spawned_clone.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(fut);
+            }),
+            ..Default::default()
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(3);
+        let (_, io) = SocketIo::builder().build_svc();
+        io.ns("/", move |socket: SocketRef| {
+            let tx1 = tx.clone();
+            socket.on(
+                "async",
+                move || async move { tx1.try_send("async").unwrap() },
+            );
+            let tx2 = tx.clone();
+            socket.on("sync", move || tx2.try_send("sync").unwrap());
+            socket.on_disconnect(move || async move { tx.try_send("disconnect").unwrap() });
+        });
+        let sid = Sid::new();
+        let ns = io.0.get_ns("/").unwrap();
+        let esocket = engineioxide::Socket::new_dummy(sid, Box::new(|_, _| {})).into();
+        ns.clone()
+            .connect(sid, esocket, None, config.into())
+            .unwrap();
+        let socket = ns.get_socket(sid).unwrap();
+
+        let event = |e: &str| PacketData::Event(e.to_string().into(), Value::Null, None);
+        socket.clone().recv(event("async")).unwrap();
+        socket.clone().recv(event("sync")).unwrap();
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+
+        socket.close(DisconnectReason::TransportClose).unwrap();
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(rx.recv().await.unwrap());
+        }
+        received.sort();
+        assert_eq!(received, ["async", "disconnect", "sync"]);
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack_cb_spawner() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let spawned_clone = spawned.clone();
+        let (_, io) = SocketIo::builder()
+            .spawner(move |fut| {
+                spawned_clone.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(fut);
+            })
+            .build_svc();
+        io.ns("/", || {});
+
+        // Without any socket, the callback is called with no acks
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        io.of("/")
+            .unwrap()
+            .emit_with_ack_cb::<Value>("test", (), move |acks| tx.send(acks.len()).unwrap())
+            .unwrap();
+        assert_eq!(rx.await.unwrap(), 0);
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    }
 }
@@ -61,7 +61,7 @@ impl<A: Adapter> Clone for SocketIoLayer<A> {
 
 impl<A: Adapter> SocketIoLayer<A> {
     pub(crate) fn from_config(config: Arc<SocketIoConfig>) -> (Self, Arc<Client<A>>) {
-        let client = Arc::new(Client::new(config.clone()));
+        let client = Client::new(config.clone());
         let layer = Self {
             client: client.clone(),
         };
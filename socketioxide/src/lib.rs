@@ -153,6 +153,7 @@
 //!     - for [`MessageHandler`](handler::MessageHandler): extracts and deserialize to json the message data
 //! * [`SocketRef`](extract::SocketRef): extracts a reference to the [`Socket`](socket::Socket)
 //! * [`Bin`](extract::Bin): extract a binary payload for a given message. Because it consumes the event it should be the last argument
+//! * [`Binary`](extract::Binary): same as [`Bin`](extract::Bin) but with bounds checked accessors to the attachments
 //! * [`AckSender`](extract::AckSender): Can be used to send an ack response to the current message event
 //! * [`ProtocolVersion`]: extracts the protocol version of the socket
 //! * [`TransportType`]: extracts the transport type of the socket
@@ -250,10 +251,29 @@
 //! Currently there is no other adapters available but more will be added in the future.
 //!
 //! ## [Feature flags](#feature-flags)
-//! * `v4`: enable support for the socket.io protocol v4
+//! * `v4`: enable support for the socket.io protocol v4 (and therefore the engine.io protocol v3)
 //! * `tracing`: enable logging with [`tracing`] calls
 //! * `extensions`: enable per-socket state with the [`extensions`] module
 //! * `state`: enable global state management
+//! * `compress-binary`: enable the gzip compression of the binary attachments sent over polling,
+//!   see [`SocketIoBuilder::compress_binary`](SocketIoBuilder)
+//! * `arbitrary-precision`: enable the `arbitrary_precision` feature of [`serde_json`], see below
+//! * `test-utils`: enable test only helpers such as [`Socket::connect_handler_completed`](socket::Socket)
+//!   and [`SocketIo::connect_dummy_sockets`]
+//!
+//! #### Number precision
+//! All the event payloads go through [`serde_json::Value`]. By default, numbers are stored as
+//! `u64`, `i64` or `f64`, so integers that do not fit in 64 bits and floats with more significant digits
+//! than a `f64` lose their precision (e.g. `18446744073709551616` becomes `1.8446744073709552e19`).
+//!
+//! With the `arbitrary-precision` feature, numbers keep their exact textual representation and can be
+//! deserialized into types such as [`serde_json::Number`] or big number types. The tradeoffs are:
+//! * every number is stored as a string, which is slower and uses more memory;
+//! * the feature is enabled for the whole dependency tree because of cargo feature unification;
+//! * numbers are compared by their text (`1.0` is not equal to `1`) and some `serde_json` behaviors
+//!   change, see the [`serde_json` docs](https://docs.rs/serde_json/latest/serde_json/#arbitrary-precision).
+//!
+//! Therefore, if you only need to transmit 64-bit ids, you can also send them as strings.
 //!
 pub mod adapter;
 
@@ -266,15 +286,19 @@ mod state;
 pub mod ack;
 pub mod handler;
 pub mod layer;
+pub mod offline;
 pub mod operators;
 pub mod packet;
+pub mod recovery;
 pub mod service;
 pub mod socket;
 
 pub use engineioxide::TransportType;
-pub use errors::{AckError, AdapterError, BroadcastError, DisconnectError, SendError, SocketError};
+pub use errors::{
+    AckError, AdapterError, BroadcastError, DisconnectError, Error, SendError, SocketError,
+};
 pub use handler::extract;
-pub use io::{SocketIo, SocketIoBuilder, SocketIoConfig};
+pub use io::{MaxSocketsPolicy, OutboundPolicy, SocketIo, SocketIoBuilder, SocketIoConfig};
 
 mod client;
 mod errors;
@@ -285,6 +309,9 @@ mod ns;
 /// It is accessible with the [`Socket::protocol`](socket::Socket) method or as an extractor
 ///
 /// **Note**: The socket.io protocol version does not correspond to the engine.io protocol version.
+/// The socket.io protocol v4 is based on the engine.io protocol v3 (with base64 encoded binary payloads over polling)
+/// and the socket.io protocol v5 on the engine.io protocol v4.
+/// The engine.io version of a socket is accessible with the [`Socket::engineio_version`](socket::Socket::engineio_version) method.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ProtocolVersion {
     /// The socket.io protocol version 4, only available with the feature flag `v4`
@@ -1,29 +1,97 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, RwLock, TryLockError, Weak,
+    },
 };
 
 use crate::{
-    adapter::Adapter,
-    errors::Error,
-    handler::{BoxedConnectHandler, ConnectHandler, MakeErasedHandler},
+    adapter::{Adapter, BroadcastOptions},
+    errors::{DisconnectError, Error},
+    handler::{BoxedConnectHandler, ConnectContext, ConnectHandler, MakeErasedHandler},
     packet::{Packet, PacketData},
-    socket::Socket,
-    SocketIoConfig,
+    recovery::RecoveryStore,
+    socket::{ConnectionEvent, ConnectionEvents, DisconnectReason, Socket},
+    MaxSocketsPolicy, SocketIoConfig,
+};
+use crate::{
+    client::{Client, SocketData},
+    errors::AdapterError,
 };
-use crate::{client::SocketData, errors::AdapterError};
 use engineioxide::sid::Sid;
+use serde_json::{Map, Value};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// A middleware called on each packet sent to the sockets of a namespace
+type OutboundMiddleware = Box<dyn Fn(&mut Packet<'_>) + Send + Sync>;
+/// A middleware called on each connection to a namespace, returning the reason of the refusal
+type ConnectMiddleware = Box<dyn Fn(&ConnectContext<'_>) -> Result<(), String> + Send + Sync>;
+/// A transformer called on the data of each ack response sent by the sockets of a namespace
+type AckTransformer = Box<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// A connection waiting for a slot of a full namespace
+pub(crate) struct QueuedConnection {
+    esocket: Arc<engineioxide::Socket<SocketData>>,
+    auth: Option<String>,
+    config: Arc<SocketIoConfig>,
+}
+
+/// The admission lock of a namespace, see [`Namespace::lock_admission`]
+pub(crate) struct AdmissionGuard<'a, A: Adapter> {
+    ns: &'a Arc<Namespace<A>>,
+    lock: Option<MutexGuard<'a, ()>>,
+}
+
+impl<A: Adapter> Drop for AdmissionGuard<'_, A> {
+    fn drop(&mut self) {
+        self.lock.take();
+        if self.ns.readmit.swap(false, Ordering::SeqCst) {
+            self.ns.admit_queued();
+        }
+    }
+}
 
 pub struct Namespace<A: Adapter> {
     pub path: Cow<'static, str>,
     pub(crate) adapter: A,
     handler: BoxedConnectHandler<A>,
     sockets: RwLock<HashMap<Sid, Arc<Socket<A>>>>,
+    /// The sessions of the disconnected sockets that can be recovered
+    pub(crate) recovery: RecoveryStore,
+    /// The senders of the [`ConnectionEvents`] streams of this namespace
+    events: Mutex<Vec<mpsc::Sender<ConnectionEvent>>>,
+    /// The connect middlewares, in their registration order
+    connect_middlewares: RwLock<Vec<ConnectMiddleware>>,
+    /// The outbound middlewares, in their registration order
+    outbound: RwLock<Vec<OutboundMiddleware>>,
+    /// The prefix added to the outbound event names and stripped from the inbound ones
+    event_prefix: RwLock<Option<Cow<'static, str>>>,
+    /// The transformer applied to the data of the ack responses
+    ack_transformer: RwLock<Option<AckTransformer>>,
+    /// Whether the [`Data`](crate::extract::Data) extractors unwrap the single element arrays
+    unwrap_single_element: AtomicBool,
+    /// The custom data added to the payload of the connect packets
+    connect_data: RwLock<Option<Map<String, Value>>>,
+    /// The maximum number of sockets and the policy applied to the connections over it
+    max_sockets: RwLock<Option<(usize, MaxSocketsPolicy)>>,
+    /// The connections waiting for a slot, in their arrival order
+    queue: Mutex<VecDeque<QueuedConnection>>,
+    /// Held while the connections are admitted to the namespace if its number of sockets is limited,
+    /// so that concurrent connections cannot exceed the limit
+    admission: Mutex<()>,
+    /// Set when the queued connections must be admitted by the holder of the admission lock
+    readmit: AtomicBool,
+    /// Whether the namespace was created on the fly by the dynamic namespace handler,
+    /// in which case it is removed once it has no sockets left
+    dynamic: AtomicBool,
+    /// The client owning this namespace
+    pub(crate) client: Weak<Client<A>>,
 }
 
 impl<A: Adapter> Namespace<A> {
-    pub fn new<C, T>(path: Cow<'static, str>, handler: C) -> Arc<Self>
+    pub fn new<C, T>(path: Cow<'static, str>, client: Weak<Client<A>>, handler: C) -> Arc<Self>
     where
         C: ConnectHandler<A, T> + Send + Sync + 'static,
         T: Send + Sync + 'static,
@@ -33,6 +101,20 @@ impl<A: Adapter> Namespace<A> {
             handler: MakeErasedHandler::new_ns_boxed(handler),
             sockets: HashMap::new().into(),
             adapter: A::new(ns.clone()),
+            recovery: RecoveryStore::default(),
+            events: Mutex::new(Vec::new()),
+            connect_middlewares: RwLock::new(Vec::new()),
+            outbound: RwLock::new(Vec::new()),
+            event_prefix: RwLock::new(None),
+            ack_transformer: RwLock::new(None),
+            unwrap_single_element: AtomicBool::new(true),
+            connect_data: RwLock::new(None),
+            max_sockets: RwLock::new(None),
+            queue: Mutex::new(VecDeque::new()),
+            admission: Mutex::new(()),
+            readmit: AtomicBool::new(false),
+            dynamic: AtomicBool::new(false),
+            client,
         })
     }
 
@@ -44,39 +126,412 @@ impl<A: Adapter> Namespace<A> {
         auth: Option<String>,
         config: Arc<SocketIoConfig>,
     ) -> Result<(), serde_json::Error> {
-        let socket: Arc<Socket<A>> = Socket::new(sid, self.clone(), esocket.clone(), config).into();
+        let session = match (&config.connection_recovery, &auth) {
+            (Some(recovery), Some(auth)) => self
+                .recovery
+                .recover(auth, recovery.max_disconnection_duration),
+            _ => None,
+        };
+        let mut socket = Socket::new(sid, self.clone(), esocket.clone(), config);
+        let session = session.map(|session| {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                "recovering session {} for socket {}",
+                session.buffer.pid,
+                sid
+            );
+            socket.recover(session.sid, session.buffer);
+            #[cfg(feature = "extensions")]
+            {
+                socket.extensions = session.extensions;
+            }
+            (session.sid, session.rooms, session.missed_packets)
+        });
+        let socket: Arc<Socket<A>> = socket.into();
 
         self.sockets.write().unwrap().insert(sid, socket.clone());
+        if let Some(client) = self.client.upgrade() {
+            client.socket_added(sid);
+        }
+        // Each socket is in a room named after its id so that it can be targeted directly
+        if let Err(_e) = self.adapter.add_all(sid, sid) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error joining the socket room: {:?}", _e);
+        }
 
         let protocol = esocket.protocol.into();
-        if let Err(_e) = socket.send(Packet::connect(&self.path, socket.id, protocol)) {
+        let mut packet = match socket.recovery_pid() {
+            Some(pid) => Packet::connect_recoverable(&self.path, socket.id, pid),
+            None => Packet::connect(&self.path, socket.id, protocol),
+        };
+        self.add_connect_data(&mut packet);
+        if let Err(_e) = socket.send(packet) {
             #[cfg(feature = "tracing")]
             tracing::debug!("error sending connect packet: {:?}, closing conn", _e);
             esocket.close(engineioxide::DisconnectReason::PacketParsingError);
             return Ok(());
         }
 
+        let event = match session {
+            Some((old_sid, rooms, missed_packets)) => {
+                if let Err(_e) = self.adapter.add_all(sid, rooms) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error rejoining rooms of recovered socket: {:?}", _e);
+                }
+                socket.replay(missed_packets);
+                socket.flush_offline(old_sid);
+                ConnectionEvent::Reconnected(sid, old_sid)
+            }
+            None => ConnectionEvent::Connected(sid),
+        };
+
+        self.send_event(event);
+        socket.spawn_idle_timeout_task();
         self.handler.call(socket, auth);
         Ok(())
     }
 
+    /// Creates a new stream of the [`ConnectionEvent`]s of this namespace
+    pub fn events(&self) -> ConnectionEvents {
+        let (tx, events) = ConnectionEvents::new();
+        self.events.lock().unwrap().push(tx);
+        events
+    }
+
+    /// Adds a middleware called on each connection to this namespace
+    pub fn use_connect<E: std::fmt::Display>(
+        &self,
+        middleware: impl Fn(&ConnectContext<'_>) -> Result<(), E> + Send + Sync + 'static,
+    ) {
+        let middleware = move |ctx: &ConnectContext<'_>| middleware(ctx).map_err(|e| e.to_string());
+        self.connect_middlewares
+            .write()
+            .unwrap()
+            .push(Box::new(middleware));
+    }
+
+    /// Runs the connect middlewares in their registration order, until one of them refuses the connection
+    pub(crate) fn check_connect(
+        &self,
+        req_parts: &http::request::Parts,
+        auth: Option<&str>,
+    ) -> Result<(), String> {
+        let middlewares = self.connect_middlewares.read().unwrap();
+        if middlewares.is_empty() {
+            return Ok(());
+        }
+        let ctx = ConnectContext::new(req_parts, auth);
+        middlewares
+            .iter()
+            .try_for_each(|middleware| middleware(&ctx))
+    }
+
+    /// Adds a middleware called on each packet sent to the sockets of this namespace
+    pub fn use_outbound(&self, middleware: impl Fn(&mut Packet<'_>) + Send + Sync + 'static) {
+        self.outbound.write().unwrap().push(Box::new(middleware));
+    }
+
+    /// Runs the outbound middlewares on a packet, in their registration order
+    pub(crate) fn apply_outbound(&self, packet: &mut Packet<'_>) {
+        for middleware in self.outbound.read().unwrap().iter() {
+            middleware(packet);
+        }
+    }
+
+    /// Buffers a broadcasted packet for the disconnected sessions that can be recovered.
+    /// The outbound middlewares and the event prefix are applied as for the connected sockets.
+    pub(crate) fn buffer_missed(&self, opts: &BroadcastOptions, packet: &Packet<'_>) {
+        self.recovery.buffer(opts, packet, |packet| {
+            self.apply_outbound(packet);
+            self.add_event_prefix(packet);
+        });
+    }
+
+    /// Sets the prefix added to the outbound event names and stripped from the inbound ones
+    pub fn set_event_prefix(&self, prefix: Cow<'static, str>) {
+        self.event_prefix.write().unwrap().replace(prefix);
+    }
+
+    /// Adds the event prefix, if it is set, to the name of an event packet
+    pub(crate) fn add_event_prefix(&self, packet: &mut Packet<'_>) {
+        if let Some(prefix) = self.event_prefix.read().unwrap().as_ref() {
+            if let PacketData::Event(e, _, _) | PacketData::BinaryEvent(e, _, _) = &mut packet.inner
+            {
+                *e = Cow::Owned(format!("{prefix}{e}"));
+            }
+        }
+    }
+
+    /// Strips the event prefix, if it is set, from an inbound event name.
+    /// Returns `None` if the event name doesn't start with the prefix.
+    pub(crate) fn strip_event_prefix<'a>(&self, e: &'a str) -> Option<&'a str> {
+        match self.event_prefix.read().unwrap().as_ref() {
+            Some(prefix) => e.strip_prefix(prefix.as_ref()),
+            None => Some(e),
+        }
+    }
+
+    /// Sets the transformer applied to the data of the ack responses sent by the sockets of this namespace
+    pub fn set_ack_transformer(
+        &self,
+        transformer: impl Fn(Value) -> Value + Send + Sync + 'static,
+    ) {
+        self.ack_transformer
+            .write()
+            .unwrap()
+            .replace(Box::new(transformer));
+    }
+
+    /// Applies the ack transformer, if it is set, to the data of an ack response
+    pub(crate) fn transform_ack(&self, data: Value) -> Value {
+        match self.ack_transformer.read().unwrap().as_ref() {
+            Some(transformer) => transformer(data),
+            None => data,
+        }
+    }
+
+    /// Sets whether the data extractors unwrap the single element arrays of the events of this namespace
+    pub fn set_unwrap_single_element(&self, enabled: bool) {
+        self.unwrap_single_element.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns true if the data extractors unwrap the single element arrays
+    pub(crate) fn unwrap_single_element(&self) -> bool {
+        self.unwrap_single_element.load(Ordering::Relaxed)
+    }
+
+    /// Sets the custom data added to the payload of the connect packets sent by this namespace
+    pub fn set_connect_data(&self, data: Map<String, Value>) {
+        self.connect_data.write().unwrap().replace(data);
+    }
+
+    /// Adds the custom connect data, if it is set, to the payload of a connect packet.
+    /// The `sid` and `pid` fields set by the server are never overridden.
+    /// The v4 connect packets have no payload and are unchanged.
+    fn add_connect_data(&self, packet: &mut Packet<'_>) {
+        let connect_data = self.connect_data.read().unwrap();
+        let (Some(data), PacketData::Connect(Some(payload))) =
+            (connect_data.as_ref(), &mut packet.inner)
+        else {
+            return;
+        };
+        let Ok(mut map) = serde_json::from_str::<Map<String, Value>>(payload) else {
+            return;
+        };
+        for (key, value) in data {
+            if !map.contains_key(key) {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+        *payload = Value::Object(map).to_string();
+    }
+
+    /// Sends a [`ConnectionEvent`] to all the [`ConnectionEvents`] streams without waiting.
+    /// Events are dropped for the streams whose buffer is full and closed streams are removed.
+    pub(crate) fn send_event(&self, event: ConnectionEvent) {
+        self.events
+            .lock()
+            .unwrap()
+            .retain(|tx| match tx.try_send(event) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("connection events buffer full, dropping event {:?}", event);
+                    true
+                }
+                Err(TrySendError::Closed(_)) => false,
+            });
+    }
+
     /// Removes a socket from a namespace and propagate the event to the adapter
     pub fn remove_socket(&self, sid: Sid) -> Result<(), AdapterError> {
-        self.sockets.write().unwrap().remove(&sid);
+        let removed = self.sockets.write().unwrap().remove(&sid);
+        if let (Some(_), Some(client)) = (removed, self.client.upgrade()) {
+            client.socket_removed(sid);
+            if self.is_dynamic() {
+                client.remove_idle_ns(self);
+            }
+        }
         self.adapter
             .del_all(sid)
             .map_err(|err| AdapterError(Box::new(err)))
     }
 
+    /// Returns the number of sockets connected to this namespace
+    pub fn len(&self) -> usize {
+        self.sockets.read().unwrap().len()
+    }
+
+    /// Sets the maximum number of sockets of this namespace and the policy applied to the connections over it
+    pub fn set_max_sockets(self: &Arc<Self>, max: usize, policy: MaxSocketsPolicy) {
+        self.max_sockets.write().unwrap().replace((max, policy));
+        // Connections may be admitted if the limit is raised
+        self.admit_queued();
+    }
+
+    /// Marks the namespace as created by the dynamic namespace handler
+    pub(crate) fn set_dynamic(&self) {
+        self.dynamic.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the namespace was created by the dynamic namespace handler
+    pub(crate) fn is_dynamic(&self) -> bool {
+        self.dynamic.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if no socket is connected to this namespace nor waiting to join it
+    pub(crate) fn is_idle(&self) -> bool {
+        self.len() == 0 && self.queue.lock().unwrap().is_empty()
+    }
+
+    /// Returns true if the number of sockets of this namespace is limited
+    pub(crate) fn is_limited(&self) -> bool {
+        self.max_sockets.read().unwrap().is_some()
+    }
+
+    /// Takes the admission lock of the namespace, held while a connection is checked against the limit and connected.
+    ///
+    /// When it is released, the queued connections are admitted if slots were freed while it was held
+    /// (e.g. by a socket disconnected from its connect handler).
+    pub(crate) fn lock_admission(self: &Arc<Self>) -> AdmissionGuard<'_, A> {
+        // The lock guards no data, it is still usable if a connect handler panicked while it was held
+        let lock = self.admission.lock().unwrap_or_else(|e| e.into_inner());
+        AdmissionGuard {
+            ns: self,
+            lock: Some(lock),
+        }
+    }
+
+    /// Returns the policy to apply to a new connection if the namespace is full.
+    /// It should be called with the admission lock held.
+    ///
+    /// The namespace is also considered full while connections are queued,
+    /// so that the new connections don't overtake them.
+    pub(crate) fn full_policy(&self) -> Option<MaxSocketsPolicy> {
+        let (max, policy) = (*self.max_sockets.read().unwrap())?;
+        let full = self.len() >= max || !self.queue.lock().unwrap().is_empty();
+        full.then_some(policy)
+    }
+
+    /// Queues a connection until a slot is available in the namespace.
+    /// It is rejected with a `connect_error` packet if no slot is available after `timeout`.
+    pub(crate) fn enqueue(
+        self: &Arc<Self>,
+        esocket: Arc<engineioxide::Socket<SocketData>>,
+        auth: Option<String>,
+        config: Arc<SocketIoConfig>,
+        timeout: std::time::Duration,
+    ) {
+        let sid = esocket.id;
+        #[cfg(feature = "tracing")]
+        tracing::debug!("namespace {} is full, queuing socket {}", self.path, sid);
+        self.queue.lock().unwrap().push_back(QueuedConnection {
+            esocket,
+            auth,
+            config,
+        });
+
+        let ns = Arc::downgrade(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let Some(ns) = ns.upgrade() else { return };
+            if let Some(conn) = ns.remove_queued(sid) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("socket {} waited too long for namespace {}", sid, ns.path);
+                let packet = Packet::encode_connect_error(&ns.path, "Namespace queue timeout");
+                if let Err(_e) = conn.esocket.emit(packet) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("error while sending connect error packet: {}", _e);
+                }
+            }
+        });
+        // A slot may have been freed in the meantime
+        self.admit_queued();
+    }
+
+    /// Removes a queued connection, returns it if it was still queued
+    pub(crate) fn remove_queued(&self, sid: Sid) -> Option<QueuedConnection> {
+        let mut queue = self.queue.lock().unwrap();
+        let i = queue.iter().position(|conn| conn.esocket.id == sid)?;
+        queue.remove(i)
+    }
+
+    /// Connects the queued connections, in their arrival order, while slots are available.
+    ///
+    /// If the admission lock is already held, they are admitted by its holder once it is released.
+    pub(crate) fn admit_queued(self: &Arc<Self>) {
+        let Some((max, _)) = *self.max_sockets.read().unwrap() else {
+            return;
+        };
+        // The flag is set before trying the lock so that a holder releasing it concurrently sees it
+        self.readmit.store(true, Ordering::SeqCst);
+        let lock = match self.admission.try_lock() {
+            Ok(lock) => lock,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return,
+        };
+        self.readmit.store(false, Ordering::SeqCst);
+        let _admission = AdmissionGuard {
+            ns: self,
+            lock: Some(lock),
+        };
+        // The connections are also checked against the limit of the server, under its admission lock
+        let client = self.client.upgrade();
+        let client = client
+            .as_ref()
+            .filter(|c| c.config.max_connections.is_some());
+        let _client_admission = match client {
+            Some(client) => match client.try_lock_admission() {
+                Some(admission) => Some(admission),
+                None => return,
+            },
+            None => None,
+        };
+        while self.len() < max {
+            let Some(conn) = self.queue.lock().unwrap().pop_front() else {
+                return;
+            };
+            if conn.esocket.is_closed() {
+                continue;
+            }
+            let sid = conn.esocket.id;
+            if let Some(message) = client.and_then(|c| c.check_connections(sid)) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    "refusing queued socket {} to namespace {}: {}",
+                    sid,
+                    self.path,
+                    message
+                );
+                let packet = Packet::encode_connect_error(&self.path, message);
+                if let Err(_e) = conn.esocket.emit(packet) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("error while sending connect error packet: {}", _e);
+                }
+                continue;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!("admitting queued socket {} to namespace {}", sid, self.path);
+            conn.esocket.data.cancel_connect_timeout();
+            if let Err(_e) = self
+                .clone()
+                .connect(sid, conn.esocket, conn.auth, conn.config)
+            {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error while admitting queued socket {}: {}", sid, _e);
+            }
+        }
+    }
+
     pub fn has(&self, sid: Sid) -> bool {
-        self.sockets.read().unwrap().values().any(|s| s.id == sid)
+        self.sockets.read().unwrap().contains_key(&sid)
     }
 
-    pub fn recv(&self, sid: Sid, packet: PacketData<'_>) -> Result<(), Error> {
+    pub fn recv(&self, sid: Sid, packet: PacketData<'_>, payload_len: usize) -> Result<(), Error> {
         match packet {
             PacketData::Connect(_) => unreachable!("connect packets should be handled before"),
             PacketData::ConnectError => Err(Error::InvalidPacketType),
-            packet => self.get_socket(sid)?.recv(packet),
+            packet => self.get_socket(sid)?.recv_with_len(packet, payload_len),
         }
     }
 
@@ -93,12 +548,34 @@ impl<A: Adapter> Namespace<A> {
         self.sockets.read().unwrap().values().cloned().collect()
     }
 
+    /// Disconnects all the sockets of this namespace with the given reason and calls their disconnect handlers.
+    /// The underlying connections are kept open for the other namespaces.
+    ///
+    /// A socket whose disconnect packet cannot be sent because its buffer is full is still disconnected.
+    /// All the sockets are disconnected even if an error occurs, the first error is then returned.
+    pub fn disconnect_all(&self, reason: DisconnectReason) -> Result<(), Error> {
+        let mut res = Ok(());
+        for socket in self.get_sockets() {
+            let err = match socket.clone().disconnect_with(reason) {
+                Ok(()) => continue,
+                Err(DisconnectError::InternalChannelFull) => match socket.close(reason) {
+                    Ok(()) => continue,
+                    Err(e) => e,
+                },
+                Err(DisconnectError::Adapter(e)) => e,
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error while disconnecting socket: {:?}", err);
+            res = res.and(Err(err.into()));
+        }
+        res
+    }
+
     /// Closes the entire namespace :
-    /// * Closes the adapter
     /// * Closes all the sockets and their underlying connections
     /// * Removes all the sockets from the namespace
+    /// * Closes the adapter
     pub async fn close(&self) {
-        self.adapter.close().ok();
         #[cfg(feature = "tracing")]
         tracing::debug!("closing all sockets in namespace {}", self.path);
         let sockets = self.sockets.read().unwrap().clone();
@@ -106,13 +583,21 @@ impl<A: Adapter> Namespace<A> {
         self.sockets.write().unwrap().shrink_to_fit();
         #[cfg(feature = "tracing")]
         tracing::debug!("all sockets in namespace {} closed", self.path);
+        if let Err(_e) = self.adapter.close().await {
+            #[cfg(feature = "tracing")]
+            tracing::error!(
+                "error while closing the adapter of namespace {}: {}",
+                self.path,
+                _e
+            );
+        }
     }
 }
 
 #[cfg(test)]
 impl<A: Adapter> Namespace<A> {
     pub fn new_dummy<const S: usize>(sockets: [Sid; S]) -> Arc<Self> {
-        let ns = Namespace::new(Cow::Borrowed("/"), || {});
+        let ns = Namespace::new(Cow::Borrowed("/"), Weak::new(), || {});
         for sid in sockets {
             ns.sockets
                 .write()
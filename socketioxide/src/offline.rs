@@ -0,0 +1,69 @@
+//! Store-and-forward of the messages emitted to disconnected sockets.
+//!
+//! When a message is emitted with [`Socket::emit`] to a socket that is not [`connected`] anymore,
+//! it is given to the [`OfflineStore`] set with [`SocketIoBuilder::offline_store`].
+//! By default the [`NoopOfflineStore`] is used and the message is dropped.
+//!
+//! The stored messages are flushed to the client when it reconnects and recovers its session.
+//! Therefore the [connection state recovery](crate::recovery) must be enabled for the messages to be delivered.
+//!
+//! [`Socket::emit`]: crate::socket::Socket::emit
+//! [`connected`]: crate::socket::Socket::connected
+//! [`SocketIoBuilder::offline_store`]: crate::SocketIoBuilder::offline_store
+//!
+//! ## Example
+//! ```
+//! # use socketioxide::{SocketIo, offline::OfflineStore, packet::Packet};
+//! # use engineioxide::sid::Sid;
+//! # use std::{collections::HashMap, sync::Mutex, time::Duration};
+//! #[derive(Debug, Default)]
+//! struct MemoryStore(Mutex<HashMap<(String, Sid), Vec<Packet<'static>>>>);
+//!
+//! impl OfflineStore for MemoryStore {
+//!     fn store(&self, ns: &str, sid: Sid, packet: Packet<'static>) -> bool {
+//!         let mut map = self.0.lock().unwrap();
+//!         map.entry((ns.to_string(), sid)).or_default().push(packet);
+//!         true
+//!     }
+//!     fn take(&self, ns: &str, sid: Sid) -> Vec<Packet<'static>> {
+//!         let mut map = self.0.lock().unwrap();
+//!         map.remove(&(ns.to_string(), sid)).unwrap_or_default()
+//!     }
+//! }
+//!
+//! let (_, io) = SocketIo::builder()
+//!     .connection_recovery(Duration::from_secs(120), 128)
+//!     .offline_store(MemoryStore::default())
+//!     .build_svc();
+//! ```
+use engineioxide::sid::Sid;
+
+use crate::packet::Packet;
+
+/// A store for the messages emitted to disconnected sockets.
+///
+/// Messages are identified by the namespace path and the id of the socket they were emitted to.
+pub trait OfflineStore: std::fmt::Debug + Send + Sync + 'static {
+    /// Stores a packet emitted to the disconnected socket `sid` of the namespace `ns`.
+    ///
+    /// Returns `false` if the packet was not stored,
+    /// the emission will then fail with a [`SocketError::Closed`](crate::SocketError::Closed) error.
+    fn store(&self, ns: &str, sid: Sid, packet: Packet<'static>) -> bool;
+
+    /// Takes all the packets stored for the socket `sid` of the namespace `ns`, in their emission order.
+    fn take(&self, ns: &str, sid: Sid) -> Vec<Packet<'static>>;
+}
+
+/// The default [`OfflineStore`], it doesn't store anything and messages emitted to disconnected sockets are dropped.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopOfflineStore;
+
+impl OfflineStore for NoopOfflineStore {
+    fn store(&self, _: &str, _: Sid, _: Packet<'static>) -> bool {
+        false
+    }
+
+    fn take(&self, _: &str, _: Sid) -> Vec<Packet<'static>> {
+        Vec::new()
+    }
+}
@@ -6,19 +6,28 @@
 //! There is two types of operators:
 //! * [`ConfOperators`]: Chainable operators to configure the message to be sent.
 //! * [`BroadcastOperators`]: Chainable operators to select sockets to send a message to and to configure the message to be sent.
+//!
+//! A [`ChunkedBroadcast`] can also be created from the [`BroadcastOperators`] to fan out a message in batches.
 use std::borrow::Cow;
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use engineioxide::sid::Sid;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
 
-use crate::ack::{AckInnerStream, AckStream};
+use crate::ack::{AckInnerStream, AckOrderedStream, AckResponse, AckStream};
 use crate::adapter::LocalAdapter;
-use crate::errors::{BroadcastError, DisconnectError};
+use crate::errors::{AckError, BroadcastError, DisconnectError};
 use crate::extract::SocketRef;
-use crate::socket::Socket;
+use crate::handler::Spawner;
+use crate::socket::{CompressCache, Socket};
 use crate::SendError;
 use crate::{
-    adapter::{Adapter, BroadcastFlags, BroadcastOptions, Room},
+    adapter::{Adapter, BroadcastFlags, BroadcastOptions, Room, RoomPattern},
     ns::Namespace,
     packet::Packet,
 };
@@ -26,6 +35,7 @@ use crate::{
 /// A trait for types that can be used as a room parameter.
 ///
 /// [`String`], [`Vec<String>`], [`Vec<&str>`], [`&'static str`](str) and const arrays are implemented by default.
+/// It is also implemented for all the types implementing [`ToRoom`].
 pub trait RoomParam: 'static {
     /// The type of the iterator returned by `into_room_iter`.
     type IntoIter: Iterator<Item = Room>;
@@ -34,6 +44,54 @@ pub trait RoomParam: 'static {
     fn into_room_iter(self) -> Self::IntoIter;
 }
 
+/// A trait for typed rooms, to get compile-time checked room names instead of strings.
+///
+/// Every type implementing it can be used as a [`RoomParam`],
+/// for example with [`Socket::join`](crate::socket::Socket::join) or the `to`, `within` and `except` operators.
+/// The rooms are still strings for the adapter and the other servers.
+///
+/// #### Example
+/// ```
+/// # use socketioxide::{SocketIo, extract::*, adapter::Room, operators::ToRoom};
+/// # use serde_json::Value;
+/// enum Channel {
+///     Lobby,
+///     Game(u32),
+/// }
+///
+/// impl ToRoom for Channel {
+///     fn to_room(&self) -> Room {
+///         match self {
+///             Channel::Lobby => "lobby".into(),
+///             Channel::Game(id) => format!("game:{id}").into(),
+///         }
+///     }
+/// }
+///
+/// let (_, io) = SocketIo::new_svc();
+/// io.ns("/", |socket: SocketRef| {
+///     socket.join(Channel::Lobby).ok();
+///     socket.on("join_game", |socket: SocketRef, Data::<u32>(id)| {
+///         socket.leave(Channel::Lobby).ok();
+///         socket.join(Channel::Game(id)).ok();
+///         // Sent to the "game:{id}" room
+///         socket.to(Channel::Game(id)).emit("joined", socket.id.as_str()).ok();
+///     });
+/// });
+/// ```
+pub trait ToRoom {
+    /// Returns the name of the room.
+    fn to_room(&self) -> Room;
+}
+
+impl<T: ToRoom + 'static> RoomParam for T {
+    type IntoIter = std::iter::Once<Room>;
+    #[inline(always)]
+    fn into_room_iter(self) -> Self::IntoIter {
+        std::iter::once(self.to_room())
+    }
+}
+
 impl RoomParam for Room {
     type IntoIter = std::iter::Once<Room>;
     #[inline(always)]
@@ -105,12 +163,14 @@ impl RoomParam for Sid {
 pub struct ConfOperators<'a, A: Adapter = LocalAdapter> {
     binary: Vec<Vec<u8>>,
     timeout: Option<Duration>,
+    idempotency_key: Option<String>,
     socket: &'a Socket<A>,
 }
 /// Chainable operators to select sockets to send a message to and to configure the message to be sent.
 pub struct BroadcastOperators<A: Adapter = LocalAdapter> {
     binary: Vec<Vec<u8>>,
     timeout: Option<Duration>,
+    idempotency_key: Option<String>,
     ns: Arc<Namespace<A>>,
     opts: BroadcastOptions,
 }
@@ -124,6 +184,7 @@ impl<A: Adapter> From<ConfOperators<'_, A>> for BroadcastOperators<A> {
         Self {
             binary: conf.binary,
             timeout: conf.timeout,
+            idempotency_key: conf.idempotency_key,
             ns: conf.socket.ns.clone(),
             opts,
         }
@@ -136,6 +197,7 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         Self {
             binary: vec![],
             timeout: None,
+            idempotency_key: None,
             socket: sender,
         }
     }
@@ -164,6 +226,18 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         BroadcastOperators::from(self).to(rooms)
     }
 
+    /// Selects the sockets with the given ids.
+    /// See [`BroadcastOperators::to_sids`] for more details.
+    pub fn to_sids(self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).to_sids(sids)
+    }
+
+    /// Applies reusable [`BroadcastOptions`] to select the sockets.
+    /// See [`BroadcastOperators::with_opts`] for more details.
+    pub fn with_opts(self, opts: BroadcastOptions) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).with_opts(opts)
+    }
+
     /// Selects all sockets in the given rooms.
     ///
     /// It does include the current socket contrary to the `to()` operator.
@@ -211,6 +285,50 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         BroadcastOperators::from(self).except(rooms)
     }
 
+    /// Filters out the sockets with the given ids and the current socket.
+    /// See [`BroadcastOperators::except_sids`] for more details.
+    pub fn except_sids(self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).except_sids(sids)
+    }
+
+    /// Selects all sockets in the rooms matching the given glob pattern except the current socket.
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data), Bin(bin)| async move {
+    ///         // In all the rooms starting with "game:" except the current socket
+    ///         socket.bin(bin).to_pattern("game:*").emit("test", data);
+    ///     });
+    /// });
+    pub fn to_pattern(self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).to_pattern(pattern)
+    }
+
+    /// Filters out all sockets selected with the previous operators which are in the rooms
+    /// matching the given glob pattern.
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data), Bin(bin)| async move {
+    ///         // This message will be broadcast to all sockets in the Namespace
+    ///         // except for ones in rooms starting with "admin:" and the current socket
+    ///         socket.bin(bin).except_pattern("admin:*").emit("test", data);
+    ///     });
+    /// });
+    pub fn except_pattern(self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).except_pattern(pattern)
+    }
+
     /// Broadcasts to all sockets only connected on this node (when using multiple nodes).
     /// When using the default in-memory adapter, this operator is a no-op.
     /// #### Example
@@ -228,6 +346,25 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         BroadcastOperators::from(self).local()
     }
 
+    /// Broadcasts to the sockets connected on the other nodes only (when using multiple nodes).
+    /// It is the inverse of [`local`](Self::local), it can be used to forward a message to the rest of the cluster
+    /// once the current node has handled its own sockets.
+    /// When using the default in-memory adapter there is no other node and nobody receives the message.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // This message will be broadcast to the sockets of room1 connected on the other nodes
+    ///         socket.to("room1").remote().emit("test", data);
+    ///     });
+    /// });
+    pub fn remote(self) -> BroadcastOperators<A> {
+        BroadcastOperators::from(self).remote()
+    }
+
     /// Broadcasts to all sockets without any filtering (except the current socket).
     /// #### Example
     /// ```
@@ -300,6 +437,34 @@ impl<'a, A: Adapter> ConfOperators<'a, A> {
         self.binary = binary;
         self
     }
+
+    /// Attaches an idempotency key to the message.
+    ///
+    /// The key is sent as an additional last argument `{"_idempotencyKey": key}`,
+    /// so that the client (or a relay) can detect the messages it has already received,
+    /// for example when a message is sent again after a failed acknowledgement.
+    /// The handlers of the client therefore receive this key as an extra last argument.
+    /// On the server side it is removed from the arguments of the incoming messages before they are extracted,
+    /// and it can be read with the [`IdempotencyKey`] extractor.
+    ///
+    /// Enforcing the deduplication is the responsibility of the application,
+    /// socketioxide doesn't keep track of the keys that were already sent or received.
+    ///
+    /// [`IdempotencyKey`]: crate::extract::IdempotencyKey
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("order", |socket: SocketRef| async move {
+    ///         // The client can drop this message if it already received the key "order-42"
+    ///         socket.with_id("order-42").emit("order-created", 42).ok();
+    ///     });
+    /// });
+    pub fn with_id(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
 }
 
 // ==== impl ConfOperators consume fns ====
@@ -312,7 +477,7 @@ impl<A: Adapter> ConfOperators<'_, A> {
     ///
     /// ## Errors
     /// * When encoding the data into JSON a [`SendError::Serialize`] may be returned.
-    /// * If the underlying engine.io connection is closed a [`SendError::Socket(SocketError::Closed)`]
+    /// * If the socket is disconnected or its underlying engine.io connection is closed a [`SendError::Socket(SocketError::Closed)`]
     /// will be returned and the provided data to be send will be given back in the error.
     /// * If the packet buffer is full, a [`SendError::Socket(SocketError::InternalChannelFull)`]
     /// will be returned and the provided data to be send will be given back in the error.
@@ -345,7 +510,13 @@ impl<A: Adapter> ConfOperators<'_, A> {
         event: impl Into<Cow<'static, str>>,
         data: T,
     ) -> Result<(), SendError<T>> {
-        use crate::socket::PermitIteratorExt;
+        if !self.socket.connected() {
+            let packet = self.get_packet(event, &data)?;
+            return self
+                .socket
+                .store_offline(packet)
+                .map_err(|e| e.with_value(data).into());
+        }
         let permits = match self.socket.reserve(1 + self.binary.len()) {
             Ok(permits) => permits,
             Err(e) => {
@@ -354,12 +525,105 @@ impl<A: Adapter> ConfOperators<'_, A> {
                 return Err(e.with_value(data).into());
             }
         };
-        let packet = self.get_packet(event, data)?;
+        let mut packet = self.get_packet(event, data)?;
+        self.socket.prepare_packet(&mut packet);
         permits.emit(packet);
 
         Ok(())
     }
 
+    /// Emits a message to the client with an explicit list of arguments and apply the previous operators on the message.
+    ///
+    /// Each element of `args` is sent as one argument of the event, even if it is itself an array.
+    /// See [`Socket::emit_args`] for more details. The errors are the same as with [`emit()`](#method.emit).
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::json;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.bin(vec![vec![1, 2]]).emit_args("test", &[json!([1, 2, 3])]).ok();
+    /// });
+    pub fn emit_args(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        args: &[serde_json::Value],
+    ) -> Result<(), SendError<&[serde_json::Value]>> {
+        self.emit(event, args)
+    }
+
+    /// Emits a message with only binary payloads to the client and apply the previous operators on the message.
+    ///
+    /// The packet doesn't have any JSON data, so the client receives the binary payloads
+    /// as the only arguments of the event. The errors are the same as with [`emit()`](#method.emit).
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Bin(bin)| async move {
+    ///         socket.timeout(std::time::Duration::from_secs(1)).emit_binary("test", bin).ok();
+    ///     });
+    /// });
+    pub fn emit_binary(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        bin: Vec<Vec<u8>>,
+    ) -> Result<(), SendError<()>> {
+        self.bin(bin)
+            .emit(event, [(); 0])
+            .map_err(SendError::discard_value)
+    }
+
+    /// Encodes a message as it would be emitted to the client, without sending it.
+    ///
+    /// It returns the socket.io text frame of the message, as encoded by the socket.io parser.
+    /// The engine.io transport sends it in a message packet (prefixed by `4`).
+    /// The outbound middlewares and the event prefix of the namespace are not applied.
+    ///
+    /// If binary payloads were attached with [`bin`](Self::bin), only the text frame with their placeholders is returned,
+    /// use [`encode_bin`](Self::encode_bin) to get them as well.
+    ///
+    /// It can be used to check the wire compatibility with the socket.io clients.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let frame = socket.with_id("key").encode("hello", "world").unwrap();
+    ///     println!("would send: {}", frame);
+    /// });
+    pub fn encode<T: serde::Serialize>(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<String, serde_json::Error> {
+        self.encode_bin(event, data).map(|(frame, _)| frame)
+    }
+
+    /// Encodes a message as it would be emitted to the client, without sending it.
+    ///
+    /// Like [`encode`](Self::encode) but it also returns the binary payloads,
+    /// sent after the text frame as separate binary frames.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let (frame, bin) = socket.bin(vec![vec![1, 2, 3]]).encode_bin("file", ()).unwrap();
+    ///     assert_eq!(frame, r#"51-["file",null,{"_placeholder":true,"num":0}]"#);
+    ///     assert_eq!(bin, [vec![1, 2, 3]]);
+    /// });
+    pub fn encode_bin<T: serde::Serialize>(
+        mut self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<(String, Vec<Vec<u8>>), serde_json::Error> {
+        Ok(self.get_packet(event, data)?.encode())
+    }
+
     /// Emits a message to the client and wait for acknowledgement.
     ///
     /// The acknowledgement has a timeout specified in the config (5s by default)
@@ -380,7 +644,8 @@ impl<A: Adapter> ConfOperators<'_, A> {
     ///
     /// If the client didn't respond before the timeout, the [`AckStream`] will yield
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `V`,
-    /// an [`AckError::Serde`] will be yielded.
+    /// an [`AckError::Serde`] will be yielded. If the socket is disconnected before
+    /// the client responds, an [`AckError::Disconnected`] will be yielded.
     ///
     /// [`timeout()`]: crate::operators::ConfOperators#method.timeout
     /// [`SocketIoBuilder::ack_timeout`]: crate::SocketIoBuilder#method.ack_timeout
@@ -390,6 +655,7 @@ impl<A: Adapter> ConfOperators<'_, A> {
     /// [`AckError`]: crate::AckError
     /// [`AckError::Serde`]: crate::AckError::Serde
     /// [`AckError::Timeout`]: crate::AckError::Timeout
+    /// [`AckError::Disconnected`]: crate::AckError::Disconnected
     /// [`AckError::Socket`]: crate::AckError::Socket
     /// [`AckError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
     /// [`io::get_socket()`]: crate::SocketIo#method.get_socket
@@ -426,8 +692,8 @@ impl<A: Adapter> ConfOperators<'_, A> {
         };
         let timeout = self.timeout.unwrap_or(self.socket.config.ack_timeout);
         let packet = self.get_packet(event, data)?;
-        let rx = self.socket.send_with_ack_permit(packet, permits);
-        let stream = AckInnerStream::send(rx, timeout, self.socket.id);
+        let (rx, guard) = self.socket.send_with_ack_permit(packet, permits);
+        let stream = AckInnerStream::send_guarded(rx, timeout, self.socket.id, guard);
         Ok(AckStream::<V>::from(stream))
     }
 
@@ -476,12 +742,15 @@ impl<A: Adapter> ConfOperators<'_, A> {
     ) -> Result<Packet<'static>, serde_json::Error> {
         let ns = self.socket.ns.path.clone();
         let data = serde_json::to_value(data)?;
-        let packet = if self.binary.is_empty() {
+        let mut packet = if self.binary.is_empty() {
             Packet::event(ns, event.into(), data)
         } else {
             let binary = std::mem::take(&mut self.binary);
             Packet::bin_event(ns, event.into(), data, binary)
         };
+        if let Some(key) = self.idempotency_key.take() {
+            packet.push_idempotency_key(key);
+        }
         Ok(packet)
     }
 }
@@ -491,6 +760,7 @@ impl<A: Adapter> BroadcastOperators<A> {
         Self {
             binary: vec![],
             timeout: None,
+            idempotency_key: None,
             ns,
             opts: BroadcastOptions::default(),
         }
@@ -499,6 +769,7 @@ impl<A: Adapter> BroadcastOperators<A> {
         Self {
             binary: vec![],
             timeout: None,
+            idempotency_key: None,
             ns,
             opts: BroadcastOptions {
                 sid: Some(sid),
@@ -532,6 +803,91 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.broadcast()
     }
 
+    /// Applies reusable [`BroadcastOptions`] on top of the previous operators.
+    ///
+    /// The flags, rooms and patterns of the options are added to the ones already selected.
+    /// The sender of the options, if it is set, replaces the current one.
+    /// It allows to build a filter once, store it and use it for many emissions.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, adapter::BroadcastOptions};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let unmuted_team = BroadcastOptions::new(None)
+    ///     .with_rooms("team")
+    ///     .with_except("muted");
+    /// io.ns("/", move |socket: SocketRef| {
+    ///     let unmuted_team = unmuted_team.clone();
+    ///     socket.on("score", move |socket: SocketRef| {
+    ///         socket.with_opts(unmuted_team.clone()).emit("score", 1).ok();
+    ///         socket.with_opts(unmuted_team.clone()).emit("cheer", ()).ok();
+    ///     });
+    /// });
+    /// ```
+    pub fn with_opts(mut self, opts: BroadcastOptions) -> Self {
+        let BroadcastOptions {
+            flags,
+            rooms,
+            except,
+            room_patterns,
+            except_patterns,
+            sids,
+            except_sids,
+            sid,
+        } = opts;
+        self.opts.flags.extend(flags);
+        self.opts.rooms.extend(rooms);
+        self.opts.except.extend(except);
+        self.opts.room_patterns.extend(room_patterns);
+        self.opts.except_patterns.extend(except_patterns);
+        self.opts.sids.extend(sids);
+        self.opts.except_sids.extend(except_sids);
+        if sid.is_some() {
+            self.opts.sid = sid;
+        }
+        self
+    }
+
+    /// Selects the socket with the given id.
+    ///
+    /// Every socket joins a room named after its id when it connects,
+    /// so it is equivalent to `to(sid)` and can be combined with the other operators.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, socket::Sid};
+    /// let (_, io) = SocketIo::new_svc();
+    /// let io2 = io.clone();
+    /// io.ns("/", move |socket: SocketRef| {
+    ///     let io = io2.clone();
+    ///     socket.on("private", move |Data::<(Sid, String)>((sid, msg))| {
+    ///         // Send a direct message to the socket with the given id
+    ///         io.of("/").unwrap().to_sid(sid).emit("private", msg).ok();
+    ///     });
+    /// });
+    pub fn to_sid(self, sid: Sid) -> Self {
+        self.to(sid)
+    }
+
+    /// Selects the sockets with the given ids, in addition to the sockets of the selected rooms.
+    ///
+    /// The sockets are targeted directly by the adapter, without going through their rooms.
+    /// Contrary to the `to()` operator, the current socket is selected if its id is given.
+    /// The ids of the sockets that are not connected to the namespace are ignored.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, socket::Sid};
+    /// # fn online_friends(id: Sid) -> Vec<Sid> { vec![] }
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("status", |socket: SocketRef, Data::<String>(status)| {
+    ///         let friends = online_friends(socket.id);
+    ///         socket.to_sids(friends).emit("status", status).ok();
+    ///     });
+    /// });
+    pub fn to_sids(mut self, sids: impl IntoIterator<Item = Sid>) -> Self {
+        self.opts.sids.extend(sids);
+        self
+    }
+
     /// Selects all sockets in the given rooms.
     ///
     /// It does include the current socket contrary to the `to()` operator.
@@ -581,6 +937,79 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.broadcast()
     }
 
+    /// Filters out the sockets with the given ids from the sockets selected with the previous operators.
+    ///
+    /// Like the `except()` operator, it also filters out the current socket.
+    /// The exclusions are combined: a socket is filtered out if its id is given here,
+    /// if it is in one of the excluded rooms or if it is the current socket.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, socket::Sid};
+    /// # use serde_json::Value;
+    /// # fn blocked_by(id: Sid) -> Vec<Sid> { vec![] }
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("chat", |socket: SocketRef, Data::<Value>(data)| {
+    ///         // Everyone in the lobby, except the muted sockets, the ones blocked by the sender
+    ///         // and the sender itself
+    ///         socket.to("lobby")
+    ///             .except("muted")
+    ///             .except_sids(blocked_by(socket.id))
+    ///             .emit("chat", data)
+    ///             .ok();
+    ///     });
+    /// });
+    pub fn except_sids(mut self, sids: impl IntoIterator<Item = Sid>) -> Self {
+        self.opts.except_sids.extend(sids);
+        self.broadcast()
+    }
+
+    /// Selects all sockets in the rooms matching the given glob pattern except the current socket.
+    /// If it is called from the `Namespace` level there will be no difference with the `within()` operator
+    ///
+    /// The pattern is expanded by the adapter against the known rooms at broadcast time.
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // In all the rooms starting with "game:" and in the lobby room except the current socket
+    ///         socket
+    ///             .to_pattern("game:*")
+    ///             .to("lobby")
+    ///             .emit("test", data);
+    ///     });
+    /// });
+    pub fn to_pattern(mut self, pattern: impl Into<RoomPattern>) -> Self {
+        self.opts.room_patterns.insert(pattern.into());
+        self.broadcast()
+    }
+
+    /// Filters out all sockets selected with the previous operators which are in the rooms
+    /// matching the given glob pattern.
+    ///
+    /// The pattern is expanded by the adapter against the known rooms at broadcast time.
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // This message will be broadcast to all sockets in the Namespace
+    ///         // except for ones in rooms starting with "admin:" and the current socket
+    ///         socket.broadcast().except_pattern("admin:*").emit("test", data);
+    ///     });
+    /// });
+    pub fn except_pattern(mut self, pattern: impl Into<RoomPattern>) -> Self {
+        self.opts.except_patterns.insert(pattern.into());
+        self.broadcast()
+    }
+
     /// Broadcasts to all sockets only connected on this node (when using multiple nodes).
     /// When using the default in-memory adapter, this operator is a no-op.
     /// #### Example
@@ -599,6 +1028,26 @@ impl<A: Adapter> BroadcastOperators<A> {
         self
     }
 
+    /// Broadcasts to the sockets connected on the other nodes only (when using multiple nodes).
+    /// It is the inverse of [`local`](Self::local), it can be used to forward a message to the rest of the cluster
+    /// once the current node has handled its own sockets.
+    /// When using the default in-memory adapter there is no other node and nobody receives the message.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // This message will be broadcast to the sockets of room1 connected on the other nodes
+    ///         socket.to("room1").remote().emit("test", data);
+    ///     });
+    /// });
+    pub fn remote(mut self) -> Self {
+        self.opts.flags.insert(BroadcastFlags::Remote);
+        self
+    }
+
     /// Broadcasts to all sockets without any filtering (except the current socket).
     /// #### Example
     /// ```
@@ -672,6 +1121,68 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.binary = binary;
         self
     }
+
+    /// Attaches an idempotency key to the message.
+    ///
+    /// The key is sent as an additional last argument `{"_idempotencyKey": key}`,
+    /// so that the client (or a relay) can detect the messages it has already received,
+    /// for example when a message is sent again after a failed acknowledgement.
+    /// The handlers of the client therefore receive this key as an extra last argument.
+    /// On the server side it is removed from the arguments of the incoming messages before they are extracted,
+    /// and it can be read with the [`IdempotencyKey`] extractor.
+    ///
+    /// Enforcing the deduplication is the responsibility of the application,
+    /// socketioxide doesn't keep track of the keys that were already sent or received.
+    ///
+    /// [`IdempotencyKey`]: crate::extract::IdempotencyKey
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("order", |socket: SocketRef| async move {
+    ///         // The client can drop this message if it already received the key "order-42"
+    ///         socket.broadcast().with_id("order-42").emit("order-created", 42).ok();
+    ///     });
+    /// });
+    pub fn with_id(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Returns the rooms targeted by the previous operators.
+    ///
+    /// Room patterns added with [`to_pattern()`](#method.to_pattern) are not included
+    /// as they are only expanded when the message is sent.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let op = socket.to("room1").to(["room2", "room3"]).except("room2");
+    ///     assert_eq!(op.targeted_rooms().len(), 3);
+    ///     assert!(op.excluded_rooms().contains("room2"));
+    /// });
+    pub fn targeted_rooms(&self) -> &HashSet<Room> {
+        &self.opts.rooms
+    }
+
+    /// Returns the rooms excluded by the previous operators.
+    ///
+    /// Room patterns added with [`except_pattern()`](#method.except_pattern) are not included
+    /// as they are only expanded when the message is sent.
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let op = socket.broadcast().except(["room1", "room2"]);
+    ///     assert!(op.targeted_rooms().is_empty());
+    ///     assert_eq!(op.excluded_rooms().len(), 2);
+    /// });
+    pub fn excluded_rooms(&self) -> &HashSet<Room> {
+        &self.opts.except
+    }
 }
 
 // ==== impl BroadcastOperators consume fns ====
@@ -689,6 +1200,10 @@ impl<A: Adapter> BroadcastOperators<A> {
     /// * If the packet buffer is full for a given socket, a [`BroadcastError::Socket(SocketError::InternalChannelFull)`]
     /// will be retured.
     /// See [`SocketIoBuilder::max_buffer_size`] option for more infos on internal buffer config
+    /// * If the adapter fails to deliver the packet to other nodes, a [`BroadcastError::Adapter`] will be returned.
+    ///
+    /// A [`BroadcastError::Serialize`] is returned before anything is sent, so unlike the other
+    /// variants it is not worth retrying the emission.
     ///
     /// > **Note**: If a error is returned because of a specific socket, the message will still be sent to all other sockets.
     ///
@@ -720,6 +1235,7 @@ impl<A: Adapter> BroadcastOperators<A> {
         data: T,
     ) -> Result<(), BroadcastError> {
         let packet = self.get_packet(event, data)?;
+        self.ns.buffer_missed(&self.opts, &packet);
         if let Err(e) = self.ns.adapter.broadcast(packet, self.opts) {
             #[cfg(feature = "tracing")]
             tracing::debug!("broadcast error: {e:?}");
@@ -728,6 +1244,236 @@ impl<A: Adapter> BroadcastOperators<A> {
         Ok(())
     }
 
+    /// Emits the same event to several rooms with a different data for each of them.
+    ///
+    /// The data of each entry is serialized once and the entries with identical data are grouped
+    /// into a single broadcast to all their rooms, so a socket in several of these rooms receives it only once.
+    /// The rooms of each entry are added to the rooms selected with the previous operators
+    /// and the other operators (e.g. `except`, `bin`) apply to every entry.
+    ///
+    /// A [`BroadcastError::Serialize`] is returned before anything is sent.
+    /// Otherwise the emission continues for every entry and the socket errors are all returned
+    /// in a single [`BroadcastError::Socket`].
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("scores", |socket: SocketRef| {
+    ///         let scores = [("team1", 10), ("team2", 7), ("team3", 10)];
+    ///         socket.broadcast().emit_many("score", scores).ok();
+    ///     });
+    /// });
+    /// ```
+    pub fn emit_many<R: RoomParam, T: serde::Serialize>(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        entries: impl IntoIterator<Item = (R, T)>,
+    ) -> Result<(), BroadcastError> {
+        let event = event.into();
+        let mut groups: Vec<(serde_json::Value, Vec<Room>)> = Vec::new();
+        for (rooms, data) in entries {
+            let data = serde_json::to_value(data)?;
+            match groups.iter_mut().find(|(d, _)| *d == data) {
+                Some((_, group)) => group.extend(rooms.into_room_iter()),
+                None => groups.push((data, rooms.into_room_iter().collect())),
+            }
+        }
+
+        let mut socket_errors = Vec::new();
+        let mut error = None;
+        for (data, rooms) in groups {
+            let mut op = Self {
+                binary: self.binary.clone(),
+                timeout: self.timeout,
+                idempotency_key: self.idempotency_key.clone(),
+                ns: self.ns.clone(),
+                opts: self.opts.clone(),
+            };
+            op.opts.rooms.extend(rooms);
+            match op.broadcast().emit(event.clone(), &data) {
+                Ok(()) => (),
+                Err(BroadcastError::Socket(errors)) => socket_errors.extend(errors),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None if !socket_errors.is_empty() => Err(BroadcastError::Socket(socket_errors)),
+            None => Ok(()),
+        }
+    }
+
+    /// Emits a message to all sockets selected with the previous operators,
+    /// or fails with a [`BroadcastError::NoRecipients`] if no socket is selected.
+    ///
+    /// It is useful when a recipient is expected, for example to fall back to another path
+    /// when a room is empty. The emission to a [`broadcast`](Self::broadcast) selection
+    /// also fails if the sender is the only socket of the namespace.
+    ///
+    /// The selected sockets are fetched before the message is emitted, so a socket joining
+    /// or leaving in the meantime may not be taken into account.
+    /// The other errors are the same as with [`emit`](Self::emit).
+    ///
+    /// The recipients are counted with [`Adapter::fetch_sockets`](crate::adapter::Adapter::fetch_sockets),
+    /// which only returns the sockets of the current node with the [`LocalAdapter`](crate::adapter::LocalAdapter).
+    /// With such an adapter, the emission fails if no **local** socket is selected,
+    /// even if sockets of other nodes would have received the message.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, BroadcastError, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("request", |socket: SocketRef, Data::<Value>(data)| {
+    ///         match socket.to("workers").emit_or_err("request", data) {
+    ///             Err(BroadcastError::NoRecipients) => println!("no worker available"),
+    ///             res => res.unwrap(),
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn emit_or_err<T: serde::Serialize>(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<(), BroadcastError> {
+        let sockets = self
+            .ns
+            .adapter
+            .fetch_sockets(self.opts.clone())
+            .map_err(|e| BroadcastError::Adapter(e.into()))?;
+        if sockets.is_empty() {
+            return Err(BroadcastError::NoRecipients);
+        }
+        self.emit(event, data)
+    }
+
+    /// Emits a message to all sockets selected with the previous operators with an explicit list of arguments.
+    ///
+    /// Each element of `args` is sent as one argument of the event, even if it is itself an array.
+    /// See [`Socket::emit_args`] for more details. The errors are the same as with [`emit()`](#method.emit).
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::json;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.to("room1").emit_args("test", &[json!("hello"), json!([1, 2, 3])]).ok();
+    /// });
+    pub fn emit_args(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        args: &[serde_json::Value],
+    ) -> Result<(), BroadcastError> {
+        self.emit(event, args)
+    }
+
+    /// Emits a message with only binary payloads to all sockets selected with the previous operators.
+    ///
+    /// The packet doesn't have any JSON data, so the clients receive the binary payloads
+    /// as the only arguments of the event. The errors are the same as with [`emit()`](#method.emit).
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Bin(bin)| async move {
+    ///         socket.to("room1").emit_binary("test", bin).ok();
+    ///     });
+    /// });
+    pub fn emit_binary(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        bin: Vec<Vec<u8>>,
+    ) -> Result<(), BroadcastError> {
+        self.bin(bin).emit(event, [(); 0])
+    }
+
+    /// Encodes a message as it would be broadcast to the selected sockets, without sending it.
+    ///
+    /// It returns the socket.io text frame of the message, as encoded by the socket.io parser.
+    /// The engine.io transport sends it in a message packet (prefixed by `4`).
+    /// The outbound middlewares and the event prefix of the namespace are not applied.
+    ///
+    /// If binary payloads were attached with [`bin`](Self::bin), only the text frame with their placeholders is returned,
+    /// use [`encode_bin`](Self::encode_bin) to get them as well.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let frame = socket.to("room1").encode("hello", "world").unwrap();
+    ///     assert_eq!(frame, r#"2["hello","world"]"#);
+    /// });
+    pub fn encode<T: serde::Serialize>(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<String, serde_json::Error> {
+        self.encode_bin(event, data).map(|(frame, _)| frame)
+    }
+
+    /// Encodes a message as it would be broadcast to the selected sockets, without sending it.
+    ///
+    /// Like [`encode`](Self::encode) but it also returns the binary payloads,
+    /// sent after the text frame as separate binary frames.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let (frame, bin) = socket.broadcast().bin(vec![vec![1]]).encode_bin("file", ()).unwrap();
+    ///     assert_eq!(frame, r#"51-["file",null,{"_placeholder":true,"num":0}]"#);
+    ///     assert_eq!(bin, [vec![1]]);
+    /// });
+    pub fn encode_bin<T: serde::Serialize>(
+        mut self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<(String, Vec<Vec<u8>>), serde_json::Error> {
+        Ok(self.get_packet(event, data)?.encode())
+    }
+
+    /// Turns the operators into a [`ChunkedBroadcast`] that emits the message to the selected sockets
+    /// by batches of `batch_size` sockets, yielding to the runtime between each batch.
+    ///
+    /// It avoids monopolizing the runtime when broadcasting to a very large room.
+    /// Slow clients can also be skipped with [`ChunkedBroadcast::max_buffer_len`].
+    ///
+    /// The sockets are fetched with [`Adapter::fetch_sockets`], so only the sockets of the
+    /// current node are reached with the [`LocalAdapter`]: the message is not forwarded to the other nodes.
+    ///
+    /// A `batch_size` of 0 is treated as 1.
+    ///
+    /// #### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("update", |socket: SocketRef, Data::<String>(data)| async move {
+    ///         socket.to("lobby")
+    ///             .broadcast_chunked(500)
+    ///             .max_buffer_len(64)
+    ///             .emit("update", data)
+    ///             .await
+    ///             .ok();
+    ///     });
+    /// });
+    pub fn broadcast_chunked(self, batch_size: usize) -> ChunkedBroadcast<A> {
+        ChunkedBroadcast {
+            op: self,
+            batch_size: batch_size.max(1),
+            max_buffer_len: None,
+        }
+    }
+
     /// Emits a message to all sockets selected with the previous operators and
     /// waits for the acknowledgement(s).
     ///
@@ -744,12 +1490,16 @@ impl<A: Adapter> BroadcastOperators<A> {
     ///
     /// If the packet encoding failed a [`serde_json::Error`] is **immediately** returned.
     ///
+    /// If no socket is selected, nothing is sent and the returned stream is empty and ends immediately.
+    /// [`AckStream::socket_count`] can be used to detect it.
+    ///
     /// If the socket is full or if it has been closed before receiving the acknowledgement,
     /// an [`AckError::Socket`] will be yielded.
     ///
     /// If the client didn't respond before the timeout, the [`AckStream`] will yield
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `V`,
-    /// an [`AckError::Serde`] will be yielded.
+    /// an [`AckError::Serde`] will be yielded. If the socket is disconnected before
+    /// the client responds, an [`AckError::Disconnected`] will be yielded.
     ///
     /// [`timeout()`]: #method.timeout
     /// [`Stream`]: futures::stream::Stream
@@ -757,6 +1507,7 @@ impl<A: Adapter> BroadcastOperators<A> {
     /// [`AckResponse`]: crate::ack::AckResponse
     /// [`AckError::Serde`]: crate::AckError::Serde
     /// [`AckError::Timeout`]: crate::AckError::Timeout
+    /// [`AckError::Disconnected`]: crate::AckError::Disconnected
     /// [`AckError::Socket`]: crate::AckError::Socket
     /// [`AckError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
     /// [`io::get_socket()`]: crate::SocketIo#method.get_socket
@@ -800,9 +1551,94 @@ impl<A: Adapter> BroadcastOperators<A> {
         Ok(stream)
     }
 
+    /// Emits a message to all sockets selected with the previous operators and returns all their
+    /// acknowledgements in their arrival order, with the id of the socket and the time it was received at.
+    ///
+    /// It can be used when the order of the responses matters, e.g. to rank the bids of an auction.
+    /// The arrival time is taken when the stream observes the acknowledgement,
+    /// see [`AckOrderedStream`] for more details. The errors are the same as with [`emit_with_ack()`](#method.emit_with_ack).
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use futures::stream::StreamExt;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("auction", |socket: SocketRef| async move {
+    ///         let mut bids = socket
+    ///             .to("bidders")
+    ///             .emit_with_ack_ordered::<u64>("bid-request", "item-1")
+    ///             .unwrap();
+    ///         futures::pin_mut!(bids);
+    ///         while let Some((id, at, bid)) = bids.next().await {
+    ///             if let Ok(bid) = bid {
+    ///                 println!("bid of {} from {} at {:?}", bid.data, id, at);
+    ///             }
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn emit_with_ack_ordered<V>(
+        mut self,
+        event: impl Into<Cow<'static, str>>,
+        data: impl serde::Serialize,
+    ) -> Result<AckOrderedStream<V>, serde_json::Error> {
+        let packet = self.get_packet(event, data)?;
+        let stream = self
+            .ns
+            .adapter
+            .broadcast_with_ack(packet, self.opts, self.timeout);
+        Ok(AckOrderedStream::new(stream))
+    }
+
+    /// Emits a message to all sockets selected with the previous operators and calls the `callback` once
+    /// with all their acknowledgements, when every socket has responded or the timeout has elapsed.
+    ///
+    /// It can be used to handle the acknowledgements later without awaiting an [`AckStream`].
+    /// They are collected from an [`AckStream`] in a task spawned with the
+    /// [`spawner`](crate::SocketIoBuilder::spawner) of the server, with the same errors as
+    /// [`emit_with_ack()`](#method.emit_with_ack), and are given to the callback in their arrival order
+    /// with the id of their socket.
+    ///
+    /// If the packet encoding failed a [`serde_json::Error`] is **immediately** returned
+    /// and the callback is not called.
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("poll", |socket: SocketRef| {
+    ///         socket
+    ///             .broadcast()
+    ///             .timeout(Duration::from_secs(5))
+    ///             .emit_with_ack_cb::<String>("vote", "lunch?", |votes| {
+    ///                 let yes = votes.iter().filter(|(_, v)| matches!(v, Ok(v) if v.data == "yes")).count();
+    ///                 println!("{} of {} sockets voted yes", yes, votes.len());
+    ///             })
+    ///             .unwrap();
+    ///     });
+    /// });
+    pub fn emit_with_ack_cb<V: DeserializeOwned + Send + 'static>(
+        self,
+        event: impl Into<Cow<'static, str>>,
+        data: impl serde::Serialize,
+        callback: impl FnOnce(Vec<(Sid, Result<AckResponse<V>, AckError<()>>)>) + Send + 'static,
+    ) -> Result<(), serde_json::Error> {
+        let spawner = match self.ns.client.upgrade() {
+            Some(client) => client.config.spawner.clone(),
+            None => Spawner::default(),
+        };
+        let stream = self.emit_with_ack::<V>(event, data)?;
+        spawner.spawn(async move { callback(stream.collect().await) });
+        Ok(())
+    }
+
     /// Gets all sockets selected with the previous operators.
     ///
     /// It can be used to retrieve any extension data (with the `extensions` feature enabled) from the sockets or to make some sockets join other rooms.
+    /// If none of the selected rooms exist, an empty list is returned.
     ///
     /// ### Example
     /// ```
@@ -874,6 +1710,71 @@ impl<A: Adapter> BroadcastOperators<A> {
         self.ns.adapter.rooms()
     }
 
+    /// Gets the number of sockets in each room of the namespace.
+    ///
+    /// The rooms of the connected sockets named after their own id are not included.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.join(["room1", "room2"]).unwrap();
+    ///     for (room, size) in socket.broadcast().room_sizes().unwrap() {
+    ///         println!("{} sockets in {}", size, room);
+    ///     }
+    /// });
+    pub fn room_sizes(self) -> Result<HashMap<Room, usize>, A::Error> {
+        let mut sizes = self.ns.adapter.room_sizes()?;
+        for socket in self.ns.get_sockets() {
+            sizes.remove(socket.id.as_str());
+        }
+        Ok(sizes)
+    }
+
+    /// Returns true if the given room of the namespace exists and has at least one socket.
+    ///
+    /// It can be used to avoid creating a packet that would not be sent to anyone.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     if socket.broadcast().has_room("admins").unwrap() {
+    ///         socket.to("admins").emit("user connected", socket.id).ok();
+    ///     }
+    /// });
+    pub fn has_room(self, room: &str) -> Result<bool, A::Error> {
+        self.ns.adapter.has_room(room)
+    }
+
+    /// Gets the ids of all the sockets in the given room of the namespace, across all the nodes of the cluster.
+    ///
+    /// Unlike [`sockets`](Self::sockets), the remote sockets are included.
+    /// With the default in-memory adapter, only the local sockets are returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::SocketRef};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     socket.join("lobby").unwrap();
+    ///     let sids = socket.broadcast().room_sids("lobby").await.unwrap();
+    ///     socket.emit("presence", sids).ok();
+    /// });
+    pub async fn room_sids(self, room: &str) -> Result<HashSet<Sid>, A::Error> {
+        self.ns.adapter.room_sids(room).await
+    }
+
+    /// Gets the [`Adapter`] of the namespace.
+    ///
+    /// It can be used to call the methods specific to a custom adapter.
+    #[inline]
+    pub fn adapter(&self) -> &A {
+        &self.ns.adapter
+    }
+
     /// Gets a [`SocketRef`] by the specified [`Sid`].
     pub fn get_socket(&self, sid: Sid) -> Option<SocketRef<A>> {
         self.ns.get_socket(sid).map(SocketRef::from).ok()
@@ -887,12 +1788,84 @@ impl<A: Adapter> BroadcastOperators<A> {
     ) -> Result<Packet<'static>, serde_json::Error> {
         let ns = self.ns.path.clone();
         let data = serde_json::to_value(data)?;
-        let packet = if self.binary.is_empty() {
+        let mut packet = if self.binary.is_empty() {
             Packet::event(ns, event.into(), data)
         } else {
             let binary = std::mem::take(&mut self.binary);
             Packet::bin_event(ns, event.into(), data, binary)
         };
+        if let Some(key) = self.idempotency_key.take() {
+            packet.push_idempotency_key(key);
+        }
         Ok(packet)
     }
 }
+
+/// Emits a message to the sockets selected with [`BroadcastOperators`] by batches.
+/// It is created with [`BroadcastOperators::broadcast_chunked`].
+pub struct ChunkedBroadcast<A: Adapter = LocalAdapter> {
+    op: BroadcastOperators<A>,
+    batch_size: usize,
+    max_buffer_len: Option<usize>,
+}
+
+impl<A: Adapter> ChunkedBroadcast<A> {
+    /// Skips the sockets that have `len` or more packets queued and not yet flushed to their transport
+    /// (see [`Socket::send_buffer_len`]).
+    ///
+    /// Like a volatile message, the message is silently dropped for these sockets so that slow clients
+    /// do not accumulate more data in memory.
+    ///
+    /// Defaults to `None`, no socket is skipped.
+    pub fn max_buffer_len(mut self, len: usize) -> Self {
+        self.max_buffer_len = Some(len);
+        self
+    }
+
+    /// Emits the message to the selected sockets, by batches of sockets.
+    ///
+    /// The packet is encoded once and the runtime is yielded to after each batch.
+    /// Only the sockets of the current server are reached,
+    /// as they are selected with [`Adapter::fetch_sockets`].
+    ///
+    /// The errors are the same as with [`BroadcastOperators::emit`].
+    pub async fn emit<T: serde::Serialize>(
+        mut self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<(), BroadcastError> {
+        let packet = self.op.get_packet(event, data)?;
+        let ns = self.op.ns;
+        ns.buffer_missed(&self.op.opts, &packet);
+        let sockets = ns
+            .adapter
+            .fetch_sockets(self.op.opts)
+            .map_err(|e| BroadcastError::Adapter(e.into()))?;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "broadcasting packet to {} sockets by batches of {}",
+            sockets.len(),
+            self.batch_size
+        );
+        let mut errors = Vec::new();
+        let mut cache = CompressCache::default();
+        for batch in sockets.chunks(self.batch_size) {
+            for socket in batch {
+                match self.max_buffer_len {
+                    Some(max) if socket.send_buffer_len() >= max => continue,
+                    _ => (),
+                }
+                if let Err(e) = socket.send_broadcast(packet.clone(), &mut cache) {
+                    errors.push(e);
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.into())
+        }
+    }
+}
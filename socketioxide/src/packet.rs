@@ -8,8 +8,26 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use crate::errors::Error;
+use crate::extract::BinaryMeta;
 use engineioxide::sid::Sid;
 
+/// The name of the field of the argument carrying the idempotency key of a message
+pub(crate) const IDEMPOTENCY_KEY: &str = "_idempotencyKey";
+
+/// Removes the idempotency key from the arguments of a received message and returns it,
+/// if the last argument is an object with a single `_idempotencyKey` string field.
+pub(crate) fn take_idempotency_key(data: &mut Value) -> Option<String> {
+    let Value::Array(args) = data else {
+        return None;
+    };
+    let key = match args.last() {
+        Some(Value::Object(o)) if o.len() == 1 => o.get(IDEMPOTENCY_KEY)?.as_str()?.to_string(),
+        _ => return None,
+    };
+    args.pop();
+    Some(key)
+}
+
 /// The socket.io packet type.
 /// Each packet has a type and a namespace
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -59,6 +77,16 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Sends a connect packet with a payload containing the private recovery token of the session.
+    /// It is only used with v5 when the connection state recovery is enabled.
+    pub(crate) fn connect_recoverable(ns: &'a str, sid: Sid, pid: Sid) -> Self {
+        let val = json!({ "sid": sid, "pid": pid }).to_string();
+        Self {
+            inner: PacketData::Connect(Some(val)),
+            ns: Cow::Borrowed(ns),
+        }
+    }
+
     /// Create a disconnect packet for the given namespace
     pub fn disconnect(ns: &'a str) -> Self {
         Self {
@@ -66,6 +94,14 @@ impl<'a> Packet<'a> {
             ns: Cow::Borrowed(ns),
         }
     }
+
+    /// Converts the packet into an owned packet that can be stored.
+    pub(crate) fn into_owned(self) -> Packet<'static> {
+        Packet {
+            inner: self.inner.into_owned(),
+            ns: self.ns.into_owned().into(),
+        }
+    }
 }
 
 impl<'a> Packet<'a> {
@@ -77,6 +113,21 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Encodes a connect error packet for the given namespace with a custom message.
+    /// The [`PacketData::ConnectError`] packets always have the "Invalid namespace" message.
+    pub(crate) fn encode_connect_error(ns: &str, message: &str) -> String {
+        let mut packet = String::from("4");
+        if !ns.is_empty() && ns != "/" {
+            if !ns.starts_with('/') {
+                packet.push('/');
+            }
+            packet.push_str(ns);
+            packet.push(',');
+        }
+        packet.push_str(&json!({ "message": message }).to_string());
+        packet
+    }
+
     /// Create an event packet for the given namespace
     pub fn event(ns: impl Into<Cow<'a, str>>, e: impl Into<Cow<'a, str>>, data: Value) -> Self {
         Self {
@@ -109,6 +160,21 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Appends an idempotency key as the last argument of an event packet.
+    /// Other packets are left untouched.
+    pub(crate) fn push_idempotency_key(&mut self, key: String) {
+        let data = match &mut self.inner {
+            PacketData::Event(_, data, _) => data,
+            PacketData::BinaryEvent(_, bin, _) => &mut bin.data,
+            _ => return,
+        };
+        let key = json!({ IDEMPOTENCY_KEY: key });
+        match data {
+            Value::Array(v) => v.push(key),
+            data => *data = Value::Array(vec![data.take(), key]),
+        }
+    }
+
     /// Create a binary ack packet for the given namespace
     pub fn bin_ack(ns: &'a str, data: Value, bin: Vec<Vec<u8>>, ack: i64) -> Self {
         debug_assert!(!bin.is_empty());
@@ -119,6 +185,18 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Encodes the packet into the frames sent over the engine.io socket:
+    /// the text frame followed, for a binary packet, by the binary payloads sent as separate frames.
+    pub fn encode(mut self) -> (String, Vec<Vec<u8>>) {
+        let bin = match &mut self.inner {
+            PacketData::BinaryEvent(_, bin, _) | PacketData::BinaryAck(bin, _) => {
+                std::mem::take(&mut bin.bin)
+            }
+            _ => vec![],
+        };
+        (self.into(), bin)
+    }
+
     /// Get the max size the packet could have when serialized
     /// This is used to pre-allocate a buffer for the packet
     ///
@@ -204,9 +282,26 @@ pub struct BinaryPacket {
     pub bin: Vec<Vec<u8>>,
     /// The number of expected payloads (used when receiving data)
     payload_count: usize,
+    /// The number of payloads declared in the packet header (used when receiving data)
+    declared: usize,
 }
 
 impl<'a> PacketData<'a> {
+    /// Converts the packet data into an owned packet data that can be stored.
+    pub(crate) fn into_owned(self) -> PacketData<'static> {
+        match self {
+            PacketData::Event(e, data, ack) => PacketData::Event(e.into_owned().into(), data, ack),
+            PacketData::BinaryEvent(e, bin, ack) => {
+                PacketData::BinaryEvent(e.into_owned().into(), bin, ack)
+            }
+            PacketData::Connect(data) => PacketData::Connect(data),
+            PacketData::Disconnect => PacketData::Disconnect,
+            PacketData::EventAck(data, ack) => PacketData::EventAck(data, ack),
+            PacketData::ConnectError => PacketData::ConnectError,
+            PacketData::BinaryAck(bin, ack) => PacketData::BinaryAck(bin, ack),
+        }
+    }
+
     fn index(&self) -> char {
         match self {
             PacketData::Connect(_) => '0',
@@ -274,6 +369,7 @@ impl BinaryPacket {
             data,
             bin: Vec::new(),
             payload_count,
+            declared: payload_count,
         }
     }
 
@@ -294,9 +390,49 @@ impl BinaryPacket {
             data,
             bin,
             payload_count,
+            declared: payload_count,
+        }
+    }
+
+    /// Compresses with gzip the payloads of at least `min_size` bytes
+    /// and flags their placeholder with `"gzip": true`.
+    /// The payloads already flagged are left as is.
+    #[cfg(feature = "compress-binary")]
+    pub(crate) fn compress(&mut self, min_size: usize) {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let Value::Array(data) = &mut self.data else {
+            return;
+        };
+        for (i, bin) in self.bin.iter_mut().enumerate() {
+            if bin.len() < min_size {
+                continue;
+            }
+            let placeholder = data.iter_mut().filter_map(Value::as_object_mut).find(|o| {
+                o.contains_key("_placeholder")
+                    && o.get("num").and_then(Value::as_u64) == Some(i as u64)
+            });
+            let Some(placeholder) = placeholder.filter(|o| !o.contains_key("gzip")) else {
+                continue;
+            };
+            let mut encoder =
+                GzEncoder::new(Vec::with_capacity(bin.len() / 2), Compression::default());
+            // Writing to a `Vec` never fails
+            encoder.write_all(bin).unwrap();
+            *bin = encoder.finish().unwrap();
+            placeholder.insert("gzip".into(), Value::Bool(true));
         }
     }
 
+    /// Set the number of payloads declared by the packet header, if it could be parsed
+    fn with_declared(mut self, declared: Option<usize>) -> Self {
+        if let Some(declared) = declared {
+            self.declared = declared;
+        }
+        self
+    }
+
     /// Add a payload to the binary packet, when all payloads are added,
     /// the packet is complete and can be further processed
     pub fn add_payload(&mut self, payload: Vec<u8>) {
@@ -306,6 +442,14 @@ impl BinaryPacket {
     pub fn is_complete(&self) -> bool {
         self.payload_count == self.bin.len()
     }
+
+    /// Get the number of payloads declared by the packet header and the number of payloads received
+    pub fn meta(&self) -> BinaryMeta {
+        BinaryMeta {
+            declared: self.declared,
+            received: self.bin.len(),
+        }
+    }
 }
 
 impl<'a> From<Packet<'a>> for String {
@@ -438,6 +582,14 @@ fn deserialize_packet<T: DeserializeOwned>(data: &str) -> Result<Option<T>, serd
     Ok(packet)
 }
 
+/// Returns the number of attachments declared in the header of a raw binary packet, e.g. `3` for `53-["event",...]`,
+/// without parsing the rest of the packet.
+pub(crate) fn declared_attachments(value: &str) -> Option<usize> {
+    let header = value.strip_prefix(['5', '6'])?;
+    let (count, _) = header.split_once('-')?;
+    count.parse().ok()
+}
+
 /// Deserialize a packet from a string
 /// The string should be in the format of:
 /// ```text
@@ -448,6 +600,14 @@ impl<'a> TryFrom<String> for Packet<'a> {
     type Error = Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
+        Packet::decode(value).map(|(packet, _)| packet)
+    }
+}
+
+impl<'a> Packet<'a> {
+    /// Deserializes a packet from a string and returns it with the length of its JSON payload,
+    /// without the packet header (the packet type, the attachment count, the namespace and the ack id).
+    pub(crate) fn decode(value: String) -> Result<(Self, usize), Error> {
         // It is possible to parse the packet from a byte slice because separators are only ASCII
         let chars = value.as_bytes();
         let mut i = 1;
@@ -457,10 +617,12 @@ impl<'a> TryFrom<String> for Packet<'a> {
             .ok_or(Error::InvalidPacketType)?;
 
         // Move the cursor to skip the payload count if it is a binary packet
+        let mut declared = None;
         if index == b'5' || index == b'6' {
             while chars.get(i) != Some(&b'-') {
                 i += 1;
             }
+            declared = value[1..i].parse().ok();
             i += 1;
         }
 
@@ -509,19 +671,20 @@ impl<'a> TryFrom<String> for Packet<'a> {
             }
             b'5' => {
                 let (event, payload) = deserialize_event_packet(data)?;
-                PacketData::BinaryEvent(event.into(), BinaryPacket::incoming(payload), ack)
+                let packet = BinaryPacket::incoming(payload).with_declared(declared);
+                PacketData::BinaryEvent(event.into(), packet, ack)
             }
             b'6' => {
                 let packet = deserialize_packet(data)?.ok_or(Error::InvalidPacketType)?;
                 PacketData::BinaryAck(
-                    BinaryPacket::incoming(packet),
+                    BinaryPacket::incoming(packet).with_declared(declared),
                     ack.ok_or(Error::InvalidPacketType)?,
                 )
             }
             _ => return Err(Error::InvalidPacketType),
         };
 
-        Ok(Self { inner, ns })
+        Ok((Self { inner, ns }, data.len()))
     }
 }
 
@@ -537,6 +700,65 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn packet_declared_attachments() {
+        assert_eq!(declared_attachments(r#"52-["event",{}]"#), Some(2));
+        assert_eq!(declared_attachments(r#"61-/admin,1[{}]"#), Some(1));
+        assert_eq!(declared_attachments("54294967295-"), Some(4294967295));
+        assert_eq!(declared_attachments(r#"2["event"]"#), None);
+        assert_eq!(declared_attachments("5abc"), None);
+    }
+
+    #[test]
+    fn packet_encode() {
+        let packet = Packet::event("/", "event", json!({ "data": 1 }));
+        assert_eq!(
+            packet.encode(),
+            (r#"2["event",{"data":1}]"#.to_string(), vec![])
+        );
+
+        let packet = Packet::bin_event("/admin", "event", json!("data"), vec![vec![1, 2]]);
+        assert_eq!(
+            packet.encode(),
+            (
+                r#"51-/admin,["event","data",{"_placeholder":true,"num":0}]"#.to_string(),
+                vec![vec![1, 2]]
+            )
+        );
+    }
+
+    #[cfg(feature = "compress-binary")]
+    #[test]
+    fn compress_binary_payloads() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let large = vec![1; 1024];
+        let mut packet = BinaryPacket::outgoing(json!("data"), vec![large.clone(), vec![2; 8]]);
+        packet.compress(64);
+        assert_eq!(
+            packet.data,
+            json!([
+                "data",
+                { "_placeholder": true, "num": 0, "gzip": true },
+                { "_placeholder": true, "num": 1 }
+            ])
+        );
+        assert!(packet.bin[0].len() < large.len());
+        assert_eq!(packet.bin[1], vec![2; 8]);
+
+        // The payloads already compressed are not compressed again
+        let compressed = packet.bin[0].clone();
+        packet.compress(0);
+        assert_eq!(packet.bin[0], compressed);
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&packet.bin[0][..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, large);
+    }
+
     #[test]
     fn packet_decode_connect() {
         let sid = Sid::new();
@@ -630,6 +852,15 @@ mod test {
         assert_eq!(packet, comparison_packet);
     }
 
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn packet_event_arbitrary_precision() {
+        let payload = r#"2["event",123456789012345678901234567890,0.10000000000000000000001]"#;
+        let packet = Packet::try_from(payload.to_string()).unwrap();
+        let encoded: String = packet.into();
+        assert_eq!(encoded, payload);
+    }
+
     #[test]
     fn packet_encode_event() {
         let payload = format!("2{}", json!(["event", { "data": "value™" }]));
@@ -705,6 +936,12 @@ mod test {
         let payload = format!("4/admin™,{}", json!({ "message": "Invalid namespace" }));
         let packet: String = Packet::invalid_namespace("/admin™").try_into().unwrap();
         assert_eq!(packet, payload);
+
+        let payload = format!("4/admin™,{}", json!({ "message": "Namespace is full" }));
+        let packet = Packet::encode_connect_error("/admin™", "Namespace is full");
+        assert_eq!(packet, payload);
+        let packet = Packet::encode_connect_error("/", "Invalid namespace");
+        assert_eq!(packet, String::from(Packet::invalid_namespace("/")));
     }
 
     // BinaryEvent(String, BinaryPacket, Option<i64>),
@@ -765,6 +1002,7 @@ mod test {
                     bin: vec![vec![1]],
                     data: json!([{"data": "value™"}]),
                     payload_count: 1,
+                    declared: 1,
                 },
                 ack,
             ),
@@ -832,6 +1070,50 @@ mod test {
         assert_eq!(packet, payload);
     }
 
+    #[test]
+    fn packet_decode_binary_meta() {
+        let json =
+            json!(["event", { "_placeholder": true, "num": 0}, { "_placeholder": true, "num": 1}]);
+        let payload = format!("53-{}", json);
+        let mut packet = Packet::try_from(payload).unwrap();
+        match packet.inner {
+            PacketData::BinaryEvent(_, ref mut bin, _) => {
+                assert_eq!(
+                    bin.meta(),
+                    BinaryMeta {
+                        declared: 3,
+                        received: 0
+                    }
+                );
+                bin.add_payload(vec![1]);
+                bin.add_payload(vec![2]);
+                assert!(bin.is_complete());
+                assert_eq!(
+                    bin.meta(),
+                    BinaryMeta {
+                        declared: 3,
+                        received: 2
+                    }
+                );
+            }
+            _ => panic!("expected a binary event"),
+        }
+    }
+
+    #[test]
+    fn packet_decode_payload_len() {
+        let payload = r#"2/admin,12["event","data"]"#.to_string();
+        let (_, len) = Packet::decode(payload).unwrap();
+        assert_eq!(len, r#"["event","data"]"#.len());
+
+        let payload = r#"51-["event",{"_placeholder":true,"num":0}]"#.to_string();
+        let (_, len) = Packet::decode(payload).unwrap();
+        assert_eq!(len, r#"["event",{"_placeholder":true,"num":0}]"#.len());
+
+        let (_, len) = Packet::decode("1".to_string()).unwrap();
+        assert_eq!(len, 0);
+    }
+
     #[test]
     fn packet_decode_binary_ack() {
         let json = json!([{ "data": "value™" }, { "_placeholder": true, "num": 0}]);
@@ -841,6 +1123,7 @@ mod test {
                     bin: vec![vec![1]],
                     data: json!([{"data": "value™"}]),
                     payload_count: 1,
+                    declared: 1,
                 },
                 ack,
             ),
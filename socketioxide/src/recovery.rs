@@ -0,0 +1,328 @@
+//! Connection state recovery.
+//!
+//! When it is enabled with [`SocketIoBuilder::connection_recovery`](crate::SocketIoBuilder::connection_recovery),
+//! every socket connected with the v5 protocol is given a private recovery token (the `pid`) in its connect packet,
+//! and each non-ack event emitted to it is tagged with an offset appended as the last argument.
+//! The client keeps track of the last offset it received and sends it back with its `pid` when reconnecting.
+//!
+//! If the socket reconnects within the [`max_disconnection_duration`](ConnectionRecoveryConfig::max_disconnection_duration),
+//! it rejoins its previous rooms and receives all the buffered packets that it has missed.
+//!
+//! #### Memory bound and eviction policy
+//! * Each socket keeps at most [`max_buffered_packets`](ConnectionRecoveryConfig::max_buffered_packets) packets.
+//!   When the buffer is full, the oldest packet is evicted.
+//! * The session of a disconnected socket is kept for the `max_disconnection_duration`.
+//!   Expired sessions are evicted lazily, each time a session is saved or recovered.
+//! * Sessions are only saved when the disconnection is not explicit
+//!   (i.e. not a [`ClientNSDisconnect`], [`ServerNSDisconnect`], [`ClosingServer`] or [`IdleTimeout`]).
+//!
+//! The memory used is therefore bounded by `max_buffered_packets` packets for each connected socket and for
+//! each socket disconnected less than `max_disconnection_duration` ago.
+//!
+//! #### Session continuity
+//! The recovered socket is a new [`Socket`](crate::socket::Socket) with a new id,
+//! but it inherits the session of the previous one:
+//! * its rooms are rejoined and the missed packets are sent again;
+//! * its [`extensions`](crate::socket::Socket::extensions) are moved to the new socket
+//!   (if the `extensions` feature is enabled);
+//! * [`Socket::previous_id`](crate::socket::Socket::previous_id) returns the id of the previous socket;
+//! * a [`ConnectionEvent::Reconnected`](crate::socket::ConnectionEvent::Reconnected) event
+//!   is sent instead of a [`ConnectionEvent::Connected`](crate::socket::ConnectionEvent::Connected) one.
+//!
+//! [`ClientNSDisconnect`]: crate::socket::DisconnectReason::ClientNSDisconnect
+//! [`ServerNSDisconnect`]: crate::socket::DisconnectReason::ServerNSDisconnect
+//! [`ClosingServer`]: crate::socket::DisconnectReason::ClosingServer
+//! [`IdleTimeout`]: crate::socket::DisconnectReason::IdleTimeout
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use engineioxide::sid::Sid;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[cfg(feature = "extensions")]
+use crate::extensions::Extensions;
+use crate::{
+    adapter::{BroadcastOptions, Room},
+    packet::{Packet, PacketData},
+    socket::DisconnectReason,
+};
+
+/// Configuration of the connection state recovery.
+#[derive(Debug, Clone)]
+pub struct ConnectionRecoveryConfig {
+    /// The amount of time a disconnected socket session is kept to be recovered.
+    ///
+    /// Defaults to 2 minutes.
+    pub max_disconnection_duration: Duration,
+
+    /// The maximum number of packets buffered for each socket.
+    /// When the buffer is full the oldest packet is evicted.
+    ///
+    /// Defaults to 128 packets.
+    pub max_buffered_packets: usize,
+}
+
+impl Default for ConnectionRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            max_disconnection_duration: Duration::from_secs(120),
+            max_buffered_packets: 128,
+        }
+    }
+}
+
+/// The recovery data sent by the client in its connect packet.
+#[derive(Debug, Deserialize)]
+struct RecoveryAuth {
+    pid: Sid,
+    offset: Option<String>,
+}
+
+impl RecoveryAuth {
+    fn parse(auth: &str) -> Option<(Sid, Option<u64>)> {
+        let auth: RecoveryAuth = serde_json::from_str(auth).ok()?;
+        let offset = match auth.offset {
+            Some(offset) => Some(offset.parse().ok()?),
+            None => None,
+        };
+        Some((auth.pid, offset))
+    }
+}
+
+/// A bounded buffer of the last packets emitted to a socket, tagged with their offset.
+#[derive(Debug)]
+pub(crate) struct RecoveryBuffer {
+    /// The private recovery token of the socket session
+    pub pid: Sid,
+    offset: u64,
+    max_packets: usize,
+    packets: VecDeque<(u64, Packet<'static>)>,
+}
+
+impl RecoveryBuffer {
+    pub fn new(max_packets: usize) -> Self {
+        Self {
+            pid: Sid::new(),
+            offset: 0,
+            max_packets,
+            packets: VecDeque::with_capacity(max_packets.min(16)),
+        }
+    }
+
+    /// Tags the packet with the next offset and stores a copy of it.
+    ///
+    /// Only events without acknowledgement are tagged and buffered.
+    pub fn push(&mut self, packet: &mut Packet<'_>) {
+        let data = match &mut packet.inner {
+            PacketData::Event(_, data, None) => data,
+            PacketData::BinaryEvent(_, bin, None) => &mut bin.data,
+            _ => return,
+        };
+        self.offset += 1;
+        let offset = Value::String(self.offset.to_string());
+        match data {
+            Value::Array(v) => v.push(offset),
+            data => *data = Value::Array(vec![data.take(), offset]),
+        }
+
+        if self.max_packets == 0 {
+            return;
+        }
+        if self.packets.len() == self.max_packets {
+            self.packets.pop_front();
+        }
+        self.packets
+            .push_back((self.offset, packet.clone().into_owned()));
+    }
+
+    /// Returns all the buffered packets emitted after the given offset.
+    pub fn missed_packets(&self, offset: Option<u64>) -> Vec<Packet<'static>> {
+        let offset = offset.unwrap_or(0);
+        self.packets
+            .iter()
+            .filter(|(o, _)| *o > offset)
+            .map(|(_, p)| p.clone())
+            .collect()
+    }
+}
+
+/// The state of a disconnected socket that can be recovered.
+#[derive(Debug)]
+pub(crate) struct Session {
+    pub sid: Sid,
+    pub rooms: Vec<Room>,
+    pub buffer: RecoveryBuffer,
+    #[cfg(feature = "extensions")]
+    pub extensions: Extensions,
+    disconnected_at: Instant,
+}
+
+impl Session {
+    pub fn new(sid: Sid, rooms: Vec<Room>, buffer: RecoveryBuffer) -> Self {
+        Self {
+            sid,
+            rooms,
+            buffer,
+            #[cfg(feature = "extensions")]
+            extensions: Extensions::new(),
+            disconnected_at: Instant::now(),
+        }
+    }
+}
+
+/// A recovered session, with the packets missed by the client.
+#[derive(Debug)]
+pub(crate) struct RecoveredSession {
+    /// The id of the previous socket
+    pub sid: Sid,
+    pub rooms: Vec<Room>,
+    pub buffer: RecoveryBuffer,
+    #[cfg(feature = "extensions")]
+    pub extensions: Extensions,
+    pub missed_packets: Vec<Packet<'static>>,
+}
+
+/// Stores the sessions of the disconnected sockets of a namespace.
+#[derive(Debug, Default)]
+pub(crate) struct RecoveryStore {
+    sessions: Mutex<HashMap<Sid, Session>>,
+}
+
+impl RecoveryStore {
+    /// Returns true if a session should be saved when a socket is disconnected for this reason.
+    pub fn is_recoverable(reason: DisconnectReason) -> bool {
+        !matches!(
+            reason,
+            DisconnectReason::ClientNSDisconnect
+                | DisconnectReason::ServerNSDisconnect
+                | DisconnectReason::ClosingServer
+                | DisconnectReason::IdleTimeout
+        )
+    }
+
+    /// Saves the session of a disconnected socket.
+    pub fn save(&self, session: Session, ttl: Duration) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.disconnected_at.elapsed() < ttl);
+        sessions.insert(session.buffer.pid, session);
+    }
+
+    /// Tries to recover a session from the auth payload sent by the client.
+    /// The session is removed from the store if it is found.
+    pub fn recover(&self, auth: &str, ttl: Duration) -> Option<RecoveredSession> {
+        let (pid, offset) = RecoveryAuth::parse(auth)?;
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.disconnected_at.elapsed() < ttl);
+        let session = sessions.remove(&pid)?;
+        Some(RecoveredSession {
+            missed_packets: session.buffer.missed_packets(offset),
+            sid: session.sid,
+            rooms: session.rooms,
+            buffer: session.buffer,
+            #[cfg(feature = "extensions")]
+            extensions: session.extensions,
+        })
+    }
+
+    /// Buffers a broadcasted packet for all the disconnected sessions targeted by the [`BroadcastOptions`].
+    ///
+    /// The packet is transformed once with `prepare`, only if a session is targeted.
+    pub fn buffer(
+        &self,
+        opts: &BroadcastOptions,
+        packet: &Packet<'_>,
+        prepare: impl Fn(&mut Packet<'_>),
+    ) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut prepared = None;
+        for session in sessions.values_mut() {
+            if opts.targets(session.sid, &session.rooms) {
+                let packet = prepared.get_or_insert_with(|| {
+                    let mut packet = packet.clone();
+                    prepare(&mut packet);
+                    packet
+                });
+                session.buffer.push(&mut packet.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adapter::BroadcastFlags;
+
+    fn event(data: Value) -> Packet<'static> {
+        Packet::event("/", "test", data)
+    }
+
+    #[test]
+    fn buffer_tags_and_evicts_packets() {
+        let mut buffer = RecoveryBuffer::new(2);
+        let mut packet = event(Value::Array(vec![1.into()]));
+        buffer.push(&mut packet);
+        assert_eq!(
+            packet.inner,
+            PacketData::Event("test".into(), serde_json::json!([1, "1"]), None)
+        );
+
+        let mut packet = event(Value::String("foo".into()));
+        buffer.push(&mut packet);
+        assert_eq!(
+            packet.inner,
+            PacketData::Event("test".into(), serde_json::json!(["foo", "2"]), None)
+        );
+
+        let mut packet = event(Value::Null);
+        buffer.push(&mut packet);
+
+        let missed = buffer.missed_packets(None);
+        assert_eq!(missed.len(), 2);
+        assert_eq!(buffer.missed_packets(Some(2)).len(), 1);
+        assert_eq!(buffer.missed_packets(Some(3)).len(), 0);
+
+        // Events with acknowledgement are not buffered
+        let mut packet = event(Value::Null);
+        packet.inner.set_ack_id(1);
+        buffer.push(&mut packet);
+        assert_eq!(buffer.missed_packets(Some(3)).len(), 0);
+    }
+
+    #[test]
+    fn store_recover_session() {
+        let store = RecoveryStore::default();
+        let ttl = Duration::from_secs(1);
+        let sid = Sid::new();
+        let mut buffer = RecoveryBuffer::new(10);
+        buffer.push(&mut event(Value::Null));
+        let pid = buffer.pid;
+        store.save(Session::new(sid, vec!["room1".into()], buffer), ttl);
+
+        let mut opts = BroadcastOptions::default();
+        opts.flags.insert(BroadcastFlags::Broadcast);
+        store.buffer(&opts, &event(Value::Null), |_| ());
+        opts.except.insert("room1".into());
+        store.buffer(&opts, &event(Value::Null), |_| ());
+
+        assert!(store.recover(r#"{"pid":"invalid"}"#, ttl).is_none());
+        let auth = format!(r#"{{"pid":"{}","offset":"1"}}"#, pid);
+        let session = store.recover(&auth, ttl).unwrap();
+        assert_eq!(session.rooms, vec!["room1"]);
+        assert_eq!(session.missed_packets.len(), 1);
+        assert!(store.recover(&auth, ttl).is_none());
+    }
+
+    #[test]
+    fn store_expire_session() {
+        let store = RecoveryStore::default();
+        let buffer = RecoveryBuffer::new(10);
+        let auth = format!(r#"{{"pid":"{}"}}"#, buffer.pid);
+        store.save(Session::new(Sid::new(), vec![], buffer), Duration::ZERO);
+        assert!(store.recover(&auth, Duration::ZERO).is_none());
+    }
+}
@@ -122,7 +122,7 @@ impl<A: Adapter, S: Clone> SocketIoService<S, A> {
         config: Arc<SocketIoConfig>,
     ) -> (Self, Arc<Client<A>>) {
         let engine_config = config.engine_config.clone();
-        let client = Arc::new(Client::new(config));
+        let client = Client::new(config);
         let svc = EngineIoService::with_config_inner(inner, client.clone(), engine_config);
         (Self { engine_svc: svc }, client)
     }
@@ -2,36 +2,47 @@
 //! The socket struct itself should not be used directly, but through a [`SocketRef`](crate::extract::SocketRef).
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::Debug,
+    pin::Pin,
     sync::Mutex,
     sync::{
-        atomic::{AtomicI64, Ordering},
+        atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering},
         Arc, RwLock,
     },
+    task::{Context, Poll},
     time::Duration,
 };
 
 use engineioxide::socket::{DisconnectReason as EIoDisconnectReason, Permit, PermitIterator};
+use futures::Stream;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
-use tokio::sync::oneshot::{self, Receiver};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot::{self, Receiver},
+    watch,
+};
+use tokio::time::Instant;
 
 #[cfg(feature = "extensions")]
 use crate::extensions::Extensions;
 
 use crate::{
-    ack::{AckInnerStream, AckResponse, AckResult, AckStream},
-    adapter::{Adapter, LocalAdapter, Room},
+    ack::{AckGuard, AckInnerStream, AckMap, AckResponse, AckResult, AckStream},
+    adapter::{Adapter, BroadcastOptions, LocalAdapter, Room, RoomPattern},
     errors::{DisconnectError, Error, SendError},
+    extract::{BinaryMeta, SocketRef},
+    handler::message::MessageMeta,
     handler::{
-        BoxedDisconnectHandler, BoxedMessageHandler, DisconnectHandler, MakeErasedHandler,
+        BoxedDisconnectHandler, BoxedMessageHandler, DisconnectHandler, IntoAck, MakeErasedHandler,
         MessageHandler,
     },
     ns::Namespace,
     operators::{BroadcastOperators, ConfOperators, RoomParam},
     packet::{BinaryPacket, Packet, PacketData},
-    AckError, SocketIoConfig,
+    recovery::{RecoveryBuffer, RecoveryStore, Session},
+    AckError, OutboundPolicy, ProtocolVersion, SocketIo, SocketIoConfig,
 };
 use crate::{
     client::SocketData,
@@ -68,6 +79,9 @@ pub enum DisconnectReason {
 
     /// The server is being closed
     ClosingServer,
+
+    /// The socket did not send any event during the [`idle_timeout`](crate::SocketIoConfig::idle_timeout)
+    IdleTimeout,
 }
 
 impl std::fmt::Display for DisconnectReason {
@@ -82,11 +96,31 @@ impl std::fmt::Display for DisconnectReason {
             ClientNSDisconnect => "client has manually disconnected the socket from the namespace",
             ServerNSDisconnect => "socket was forcefully disconnected from the namespace",
             ClosingServer => "server is being closed",
+            IdleTimeout => "socket did not send any event in time",
         };
         f.write_str(str)
     }
 }
 
+impl DisconnectReason {
+    /// The websocket close code and reason sent to the client
+    /// when the server disconnects the last namespace of a connection
+    fn close_code(&self) -> (u16, &'static str) {
+        use DisconnectReason::*;
+        match self {
+            TransportClose => (1000, "transport close"),
+            HeartbeatTimeout => (1000, "heartbeat timeout"),
+            ClientNSDisconnect => (1000, "client namespace disconnect"),
+            ServerNSDisconnect => (1000, "server namespace disconnect"),
+            ClosingServer => (1001, "server closing"),
+            PacketParsingError => (1002, "packet parsing error"),
+            MultipleHttpPollingError => (1008, "multiple http polling error"),
+            IdleTimeout => (1008, "idle timeout"),
+            TransportError => (1011, "transport error"),
+        }
+    }
+}
+
 impl From<EIoDisconnectReason> for DisconnectReason {
     fn from(reason: EIoDisconnectReason) -> Self {
         use DisconnectReason::*;
@@ -101,35 +135,136 @@ impl From<EIoDisconnectReason> for DisconnectReason {
     }
 }
 
+/// The behavior of a [paused](Socket::pause) socket when its buffer of inbound events is full.
+/// It is also applied to the events received while the connect handler is running,
+/// see [`SocketIoBuilder::connect_buffer`](crate::SocketIoBuilder::connect_buffer).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// The new events are dropped, without acknowledging them
+    Drop,
+    /// The socket is disconnected with a [`DisconnectReason::ServerNSDisconnect`]
+    Disconnect,
+}
+
+/// The inbound events buffered while a socket is paused
+#[derive(Debug)]
+struct PausedEvents {
+    max_events: usize,
+    overflow: OverflowPolicy,
+    events: Vec<(PacketData<'static>, usize)>,
+}
+
+/// The binary attachments of a broadcasted packet compressed for a first socket,
+/// so that they are not compressed again for the other sockets.
+#[derive(Debug, Default)]
+pub(crate) struct CompressCache {
+    /// The uncompressed binary packet and its compressed version
+    #[cfg(feature = "compress-binary")]
+    packet: Option<(BinaryPacket, BinaryPacket)>,
+}
+
+/// A connection event of a namespace, yielded by a [`ConnectionEvents`] stream.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ConnectionEvent {
+    /// A socket connected to the namespace
+    Connected(Sid),
+    /// A socket connected to the namespace and recovered the session of a previous socket.
+    /// The first [`Sid`] is the id of the new socket and the second one is the id of the previous socket.
+    Reconnected(Sid, Sid),
+    /// A socket disconnected from the namespace
+    Disconnected(Sid, DisconnectReason),
+}
+
+/// A [`Stream`] of the [`ConnectionEvent`]s of a namespace.
+///
+/// It is created with [`SocketIo::connection_events`](crate::SocketIo::connection_events).
+/// The stream is backed by a bounded buffer of [`CONNECTION_EVENTS_BUFFER_SIZE`] events.
+/// If the consumer is too slow and the buffer is full, new events are dropped
+/// so that the connection path is never blocked.
+#[derive(Debug)]
+pub struct ConnectionEvents(mpsc::Receiver<ConnectionEvent>);
+
+/// The number of [`ConnectionEvent`]s buffered for each [`ConnectionEvents`] stream.
+pub const CONNECTION_EVENTS_BUFFER_SIZE: usize = 128;
+
+/// The interval at which the outbound queue is checked by [`Socket::drain_and_close`].
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+impl ConnectionEvents {
+    pub(crate) fn new() -> (mpsc::Sender<ConnectionEvent>, Self) {
+        let (tx, rx) = mpsc::channel(CONNECTION_EVENTS_BUFFER_SIZE);
+        (tx, Self(rx))
+    }
+}
+
+impl Stream for ConnectionEvents {
+    type Item = ConnectionEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
 pub(crate) trait PermitIteratorExt<'a>:
     ExactSizeIterator<Item = Permit<'a>> + Sized
 {
-    fn emit(mut self, mut packet: Packet<'_>) {
+    fn emit(mut self, packet: Packet<'_>) {
         debug_assert!(self.len() > 0, "No permits available to send the message");
 
-        let bin_payloads = match packet.inner {
-            PacketData::BinaryEvent(_, ref mut bin, _) | PacketData::BinaryAck(ref mut bin, _) => {
-                Some(std::mem::take(&mut bin.bin))
-            }
-            _ => None,
-        };
-
-        let msg = packet.into();
+        let (msg, bin_payloads) = packet.encode();
         self.next().unwrap().emit(msg);
 
-        if let Some(bin_payloads) = bin_payloads {
-            debug_assert!(
-                self.len() >= bin_payloads.len(),
-                "Not enough permits available to send the message with the binary payload"
-            );
-            for bin in bin_payloads {
-                self.next().unwrap().emit_binary(bin);
-            }
+        debug_assert!(
+            self.len() >= bin_payloads.len(),
+            "Not enough permits available to send the message with the binary payload"
+        );
+        for bin in bin_payloads {
+            self.next().unwrap().emit_binary(bin);
         }
     }
 }
 impl<'a> PermitIteratorExt<'a> for PermitIterator<'a> {}
 
+/// The permits returned by [`Socket::reserve`] to send a packet to the client.
+pub(crate) enum Permits<'a, A: Adapter> {
+    Reserved(PermitIterator<'a>),
+    /// The buffer of the connection is full and the packet is queued by the socket,
+    /// with the [`OutboundPolicy::DropOldest`] policy
+    Queued(&'a Socket<A>),
+    /// The buffer of the connection is full and the packet is dropped, according to the [`OutboundPolicy`]
+    Dropped,
+}
+
+impl<A: Adapter> Permits<'_, A> {
+    pub fn emit(self, packet: Packet<'_>) {
+        match self {
+            Permits::Reserved(permits) => permits.emit(packet),
+            Permits::Queued(socket) => socket.enqueue(packet.into_owned()),
+            Permits::Dropped => {}
+        }
+    }
+}
+
+type UnhandledHandler = Box<dyn Fn(&str, &Value) + Send + Sync>;
+type AckSentHandler = Box<dyn Fn(i64, &Value, &[Vec<u8>]) + Send + Sync>;
+type StrayAckHandler = Box<dyn Fn(i64, &Value, &[Vec<u8>]) + Send + Sync>;
+type ErrorHandler = Box<dyn Fn(i64, &SendError<()>) + Send + Sync>;
+type UpgradeHandler<A> = Box<dyn Fn(SocketRef<A>) + Send + Sync>;
+
+/// An acknowledgement owed to the client by a running handler, held by its [`AckSender`](crate::extract::AckSender)
+/// or until the result of an async handler is sent.
+/// A closing socket waits for the owed acknowledgements to be sent or dropped before closing its connection.
+#[derive(Debug)]
+pub(crate) struct OwedAck<A: Adapter>(Arc<Socket<A>>);
+
+impl<A: Adapter> Drop for OwedAck<A> {
+    fn drop(&mut self) {
+        self.0.owed_acks.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 /// A Socket represents a client connected to a namespace.
 /// It is used to send and receive messages from the client, join and leave rooms, etc.
 /// The socket struct itself should not be used directly, but through a [`SocketRef`](crate::extract::SocketRef).
@@ -137,9 +272,49 @@ pub struct Socket<A: Adapter = LocalAdapter> {
     pub(crate) config: Arc<SocketIoConfig>,
     pub(crate) ns: Arc<Namespace<A>>,
     message_handlers: RwLock<HashMap<Cow<'static, str>, BoxedMessageHandler<A>>>,
+    /// The maximum payload size of the events registered with [`Socket::on_with_max_payload`]
+    payload_limits: RwLock<HashMap<Cow<'static, str>, usize>>,
     disconnect_handler: Mutex<Option<BoxedDisconnectHandler<A>>>,
-    ack_message: Mutex<HashMap<i64, oneshot::Sender<AckResult<Value>>>>,
+    /// Called with the events that have no registered handler
+    unhandled_handler: RwLock<Option<UnhandledHandler>>,
+    ack_sent_handler: RwLock<Option<AckSentHandler>>,
+    /// Called with the acknowledgements received for an unknown ack id
+    stray_ack_handler: RwLock<Option<StrayAckHandler>>,
+    /// Called with the errors of the acknowledgements returned by the handlers
+    error_handler: RwLock<Option<ErrorHandler>>,
+    /// Called when the underlying connection is upgraded to websocket
+    upgrade_handler: RwLock<Option<UpgradeHandler<A>>>,
+    ack_message: Arc<AckMap>,
     ack_counter: AtomicI64,
+    /// The buffer of emitted packets, only set if the connection state recovery is enabled
+    recovery: Mutex<Option<RecoveryBuffer>>,
+    /// The id of the previous socket if the session was recovered
+    previous_id: Option<Sid>,
+    /// The correlation id recorded in the tracing spans of the socket events
+    trace_id: RwLock<Option<String>>,
+    connected: AtomicBool,
+    /// Set when the socket is being drained, inbound events are then ignored
+    closing: AtomicBool,
+    /// The number of acknowledgements owed to the client by running handlers
+    owed_acks: AtomicUsize,
+    /// The number of packets dropped because the buffer of the connection was full
+    dropped: AtomicU64,
+    /// The packets waiting for room in the buffer of the connection, with the [`OutboundPolicy::DropOldest`] policy.
+    /// It is set while they are sent by a drain task.
+    overflow: Mutex<Option<VecDeque<Packet<'static>>>>,
+    /// The packets received while the connect handler is running
+    pending: Mutex<Option<Vec<(PacketData<'static>, usize)>>>,
+    /// The events received while the socket is paused
+    paused: Mutex<Option<PausedEvents>>,
+    /// Set when the connect handler has completed
+    ready: watch::Sender<bool>,
+    /// Set with the disconnect reason when the socket is closed
+    disconnected: watch::Sender<Option<DisconnectReason>>,
+    /// The last time the socket received an event, used for the idle timeout
+    last_activity: Mutex<Instant>,
+    /// Set if the client supports the gzip compression of the binary attachments
+    #[cfg(feature = "compress-binary")]
+    compress_binary: bool,
     /// The socket id
     pub id: Sid,
 
@@ -161,12 +336,42 @@ impl<A: Adapter> Socket<A> {
         esocket: Arc<engineioxide::Socket<SocketData>>,
         config: Arc<SocketIoConfig>,
     ) -> Self {
+        let recovery = match &config.connection_recovery {
+            Some(recovery) if ProtocolVersion::from(esocket.protocol) == ProtocolVersion::V5 => {
+                Some(RecoveryBuffer::new(recovery.max_buffered_packets))
+            }
+            _ => None,
+        };
         Self {
             ns,
             message_handlers: RwLock::new(HashMap::new()),
+            payload_limits: RwLock::new(HashMap::new()),
             disconnect_handler: Mutex::new(None),
-            ack_message: Mutex::new(HashMap::new()),
+            unhandled_handler: RwLock::new(None),
+            ack_sent_handler: RwLock::new(None),
+            stray_ack_handler: RwLock::new(None),
+            error_handler: RwLock::new(None),
+            upgrade_handler: RwLock::new(None),
+            ack_message: Arc::new(Mutex::new(HashMap::new())),
             ack_counter: AtomicI64::new(0),
+            recovery: Mutex::new(recovery),
+            previous_id: None,
+            trace_id: RwLock::new(None),
+            connected: AtomicBool::new(true),
+            closing: AtomicBool::new(false),
+            owed_acks: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+            overflow: Mutex::new(None),
+            pending: Mutex::new(Some(Vec::new())),
+            paused: Mutex::new(None),
+            ready: watch::channel(false).0,
+            disconnected: watch::channel(None).0,
+            last_activity: Mutex::new(Instant::now()),
+            #[cfg(feature = "compress-binary")]
+            compress_binary: config.compress_binary.is_some()
+                && esocket.req_parts.uri.query().map_or(false, |q| {
+                    q.split('&').any(|p| p == "binaryCompression=gzip")
+                }),
             id: sid,
             #[cfg(feature = "extensions")]
             extensions: Extensions::new(),
@@ -232,10 +437,87 @@ impl<A: Adapter> Socket<A> {
         H: MessageHandler<A, T>,
         T: Send + Sync + 'static,
     {
+        let event = event.into();
+        self.payload_limits.write().unwrap().remove(&event);
         self.message_handlers
             .write()
             .unwrap()
-            .insert(event.into(), MakeErasedHandler::new_message_boxed(handler));
+            .insert(event, MakeErasedHandler::new_message_boxed(handler));
+    }
+
+    /// Registers the same [`MessageHandler`] for several events, like calling [`Socket::on`] for each of them.
+    ///
+    /// It can be used to keep handling the old name of a renamed event during a protocol migration.
+    /// The handler is shared between the events and is not cloned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // "message" is the legacy name of the "chat" event
+    ///     socket.on_events(["chat", "message"], |Data::<String>(msg)| {
+    ///         println!("chat message: {msg}");
+    ///     });
+    /// });
+    /// ```
+    pub fn on_events<H, T>(
+        &self,
+        events: impl IntoIterator<Item = impl Into<Cow<'static, str>>>,
+        handler: H,
+    ) where
+        H: MessageHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        let handler = MakeErasedHandler::new_message_boxed(handler);
+        let mut handlers = self.message_handlers.write().unwrap();
+        let mut payload_limits = self.payload_limits.write().unwrap();
+        for event in events {
+            let event = event.into();
+            payload_limits.remove(&event);
+            handlers.insert(event, handler.clone());
+        }
+    }
+
+    /// Registers a [`MessageHandler`] for the given event, like [`Socket::on`],
+    /// but with a maximum size in bytes for the payload of the event.
+    ///
+    /// The size of a payload is the length of the received JSON payload (including the event name,
+    /// without the packet header) plus the length of its binary attachments.
+    /// It is checked before calling the handler and thus before deserializing the data to the handler arguments.
+    /// Oversized events are not handled and, if the client requested an acknowledgement,
+    /// they are acknowledged with the error `[{ "message": "payload too large" }]`.
+    ///
+    /// Registering the event again with [`Socket::on`] removes the limit.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on_with_max_payload("chat", 1024, |Data::<String>(msg)| {
+    ///         println!("chat message: {msg}");
+    ///     });
+    ///     socket.on_with_max_payload("upload-meta", 64 * 1024, |Bin(bin)| {
+    ///         println!("received {} attachments", bin.len());
+    ///     });
+    /// });
+    /// ```
+    pub fn on_with_max_payload<H, T>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        max_payload: usize,
+        handler: H,
+    ) where
+        H: MessageHandler<A, T>,
+        T: Send + Sync + 'static,
+    {
+        let event = event.into();
+        self.on(event.clone(), handler);
+        self.payload_limits
+            .write()
+            .unwrap()
+            .insert(event, max_payload);
     }
 
     /// ## Registers a disconnect handler.
@@ -270,6 +552,204 @@ impl<A: Adapter> Socket<A> {
         self.disconnect_handler.lock().unwrap().replace(handler);
     }
 
+    /// Returns a future that resolves with the [`DisconnectReason`] when the socket is disconnected.
+    ///
+    /// It is the awaitable counterpart of [`Socket::on_disconnect`] and can be used to cancel
+    /// some work when the socket goes away. The future does not hold a reference to the socket
+    /// and resolves immediately if the socket is already disconnected.
+    /// If the socket is dropped without being disconnected, it resolves with [`DisconnectReason::ClosingServer`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let disconnected = socket.on_disconnect_future();
+    ///     tokio::spawn(async move {
+    ///         tokio::select! {
+    ///             reason = disconnected => println!("socket disconnected: {reason}"),
+    ///             _ = tokio::time::sleep(Duration::from_secs(60)) => println!("work done"),
+    ///         }
+    ///     });
+    /// });
+    pub fn on_disconnect_future(
+        &self,
+    ) -> impl std::future::Future<Output = DisconnectReason> + Send + 'static {
+        let mut rx = self.disconnected.subscribe();
+        async move {
+            loop {
+                if let Some(reason) = *rx.borrow_and_update() {
+                    return reason;
+                }
+                if rx.changed().await.is_err() {
+                    return rx.borrow().unwrap_or(DisconnectReason::ClosingServer);
+                }
+            }
+        }
+    }
+
+    /// **Test only**: returns a future that resolves when the connect handler of the socket has completed.
+    ///
+    /// If the connect handler is async, it resolves when its future is finished,
+    /// and after the events received in the meantime are dispatched.
+    /// It can be used in tests to wait for the connect logic (e.g. joining rooms) before asserting on it.
+    /// It also resolves if the socket is dropped before the handler completes.
+    ///
+    /// It is only available with the `test-utils` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use std::time::Duration;
+    /// # async fn test(io: SocketIo) {
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     tokio::time::sleep(Duration::from_millis(10)).await;
+    ///     socket.join("room1").unwrap();
+    /// });
+    ///
+    /// // ... connect a client, then:
+    /// let socket = io.sockets().unwrap().pop().unwrap();
+    /// socket.connect_handler_completed().await;
+    /// assert!(socket.rooms().unwrap().contains(&"room1".into()));
+    /// # }
+    /// ```
+    #[cfg(any(test, feature = "test-utils"))]
+    #[cfg_attr(docsrs, doc(cfg(feature = "test-utils")))]
+    pub fn connect_handler_completed(
+        &self,
+    ) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut rx = self.ready.subscribe();
+        async move {
+            while !*rx.borrow_and_update() {
+                if rx.changed().await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// ## Registers a callback for the unhandled events.
+    /// It is called with the event name and the payload of each event received
+    /// for which no handler is registered with [`Socket::on`].
+    /// It can be used to detect event name mismatches between the client and the server.
+    ///
+    /// You can register only one callback per socket. If you register multiple callbacks, only the last one will be used.
+    /// If no callback is registered, unhandled events are silently dropped.
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let id = socket.id;
+    ///     socket.on_unhandled(move |event, data| {
+    ///         println!("Socket {} received an unhandled event {}: {:?}", id, event, data);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_unhandled<C>(&self, callback: C)
+    where
+        C: Fn(&str, &Value) + Send + Sync + 'static,
+    {
+        self.unhandled_handler
+            .write()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// ## Registers a callback for the acknowledgements sent to the client.
+    /// It is called with the ack id, the data and the binary payloads of each acknowledgement
+    /// right before it is emitted, after the [ack transformer](crate::SocketIo::set_ack_transformer)
+    /// and the [outbound middlewares](crate::SocketIo::use_outbound) are applied.
+    /// For a binary acknowledgement, the data contains the placeholders of the binary payloads.
+    ///
+    /// It can be used to audit the request/response flows, along with the inbound events.
+    ///
+    /// You can register only one callback per socket. If you register multiple callbacks, only the last one will be used.
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let id = socket.id;
+    ///     socket.on_ack_sent(move |ack_id, data, bin| {
+    ///         println!("Socket {} sent ack {}: {:?} ({} binary payloads)", id, ack_id, data, bin.len());
+    ///     });
+    /// });
+    /// ```
+    pub fn on_ack_sent<C>(&self, callback: C)
+    where
+        C: Fn(i64, &Value, &[Vec<u8>]) + Send + Sync + 'static,
+    {
+        self.ack_sent_handler
+            .write()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// ## Registers a callback for the acknowledgements received with an unknown ack id.
+    /// It is called with the ack id, the data and the binary payloads of each acknowledgement
+    /// that doesn't match any pending request, for example when the client acknowledges twice
+    /// or after the [ack timeout](crate::SocketIoBuilder::ack_timeout) has elapsed.
+    ///
+    /// By default these acknowledgements are silently ignored.
+    ///
+    /// You can register only one callback per socket. If you register multiple callbacks, only the last one will be used.
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let id = socket.id;
+    ///     socket.on_stray_ack(move |ack_id, data, _| {
+    ///         println!("Socket {} sent an unexpected ack {}: {:?}", id, ack_id, data);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_stray_ack<C>(&self, callback: C)
+    where
+        C: Fn(i64, &Value, &[Vec<u8>]) + Send + Sync + 'static,
+    {
+        self.stray_ack_handler
+            .write()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// ## Registers a callback for the errors of the acknowledgements returned by the message handlers.
+    /// It is called with the ack id and the error when the value returned by a handler
+    /// (see [`IntoAck`](crate::handler::message::IntoAck)) can't be serialized or sent to the client,
+    /// and when the data given to [`AckSender::send`](crate::extract::AckSender::send) can't be serialized.
+    ///
+    /// When the value can't be serialized, an error acknowledgement is still sent to the client
+    /// so that it doesn't wait for the ack until its timeout.
+    ///
+    /// You can register only one callback per socket. If you register multiple callbacks, only the last one will be used.
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let id = socket.id;
+    ///     socket.on_error(move |ack_id, err| {
+    ///         println!("Socket {} failed to send ack {}: {}", id, ack_id, err);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_error<C>(&self, callback: C)
+    where
+        C: Fn(i64, &SendError<()>) + Send + Sync + 'static,
+    {
+        self.error_handler
+            .write()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
     /// Emits a message to the client
     ///
     /// If you provide array-like data (tuple, vec, arrays), it will be considered as multiple arguments.
@@ -284,6 +764,11 @@ impl<A: Adapter> Socket<A> {
     /// will be returned and the provided data to be send will be given back in the error.
     /// See [`SocketIoBuilder::max_buffer_size`] option for more infos on internal buffer config
     ///
+    /// If the socket is disconnected, the message is given to the [`OfflineStore`](crate::offline::OfflineStore).
+    /// If it is not stored, a [`SendError::Socket(SocketError::Closed)`] is always returned,
+    /// so that a task holding the socket after its disconnection can stop emitting.
+    /// All the other emit methods also return this error once the socket is disconnected.
+    ///
     /// [`SocketIoBuilder::max_buffer_size`]: crate::SocketIoBuilder#method.max_buffer_size
     /// [`SendError::Serialize`]: crate::SendError::Serialize
     /// [`SendError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
@@ -313,6 +798,12 @@ impl<A: Adapter> Socket<A> {
         event: impl Into<Cow<'static, str>>,
         data: T,
     ) -> Result<(), SendError<T>> {
+        if !self.connected() {
+            let packet = Packet::event(self.ns(), event.into(), serde_json::to_value(&data)?);
+            return self
+                .store_offline(packet)
+                .map_err(|e| e.with_value(data).into());
+        }
         let permits = match self.reserve(1) {
             Ok(permits) => permits,
             Err(e) => {
@@ -324,10 +815,122 @@ impl<A: Adapter> Socket<A> {
 
         let ns = self.ns();
         let data = serde_json::to_value(data)?;
-        permits.emit(Packet::event(ns, event.into(), data));
+        let mut packet = Packet::event(ns, event.into(), data);
+        self.prepare_packet(&mut packet);
+        permits.emit(packet);
         Ok(())
     }
 
+    /// Emits a message to the client with an explicit list of arguments.
+    ///
+    /// Each element of `args` is sent as one argument of the event, even if it is itself an array,
+    /// whereas with [`emit()`](#method.emit) array-like data is spread into multiple arguments.
+    /// It is symmetric to the reception of multiple arguments with a tuple extractor:
+    /// the arguments sent with `socket.emit("event", 1, [2, 3])` by a client
+    /// are received with `Data::<(i32, Vec<i32>)>` and the ones sent with
+    /// `emit_args("event", &[json!(1), json!([2, 3])])` are received by the client callback
+    /// as `(a, b) => {}` with `a = 1` and `b = [2, 3]`.
+    ///
+    /// Like with [`emit()`](#method.emit), an empty `args` list is sent as a single empty array argument.
+    ///
+    /// The errors are the same as with [`emit()`](#method.emit).
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::json;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     // The client receives a single argument: the [1, 2, 3] array
+    ///     socket.emit_args("test", &[json!([1, 2, 3])]).ok();
+    ///     // The client receives two arguments: "hello" and [1, 2, 3]
+    ///     socket.emit_args("test", &[json!("hello"), json!([1, 2, 3])]).ok();
+    /// });
+    /// ```
+    #[inline]
+    pub fn emit_args<'a>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        args: &'a [Value],
+    ) -> Result<(), SendError<&'a [Value]>> {
+        self.emit(event, args)
+    }
+
+    /// Emits a message with binary payloads to the client.
+    ///
+    /// It is a shortcut for `socket.bin(bin).emit(event, data)`, see [`emit()`](#method.emit)
+    /// for more details on the data format and the errors that can be returned.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data), Bin(bin)| async move {
+    ///         // Send back the binary payloads received to the client only
+    ///         socket.emit_bin("test", data, bin).ok();
+    ///     });
+    /// });
+    /// ```
+    #[inline]
+    pub fn emit_bin<T: Serialize>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+        bin: Vec<Vec<u8>>,
+    ) -> Result<(), SendError<T>> {
+        self.bin(bin).emit(event, data)
+    }
+
+    /// Emits a message with only binary payloads to the client.
+    ///
+    /// Unlike [`emit_bin()`](#method.emit_bin), the packet doesn't have any JSON data,
+    /// so the client receives the binary payloads as the only arguments of the event:
+    /// ```js
+    /// socket.on("file", (chunk1, chunk2) => { /* ... */ });
+    /// ```
+    /// The errors are the same as with [`emit()`](#method.emit).
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.emit_binary("file", vec![vec![1, 2, 3], vec![4, 5, 6]]).ok();
+    /// });
+    /// ```
+    #[inline]
+    pub fn emit_binary(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        bin: Vec<Vec<u8>>,
+    ) -> Result<(), SendError<()>> {
+        ConfOperators::new(self).emit_binary(event, bin)
+    }
+
+    /// Encodes a message as it would be emitted to the client, without sending it.
+    ///
+    /// It returns the socket.io text frame of the message, see [`ConfOperators::encode`] for more details.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let frame = socket.encode("hello", "world").unwrap();
+    ///     assert_eq!(frame, r#"2["hello","world"]"#);
+    /// });
+    /// ```
+    #[inline]
+    pub fn encode<T: Serialize>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+    ) -> Result<String, serde_json::Error> {
+        ConfOperators::new(self).encode(event, data)
+    }
+
     /// Emits a message to the client and wait for acknowledgement.
     ///
     /// The acknowledgement has a timeout specified in the config (5s by default)
@@ -350,7 +953,8 @@ impl<A: Adapter> Socket<A> {
     ///
     /// If the client didn't respond before the timeout, the [`AckStream`] will yield
     /// an [`AckError::Timeout`]. If the data sent by the client is not deserializable as `V`,
-    /// an [`AckError::Serde`] will be yielded.
+    /// an [`AckError::Serde`] will be yielded. If the socket is disconnected before
+    /// the client responds, an [`AckError::Disconnected`] will be yielded.
     ///
     /// [`timeout()`]: crate::operators::ConfOperators#method.timeout
     /// [`SocketIoBuilder::ack_timeout`]: crate::SocketIoBuilder#method.ack_timeout
@@ -359,6 +963,7 @@ impl<A: Adapter> Socket<A> {
     /// [`AckError`]: crate::AckError
     /// [`AckError::Serde`]: crate::AckError::Serde
     /// [`AckError::Timeout`]: crate::AckError::Timeout
+    /// [`AckError::Disconnected`]: crate::AckError::Disconnected
     /// [`AckError::Socket`]: crate::AckError::Socket
     /// [`AckError::Socket(SocketError::Closed)`]: crate::SocketError::Closed
     /// [`io::get_socket()`]: crate::SocketIo#method.get_socket
@@ -394,22 +999,112 @@ impl<A: Adapter> Socket<A> {
         };
         let data = serde_json::to_value(data)?;
         let packet = Packet::event(self.ns(), event.into(), data);
-        let rx = self.send_with_ack_permit(packet, permits);
-        let stream = AckInnerStream::send(rx, self.config.ack_timeout, self.id);
+        let (rx, guard) = self.send_with_ack_permit(packet, permits);
+        let stream = AckInnerStream::send_guarded(rx, self.config.ack_timeout, self.id, guard);
         Ok(AckStream::<V>::from(stream))
     }
 
-    // Room actions
-
-    /// Joins the given rooms.
+    /// Emits a message to the client and waits for an acknowledgement,
+    /// sending it again up to `retries` times if the acknowledgement times out.
     ///
-    /// If the room does not exist, it will be created.
+    /// Each attempt waits for the [`SocketIoBuilder::ack_timeout`] with a new ack id.
+    /// Before each retry, the socket waits for the `backoff` duration, doubled after each retry.
     ///
-    /// ## Errors
-    /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
-    /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
-    pub fn join(&self, rooms: impl RoomParam) -> Result<(), A::Error> {
-        self.ns.adapter.add_all(self.id, rooms)
+    /// **Note**: The client may receive the event several times, for example if its acknowledgement
+    /// was only delayed. It is up to the application to make the event handling idempotent.
+    ///
+    /// # Errors
+    ///
+    /// If the packet encoding failed an [`AckError::Serde`] is returned.
+    ///
+    /// If all the attempts timed out, an [`AckError::Timeout`] is returned.
+    /// Any other error (socket closed or full, invalid acknowledgement data, ...) is returned without retrying.
+    ///
+    /// [`SocketIoBuilder::ack_timeout`]: crate::SocketIoBuilder#method.ack_timeout
+    /// [`AckError::Serde`]: crate::AckError::Serde
+    /// [`AckError::Timeout`]: crate::AckError::Timeout
+    ///
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // Send the message at most 4 times, waiting 100ms, 200ms and then 400ms between each attempt
+    ///         let backoff = Duration::from_millis(100);
+    ///         match socket.emit_with_ack_retry::<_, Value>("test", data, 3, backoff).await {
+    ///             Ok(ack) => println!("Ack received {:?}", ack),
+    ///             Err(err) => println!("Ack error {:?}", err),
+    ///         }
+    ///    });
+    /// });
+    /// ```
+    pub async fn emit_with_ack_retry<T: Serialize, V: DeserializeOwned>(
+        &self,
+        event: impl Into<Cow<'static, str>>,
+        data: T,
+        retries: u32,
+        backoff: Duration,
+    ) -> Result<AckResponse<V>, AckError<()>> {
+        let event = event.into();
+        let data = serde_json::to_value(data)?;
+        let mut attempt = 0;
+        loop {
+            let packet = Packet::event(self.ns(), event.clone(), data.clone());
+            let (rx, guard) = self.send_with_ack(packet, None);
+            let stream = AckInnerStream::send_guarded(rx, self.config.ack_timeout, self.id, guard);
+            match AckStream::<V>::from(stream).await {
+                Err(AckError::Timeout) if attempt < retries => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("ack timeout for event {}, retrying", event);
+                    tokio::time::sleep(backoff.saturating_mul(2u32.saturating_pow(attempt))).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    // Room actions
+
+    /// Joins the given rooms.
+    ///
+    /// If the room does not exist, it will be created.
+    ///
+    /// ## Errors
+    /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
+    /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
+    pub fn join(&self, rooms: impl RoomParam) -> Result<(), A::Error> {
+        self.ns.adapter.add_all(self.id, rooms)
+    }
+
+    /// Joins the given rooms and waits until the adapter confirms the membership.
+    ///
+    /// With a distributed adapter, [`Socket::join`] may return before the other servers know about
+    /// the new membership, so a broadcast emitted right after it from another server could miss this socket.
+    /// This method resolves only once the membership has been propagated to the whole cluster
+    /// (see [`Adapter::add_all_confirmed`]).
+    ///
+    /// With the default [`LocalAdapter`] it resolves immediately.
+    ///
+    /// ## Errors
+    /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
+    /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| async move {
+    ///     socket.join_with_ack("room1").await.unwrap();
+    ///     // The socket is now in the room on every server
+    ///     socket.within("room1").emit("joined", socket.id).ok();
+    /// });
+    /// ```
+    pub async fn join_with_ack(&self, rooms: impl RoomParam) -> Result<(), A::Error> {
+        self.ns.adapter.add_all_confirmed(self.id, rooms).await
     }
 
     /// Leaves the given rooms.
@@ -422,7 +1117,7 @@ impl<A: Adapter> Socket<A> {
         self.ns.adapter.del(self.id, rooms)
     }
 
-    /// Leaves all rooms where the socket is connected.
+    /// Leaves all rooms where the socket is connected, including the room named after its id.
     /// ## Errors
     /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
     /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
@@ -430,7 +1125,33 @@ impl<A: Adapter> Socket<A> {
         self.ns.adapter.del_all(self.id)
     }
 
+    /// Replaces the rooms of the socket with the given ones:
+    /// it leaves the rooms that are not in the given set and joins the new ones.
+    ///
+    /// It can be used to reconcile the membership of the socket with an external source of truth,
+    /// for example after a server rejoins the cluster.
+    /// The room named after the socket id is always kept.
+    /// ## Errors
+    /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
+    /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.join(["room1", "room2"]).unwrap();
+    ///     socket.sync_rooms(vec!["room2".into(), "room3".into()]).unwrap();
+    ///     // The socket is now in "room2", "room3" and the room named after its id
+    /// });
+    /// ```
+    pub fn sync_rooms(&self, rooms: Vec<Room>) -> Result<(), A::Error> {
+        self.ns.adapter.sync_rooms(self.id, rooms)
+    }
+
     /// Gets all rooms where the socket is connected.
+    ///
+    /// It includes the room named after the socket id, which every socket joins when it connects.
     /// ## Errors
     /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
     /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
@@ -438,6 +1159,29 @@ impl<A: Adapter> Socket<A> {
         self.ns.adapter.socket_rooms(self.id)
     }
 
+    /// Returns true if the socket is in the given room, without fetching all its rooms.
+    ///
+    /// It returns false for an unknown room and once the socket is disconnected.
+    /// ## Errors
+    /// When using a distributed adapter, it can return an [`Adapter::Error`] which is mostly related to network errors.
+    /// For the default [`LocalAdapter`] it is always an [`Infallible`](std::convert::Infallible) error
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("kick", |socket: SocketRef, Data::<String>(user)| {
+    ///         if socket.in_room("admins").unwrap() {
+    ///             socket.to(user).disconnect().ok();
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn in_room(&self, room: &str) -> Result<bool, A::Error> {
+        self.ns.adapter.is_in_room(self.id, room)
+    }
+
     // Socket operators
 
     /// Selects all clients in the given rooms except the current socket.
@@ -464,6 +1208,29 @@ impl<A: Adapter> Socket<A> {
         BroadcastOperators::from_sock(self.ns.clone(), self.id).to(rooms)
     }
 
+    /// Selects all clients sharing at least one room with the current socket, except the current socket.
+    ///
+    /// The rooms are queried from the adapter when the operator is created,
+    /// so the rooms joined or left afterwards are not taken into account.
+    /// It fails if the adapter fails to return the rooms of the socket.
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.join(["room1", "room2"]).unwrap();
+    ///     socket.on("typing", |socket: SocketRef| {
+    ///         // Tell everyone in room1 or room2 that the current socket is typing
+    ///         socket.to_my_rooms().unwrap().emit("typing", socket.id).ok();
+    ///     });
+    /// });
+    pub fn to_my_rooms(&self) -> Result<BroadcastOperators<A>, A::Error> {
+        // The rooms always contain the room of the socket id,
+        // so that nobody is selected when the socket has not joined any other room
+        let rooms = self.rooms()?;
+        Ok(self.to(rooms))
+    }
+
     /// Selects all clients in the given rooms.
     ///
     /// It does include the current socket contrary to the `to()` operator.
@@ -488,6 +1255,23 @@ impl<A: Adapter> Socket<A> {
         BroadcastOperators::from_sock(self.ns.clone(), self.id).within(rooms)
     }
 
+    /// Selects the sockets with the given ids.
+    ///
+    /// The current socket is selected if its id is given.
+    /// See [`BroadcastOperators::to_sids`] for more details.
+    pub fn to_sids(&self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).to_sids(sids)
+    }
+
+    /// Selects the clients with reusable [`BroadcastOptions`].
+    ///
+    /// The current socket is the sender of the broadcast, it is only excluded if the options
+    /// have the [`BroadcastFlags::Broadcast`](crate::adapter::BroadcastFlags::Broadcast) flag.
+    /// See [`BroadcastOperators::with_opts`] for more details.
+    pub fn with_opts(&self, opts: BroadcastOptions) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).with_opts(opts)
+    }
+
     /// Filters out all clients selected with the previous operators which are in the given rooms.
     /// # Example
     /// ```
@@ -512,6 +1296,50 @@ impl<A: Adapter> Socket<A> {
         BroadcastOperators::from_sock(self.ns.clone(), self.id).except(rooms)
     }
 
+    /// Selects all clients in the namespace except the ones with the given ids and the current socket.
+    /// See [`BroadcastOperators::except_sids`] for more details.
+    pub fn except_sids(&self, sids: impl IntoIterator<Item = Sid>) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).except_sids(sids)
+    }
+
+    /// Selects all clients in the rooms matching the given glob pattern except the current socket.
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // In all the rooms starting with "game:" except the current socket
+    ///         socket.to_pattern("game:*").emit("test", data);
+    ///     });
+    /// });
+    pub fn to_pattern(&self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).to_pattern(pattern)
+    }
+
+    /// Filters out all clients selected with the previous operators which are in the rooms
+    /// matching the given glob pattern.
+    ///
+    /// See [`RoomPattern`] for the pattern semantics.
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // This message will be broadcast to all clients in the Namespace
+    ///         // except for ones in rooms starting with "admin:" and the current socket
+    ///         socket.except_pattern("admin:*").emit("test", data);
+    ///     });
+    /// });
+    pub fn except_pattern(&self, pattern: impl Into<RoomPattern>) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).except_pattern(pattern)
+    }
+
     /// Broadcasts to all clients only connected on this node (when using multiple nodes).
     /// When using the default in-memory [`LocalAdapter`], this operator is a no-op.
     /// # Example
@@ -530,6 +1358,24 @@ impl<A: Adapter> Socket<A> {
         BroadcastOperators::from_sock(self.ns.clone(), self.id).local()
     }
 
+    /// Broadcasts to the clients connected on the other nodes only (when using multiple nodes).
+    /// It is the inverse of [`local`](Self::local), the clients of the current node are skipped.
+    /// When using the default in-memory [`LocalAdapter`] there is no other node and nobody receives the message.
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use serde_json::Value;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef, Data::<Value>(data)| async move {
+    ///         // This message will be broadcast to all clients in this namespace connected on the other nodes
+    ///         socket.remote().broadcast().emit("test", data);
+    ///     });
+    /// });
+    pub fn remote(&self) -> BroadcastOperators<A> {
+        BroadcastOperators::from_sock(self.ns.clone(), self.id).remote()
+    }
+
     /// Sets a custom timeout when sending a message with an acknowledgement.
     ///
     /// See [`SocketIoBuilder::ack_timeout`](crate::SocketIoBuilder) for the default timeout.
@@ -586,6 +1432,18 @@ impl<A: Adapter> Socket<A> {
         ConfOperators::new(self).bin(binary)
     }
 
+    /// Attaches an idempotency key to the message, see [`ConfOperators::with_id`].
+    /// # Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.with_id("welcome-1").emit("welcome", "hello").ok();
+    /// });
+    pub fn with_id(&self, key: impl Into<String>) -> ConfOperators<'_, A> {
+        ConfOperators::new(self).with_id(key)
+    }
+
     /// Broadcasts to all clients without any filtering (except the current socket).
     /// # Example
     /// ```
@@ -606,19 +1464,208 @@ impl<A: Adapter> Socket<A> {
     /// Disconnects the socket from the current namespace,
     ///
     /// It will also call the disconnect handler if it is set.
+    /// If it was the last namespace of the connection, the connection is closed too
+    /// and the websocket close frame tells the client why it was disconnected.
     pub fn disconnect(self: Arc<Self>) -> Result<(), DisconnectError> {
+        self.disconnect_with(DisconnectReason::ServerNSDisconnect)
+    }
+
+    /// Disconnects the socket from the current namespace with a custom [`DisconnectReason`].
+    ///
+    /// The disconnect handler, if it is set, will be called with the given `reason`.
+    /// It can be used to notify your own disconnect handler about why the server
+    /// decided to drop the socket (e.g. a protocol violation).
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, socket::DisconnectReason, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("forbidden", |socket: SocketRef| {
+    ///         socket.disconnect_with(DisconnectReason::PacketParsingError).ok();
+    ///     });
+    ///     socket.on_disconnect(|socket: SocketRef, reason: DisconnectReason| {
+    ///         println!("Socket {} disconnected: {}", socket.id, reason);
+    ///     });
+    /// });
+    pub fn disconnect_with(
+        self: Arc<Self>,
+        reason: DisconnectReason,
+    ) -> Result<(), DisconnectError> {
         let res = self.send(Packet::disconnect(&self.ns.path));
         if let Err(SocketError::InternalChannelFull(_)) = res {
             return Err(DisconnectError::InternalChannelFull);
         }
 
-        self.close(DisconnectReason::ServerNSDisconnect)?;
+        self.clone().close(reason)?;
+
+        // The connection is closed along with its last namespace, so that the client
+        // and the proxies are told why it was closed by the websocket close frame
+        if self.namespaces().is_empty() {
+            let (code, message) = reason.close_code();
+            self.esocket
+                .close_with_code(EIoDisconnectReason::TransportClose, code, message);
+        }
         Ok(())
     }
 
+    /// Gracefully disconnects the socket from the current namespace.
+    ///
+    /// The socket stops handling the events it receives (acknowledgements are still handled)
+    /// and waits for the acknowledgements owed by the running handlers to be sent and for its outbound queue
+    /// to be flushed to the transport, at most for the given `timeout`.
+    /// An [`AckSender`](crate::extract::AckSender) still held by the calling handler, or the result of the calling handler
+    /// if it is sent as an acknowledgement (see [`IntoAck`](crate::handler::message::IntoAck)), therefore delays it until the `timeout`.
+    /// It is then disconnected as with [`Socket::disconnect`]
+    /// and the disconnect handler is called with a [`DisconnectReason::ServerNSDisconnect`].
+    ///
+    /// With the polling transport, the queue is only flushed when the client sends its next polling request.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use std::time::Duration;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("maintenance", |socket: SocketRef| async move {
+    ///         socket.emit("bye", "see you soon").ok();
+    ///         socket.drain_and_close(Duration::from_secs(5)).await.ok();
+    ///     });
+    /// });
+    pub async fn drain_and_close(
+        self: Arc<Self>,
+        timeout: Duration,
+    ) -> Result<(), DisconnectError> {
+        self.closing.store(true, Ordering::SeqCst);
+        let drained = async {
+            while (self.send_buffer_len() > 0 || self.owed_acks.load(Ordering::SeqCst) > 0)
+                && !self.esocket.is_closed()
+            {
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        };
+        if tokio::time::timeout(timeout, drained).await.is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("socket {} drain timed out, disconnecting", self.id);
+        }
+        self.disconnect()
+    }
+
+    /// Pauses the dispatch of the events received from the client, for example while loading its state.
+    ///
+    /// The events are buffered until [`Socket::resume`] is called, then they are dispatched in their arrival order.
+    /// The acknowledgements and the disconnect packets sent by the client are still handled.
+    ///
+    /// At most `max_events` events are buffered, the next ones are handled according to the [`OverflowPolicy`]:
+    /// * [`OverflowPolicy::Drop`]: they are dropped and therefore never acknowledged.
+    /// * [`OverflowPolicy::Disconnect`]: the socket is disconnected and the buffered events are dropped.
+    ///
+    /// Pausing an already paused socket only changes the bound and the policy, the buffered events are kept.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, socket::OverflowPolicy};
+    /// # async fn load_state(id: &str) {}
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("chat", |Data::<String>(msg)| println!("chat message: {msg}"));
+    ///     socket.on("reload", |socket: SocketRef| async move {
+    ///         // The chat messages received while the state is loaded are handled after it
+    ///         socket.pause(64, OverflowPolicy::Disconnect);
+    ///         load_state(socket.id.as_str()).await;
+    ///         socket.resume();
+    ///     });
+    /// });
+    /// ```
+    pub fn pause(&self, max_events: usize, overflow: OverflowPolicy) {
+        let mut paused = self.paused.lock().unwrap();
+        match paused.as_mut() {
+            Some(paused) => {
+                paused.max_events = max_events;
+                paused.overflow = overflow;
+            }
+            None => {
+                *paused = Some(PausedEvents {
+                    max_events,
+                    overflow,
+                    events: Vec::new(),
+                })
+            }
+        }
+    }
+
+    /// Resumes the dispatch of the events received from the client after a [`Socket::pause`].
+    ///
+    /// The buffered events are dispatched in their arrival order before the new ones.
+    /// It does nothing if the socket is not paused.
+    pub fn resume(self: Arc<Self>) {
+        loop {
+            let events = {
+                let mut paused = self.paused.lock().unwrap();
+                match paused.as_mut() {
+                    Some(paused) if !paused.events.is_empty() && self.connected() => {
+                        std::mem::take(&mut paused.events)
+                    }
+                    _ => {
+                        *paused = None;
+                        return;
+                    }
+                }
+            };
+            for (event, payload_len) in events {
+                if let Err(ref err) = self.clone().dispatch_packet(event, payload_len) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error while processing paused event: {}", err);
+                    if let Some(reason) = err.into() {
+                        self.esocket.close(reason);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns true if the socket is [paused](Socket::pause).
+    pub fn is_paused(&self) -> bool {
+        self.paused.lock().unwrap().is_some()
+    }
+
+    /// Buffers an inbound event if the socket is paused.
+    /// Returns the event back if it should be dispatched.
+    fn buffer_paused<'a>(
+        self: &Arc<Self>,
+        event: PacketData<'a>,
+        payload_len: usize,
+    ) -> Option<PacketData<'a>> {
+        let overflow = {
+            let mut paused = self.paused.lock().unwrap();
+            let Some(paused) = paused.as_mut() else {
+                return Some(event);
+            };
+            if paused.events.len() < paused.max_events {
+                paused.events.push((event.into_owned(), payload_len));
+                return None;
+            }
+            if paused.overflow == OverflowPolicy::Disconnect {
+                paused.events.clear();
+            }
+            paused.overflow
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!("socket {} paused buffer is full: {:?}", self.id, overflow);
+        if overflow == OverflowPolicy::Disconnect {
+            if let Err(_e) = self.clone().disconnect() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error while disconnecting the socket {}: {}", self.id, _e);
+            }
+        }
+        None
+    }
+
     /// Closes the engine.io connection if it is not already closed.
     /// Return a future that resolves when the underlying transport is closed.
     pub(crate) async fn close_underlying_transport(&self) {
+        self.flush_owed_acks().await;
         if !self.esocket.is_closed() {
             #[cfg(feature = "tracing")]
             tracing::debug!("closing underlying transport for socket: {}", self.id);
@@ -627,42 +1674,352 @@ impl<A: Adapter> Socket<A> {
         self.esocket.closed().await;
     }
 
+    /// Registers an acknowledgement owed to the client by a running handler.
+    pub(crate) fn owe_ack(self: &Arc<Self>) -> OwedAck<A> {
+        self.owed_acks.fetch_add(1, Ordering::SeqCst);
+        OwedAck(self.clone())
+    }
+
+    /// Waits until the acknowledgements owed to the client are sent,
+    /// for at most the [`ack_flush_timeout`](SocketIoConfig::ack_flush_timeout).
+    async fn flush_owed_acks(&self) {
+        let flushed = async {
+            while self.owed_acks.load(Ordering::SeqCst) > 0 && !self.esocket.is_closed() {
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        };
+        if tokio::time::timeout(self.config.ack_flush_timeout, flushed)
+            .await
+            .is_err()
+        {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("socket {} closing with unsent acks", self.id);
+        }
+    }
+
     /// Gets the current namespace path.
     #[inline]
     pub fn ns(&self) -> &str {
         &self.ns.path
     }
 
-    pub(crate) fn reserve(&self, n: usize) -> Result<PermitIterator<'_>, SocketError<()>> {
-        Ok(self.esocket.reserve(n)?)
+    /// Gets the [`Adapter`] of the current namespace.
+    ///
+    /// It can be used to call the methods specific to a custom adapter from the handlers.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*, adapter::Adapter};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let rooms = socket.adapter().rooms().unwrap();
+    ///     println!("rooms of the namespace: {:?}", rooms);
+    /// });
+    /// ```
+    #[inline]
+    pub fn adapter(&self) -> &A {
+        &self.ns.adapter
     }
 
-    pub(crate) fn send(&self, packet: Packet<'_>) -> Result<(), SocketError<()>> {
-        let permits = self.reserve(1 + packet.inner.payload_count())?;
-        permits.emit(packet);
-        Ok(())
+    /// Reserves `n` permits to send packets to the client.
+    /// Nothing can be sent once the socket is disconnected from its namespace,
+    /// even if the underlying connection is still used by other namespaces.
+    ///
+    /// If the buffer of the connection is full, the [`OutboundPolicy`] of the config is applied.
+    pub(crate) fn reserve(&self, n: usize) -> Result<Permits<'_, A>, SocketError<()>> {
+        if !self.connected() {
+            return Err(SocketError::Closed(()));
+        }
+        let policy = self.config.outbound_policy;
+        // The packets are queued behind the ones waiting for room in the buffer, to keep their order
+        if policy == OutboundPolicy::DropOldest && self.overflow.lock().unwrap().is_some() {
+            return Ok(Permits::Queued(self));
+        }
+        let err = match self.esocket.reserve(n) {
+            Ok(permits) => return Ok(Permits::Reserved(permits)),
+            Err(e @ TrySendError::Closed(_)) => return Err(e.into()),
+            Err(e) => e,
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!("socket {} buffer is full, applying {:?}", self.id, policy);
+        match policy {
+            OutboundPolicy::DropOldest => return Ok(Permits::Queued(self)),
+            OutboundPolicy::DropNewest => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return Ok(Permits::Dropped);
+            }
+            OutboundPolicy::Disconnect => {
+                // The socket is closed from another task so that its disconnect handler is not called while emitting
+                if self.connected.swap(false, Ordering::SeqCst) {
+                    if let Ok(socket) = self.ns.get_socket(self.id) {
+                        tokio::spawn(socket.disconnect_overflowed());
+                    }
+                }
+            }
+            OutboundPolicy::Error => {}
+        }
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        Err(err.into())
     }
 
-    pub(crate) fn send_with_ack_permit(
-        &self,
-        mut packet: Packet<'_>,
-        permits: PermitIterator<'_>,
-    ) -> Receiver<AckResult<Value>> {
-        let (tx, rx) = oneshot::channel();
-
-        let ack = self.ack_counter.fetch_add(1, Ordering::SeqCst) + 1;
-        packet.inner.set_ack_id(ack);
-        permits.emit(packet);
-        self.ack_message.lock().unwrap().insert(ack, tx);
-        rx
-    }
+    /// Queues a packet that could not be buffered by the connection, with the [`OutboundPolicy::DropOldest`] policy.
+    /// The queue is bounded by the [`max_buffer_size`](crate::SocketIoBuilder::max_buffer_size):
+    /// once it is full, its oldest event is dropped to make room for the packet.
+    /// Other packets (acknowledgements, disconnect packets) are never dropped.
+    fn enqueue(&self, packet: Packet<'static>) {
+        fn is_event(packet: &Packet<'_>) -> bool {
+            matches!(
+                packet.inner,
+                PacketData::Event(..) | PacketData::BinaryEvent(..)
+            )
+        }
 
-    pub(crate) fn send_with_ack(&self, mut packet: Packet<'_>) -> Receiver<AckResult<Value>> {
-        let (tx, rx) = oneshot::channel();
+        let mut overflow = self.overflow.lock().unwrap();
+        if overflow.is_none() {
+            let Ok(socket) = self.ns.get_socket(self.id) else {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            };
+            tokio::spawn(socket.drain_overflow());
+        }
+        let queue = overflow.get_or_insert_with(VecDeque::new);
+        if queue.len() >= self.config.engine_config.max_buffer_size {
+            if let Some(i) = queue.iter().position(is_event) {
+                queue.remove(i);
+            } else if is_event(&packet) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                "socket {} outbound queue is full, dropping oldest event",
+                self.id
+            );
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        queue.push_back(packet);
+    }
+
+    /// Sends the queued packets in order as soon as there is room in the buffer of the connection.
+    /// The task ends once the queue is empty or when the connection is closed.
+    async fn drain_overflow(self: Arc<Self>) {
+        loop {
+            let packet = {
+                let mut overflow = self.overflow.lock().unwrap();
+                match overflow.as_mut().and_then(VecDeque::pop_front) {
+                    Some(packet) => packet,
+                    None => {
+                        *overflow = None;
+                        return;
+                    }
+                }
+            };
+            match self
+                .esocket
+                .reserve_wait(1 + packet.inner.payload_count())
+                .await
+            {
+                Ok(permits) => permits.emit(packet),
+                Err(_) if self.esocket.is_closed() => {
+                    *self.overflow.lock().unwrap() = None;
+                    return;
+                }
+                // The packet needs more permits than the buffer size
+                Err(_) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Disconnects the socket from its namespace because its connection buffer is full,
+    /// with the [`OutboundPolicy::Disconnect`] policy.
+    /// The client is notified with a disconnect packet as soon as there is room in the buffer.
+    async fn disconnect_overflowed(self: Arc<Self>) {
+        if let Err(_e) = self.clone().close(DisconnectReason::ServerNSDisconnect) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("error while closing socket {}: {:?}", self.id, _e);
+        }
+        if let Ok(permits) = self.esocket.reserve_wait(1).await {
+            permits.emit(Packet::disconnect(&self.ns.path));
+        }
+    }
+
+    pub(crate) fn send(&self, packet: Packet<'_>) -> Result<(), SocketError<()>> {
+        self.send_cached(packet, None)
+    }
+
+    /// Sends a packet broadcasted to several sockets,
+    /// its binary attachments are compressed once for all of them with the given cache.
+    pub(crate) fn send_broadcast(
+        &self,
+        packet: Packet<'_>,
+        cache: &mut CompressCache,
+    ) -> Result<(), SocketError<()>> {
+        self.send_cached(packet, Some(cache))
+    }
+
+    fn send_cached(
+        &self,
+        mut packet: Packet<'_>,
+        cache: Option<&mut CompressCache>,
+    ) -> Result<(), SocketError<()>> {
+        let permits = self.reserve(1 + packet.inner.payload_count())?;
+        self.prepare_packet_cached(&mut packet, cache);
+        permits.emit(packet);
+        Ok(())
+    }
+
+    /// Gives a packet emitted while the socket is disconnected to the [`OfflineStore`](crate::offline::OfflineStore).
+    pub(crate) fn store_offline(&self, packet: Packet<'_>) -> Result<(), SocketError<()>> {
+        if self
+            .config
+            .offline_store
+            .store(self.ns(), self.id, packet.into_owned())
+        {
+            Ok(())
+        } else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("socket {} is disconnected, dropping packet", self.id);
+            Err(SocketError::Closed(()))
+        }
+    }
+
+    /// Sends the packets stored in the [`OfflineStore`](crate::offline::OfflineStore)
+    /// for a previous socket whose session was recovered by this socket.
+    pub(crate) fn flush_offline(&self, sid: Sid) {
+        for packet in self.config.offline_store.take(self.ns(), sid) {
+            if let Err(_e) = self.send(packet) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error flushing offline packet: {:?}", _e);
+                break;
+            }
+        }
+    }
+
+    /// Runs the outbound middlewares of the namespace on the packet and adds its event prefix.
+    /// Then tags the packet with its offset and buffers it if the connection state recovery is enabled.
+    pub(crate) fn prepare_packet(&self, packet: &mut Packet<'_>) {
+        self.prepare_packet_cached(packet, None)
+    }
+
+    #[allow(unused_variables)]
+    fn prepare_packet_cached(&self, packet: &mut Packet<'_>, cache: Option<&mut CompressCache>) {
+        self.ns.apply_outbound(packet);
+        self.ns.add_event_prefix(packet);
+        #[cfg(feature = "compress-binary")]
+        self.compress_binary(packet, cache);
+        if let Some(buffer) = self.recovery.lock().unwrap().as_mut() {
+            buffer.push(packet);
+        }
+    }
+
+    /// Calls the callback registered with [`Socket::on_error`] for an ack that failed to be sent.
+    pub(crate) fn notify_error(&self, ack_id: i64, err: &SendError<()>) {
+        if let Some(handler) = self.error_handler.read().unwrap().as_ref() {
+            handler(ack_id, err);
+        }
+    }
+
+    /// Calls the callback registered with [`Socket::on_ack_sent`] for an ack packet about to be emitted.
+    pub(crate) fn notify_ack_sent(&self, packet: &Packet<'_>) {
+        if let Some(handler) = self.ack_sent_handler.read().unwrap().as_ref() {
+            match &packet.inner {
+                PacketData::EventAck(data, ack) => handler(*ack, data, &[]),
+                PacketData::BinaryAck(bin, ack) => handler(*ack, &bin.data, &bin.bin),
+                _ => (),
+            }
+        }
+    }
+
+    /// Compresses the binary attachments of the packet if the client supports it and uses the polling transport.
+    /// With a cache, the attachments compressed for a previous socket are reused if they are the same.
+    #[cfg(feature = "compress-binary")]
+    fn compress_binary(&self, packet: &mut Packet<'_>, cache: Option<&mut CompressCache>) {
+        let Some(min_size) = self.config.compress_binary else {
+            return;
+        };
+        if !self.compress_binary || self.transport_type() != crate::TransportType::Polling {
+            return;
+        }
+        if let PacketData::BinaryEvent(_, bin, _) | PacketData::BinaryAck(bin, _) =
+            &mut packet.inner
+        {
+            match cache {
+                Some(CompressCache {
+                    packet: Some((input, output)),
+                }) if input == bin => {
+                    *bin = output.clone();
+                }
+                Some(cache) => {
+                    let input = bin.clone();
+                    bin.compress(min_size);
+                    cache.packet = Some((input, bin.clone()));
+                }
+                None => bin.compress(min_size),
+            }
+        }
+    }
+
+    /// Returns the private recovery token of the session if the connection state recovery is enabled.
+    pub(crate) fn recovery_pid(&self) -> Option<Sid> {
+        self.recovery.lock().unwrap().as_ref().map(|b| b.pid)
+    }
+
+    /// Restores the buffer of a recovered session of the previous socket `sid`.
+    pub(crate) fn recover(&mut self, sid: Sid, buffer: RecoveryBuffer) {
+        self.recovery = Mutex::new(Some(buffer));
+        self.previous_id = Some(sid);
+    }
+
+    /// Sends again the packets missed by the client during its disconnection.
+    /// They are already tagged with their offset and transformed by the outbound middlewares,
+    /// only their binary attachments are compressed for the current transport.
+    pub(crate) fn replay(&self, packets: Vec<Packet<'static>>) {
+        for packet in packets {
+            #[allow(unused_mut)]
+            let mut packet = packet;
+            #[cfg(feature = "compress-binary")]
+            self.compress_binary(&mut packet, None);
+            let res = self
+                .reserve(1 + packet.inner.payload_count())
+                .map(|permits| permits.emit(packet));
+            if let Err(_e) = res {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error replaying missed packet: {:?}", _e);
+                break;
+            }
+        }
+    }
+
+    pub(crate) fn send_with_ack_permit(
+        &self,
+        mut packet: Packet<'_>,
+        permits: Permits<'_, A>,
+    ) -> (Receiver<AckResult<Value>>, AckGuard) {
+        let (tx, rx) = oneshot::channel();
 
         let ack = self.ack_counter.fetch_add(1, Ordering::SeqCst) + 1;
         packet.inner.set_ack_id(ack);
-        match self.send(packet) {
+        self.prepare_packet(&mut packet);
+        permits.emit(packet);
+        self.ack_message.lock().unwrap().insert(ack, tx);
+        (rx, self.ack_guard(ack))
+    }
+
+    /// Sends a packet with an ack id and returns the receiver of the acknowledgement.
+    /// The returned [`AckGuard`] removes the pending acknowledgement when it is dropped.
+    /// The cache is used to compress the binary attachments once when the packet is broadcasted.
+    pub(crate) fn send_with_ack(
+        &self,
+        mut packet: Packet<'_>,
+        cache: Option<&mut CompressCache>,
+    ) -> (Receiver<AckResult<Value>>, AckGuard) {
+        let (tx, rx) = oneshot::channel();
+
+        let ack = self.ack_counter.fetch_add(1, Ordering::SeqCst) + 1;
+        packet.inner.set_ack_id(ack);
+        match self.send_cached(packet, cache) {
             Ok(()) => {
                 self.ack_message.lock().unwrap().insert(ack, tx);
             }
@@ -670,27 +2027,179 @@ impl<A: Adapter> Socket<A> {
                 tx.send(Err(AckError::Socket(e))).ok();
             }
         }
-        rx
+        (rx, self.ack_guard(ack))
+    }
+
+    fn ack_guard(&self, ack: i64) -> AckGuard {
+        AckGuard::new(
+            Arc::downgrade(&self.ack_message),
+            ack,
+            Arc::downgrade(&self.esocket),
+            self.ns.path.clone(),
+        )
     }
 
     /// Called when the socket is gracefully disconnected from the server or the client
     ///
     /// It maybe also close when the underlying transport is closed or failed.
     pub(crate) fn close(self: Arc<Self>, reason: DisconnectReason) -> Result<(), AdapterError> {
+        self.connected.store(false, Ordering::SeqCst);
         if let Some(handler) = self.disconnect_handler.lock().unwrap().take() {
             handler.call(self.clone(), reason);
         }
+        self.disconnected.send_replace(Some(reason));
+        // Dropping the pending acks senders resolves them with an `AckError::Disconnected`
+        self.ack_message.lock().unwrap().clear();
+
+        let buffer = self.recovery.lock().unwrap().take();
+        if let (Some(buffer), Some(config)) = (buffer, &self.config.connection_recovery) {
+            if RecoveryStore::is_recoverable(reason) {
+                let mut rooms = self.rooms().unwrap_or_default();
+                rooms.retain(|room| room != self.id.as_str());
+                #[allow(unused_mut)]
+                let mut session = Session::new(self.id, rooms, buffer);
+                #[cfg(feature = "extensions")]
+                {
+                    session.extensions = self.extensions.take();
+                }
+                let ttl = config.max_disconnection_duration;
+                self.ns.recovery.save(session, ttl);
+            }
+        }
 
         self.ns.remove_socket(self.id)?;
+        self.ns
+            .send_event(ConnectionEvent::Disconnected(self.id, reason));
+        // The freed slot is given to the first queued connection, if any
+        self.ns.admit_queued();
         Ok(())
     }
 
-    // Receives data from client:
+    #[cfg(test)]
     pub(crate) fn recv(self: Arc<Self>, packet: PacketData<'_>) -> Result<(), Error> {
+        self.recv_with_len(packet, 0)
+    }
+
+    // Receives data from client, with the size of the packet payload and of its binary attachments:
+    pub(crate) fn recv_with_len(
+        self: Arc<Self>,
+        packet: PacketData<'_>,
+        payload_len: usize,
+    ) -> Result<(), Error> {
+        // Acknowledgements are never delayed so that the connect handler can wait for them
+        let packet = match packet {
+            PacketData::EventAck(..) | PacketData::BinaryAck(..) => packet,
+            packet => match self.buffer_pending(packet, payload_len) {
+                Some(packet) => packet,
+                None => return Ok(()),
+            },
+        };
+        self.recv_packet(packet, payload_len)
+    }
+
+    /// Buffers a packet if the connect handler is still running.
+    /// Returns the packet back if it should be handled.
+    fn buffer_pending<'a>(
+        self: &Arc<Self>,
+        packet: PacketData<'a>,
+        payload_len: usize,
+    ) -> Option<PacketData<'a>> {
+        let overflow = {
+            let mut pending = self.pending.lock().unwrap();
+            let Some(pending) = pending.as_mut() else {
+                return Some(packet);
+            };
+            match pending.last() {
+                // Nothing is handled after a disconnect packet
+                Some((PacketData::Disconnect, _)) => return None,
+                _ if pending.len() < self.config.max_pending_events
+                    || matches!(packet, PacketData::Disconnect) =>
+                {
+                    pending.push((packet.into_owned(), payload_len));
+                    return None;
+                }
+                _ => (),
+            }
+            if self.config.pending_overflow == OverflowPolicy::Disconnect {
+                pending.clear();
+            }
+            self.config.pending_overflow
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!("socket {} pending buffer is full: {:?}", self.id, overflow);
+        if overflow == OverflowPolicy::Disconnect {
+            if let Err(_e) = self.clone().disconnect() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("error while disconnecting the socket {}: {}", self.id, _e);
+            }
+        }
+        None
+    }
+
+    /// Marks the connect handler as completed and dispatches the packets received in the meantime.
+    pub(crate) fn set_ready(self: &Arc<Self>) {
+        loop {
+            let packets = {
+                let mut pending = self.pending.lock().unwrap();
+                match pending.as_mut() {
+                    Some(packets) if !packets.is_empty() && self.connected() => {
+                        std::mem::take(packets)
+                    }
+                    _ => {
+                        *pending = None;
+                        self.ready.send_replace(true);
+                        return;
+                    }
+                }
+            };
+            for (packet, payload_len) in packets {
+                if let Err(ref err) = self.clone().recv_packet(packet, payload_len) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("error while processing pending packet: {}", err);
+                    if let Some(reason) = err.into() {
+                        self.esocket.close(reason);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn recv_packet(
+        self: Arc<Self>,
+        packet: PacketData<'_>,
+        payload_len: usize,
+    ) -> Result<(), Error> {
+        let packet = match packet {
+            PacketData::Event(..) | PacketData::BinaryEvent(..) => {
+                match self.buffer_paused(packet, payload_len) {
+                    Some(packet) => packet,
+                    None => return Ok(()),
+                }
+            }
+            packet => packet,
+        };
+        self.dispatch_packet(packet, payload_len)
+    }
+
+    fn dispatch_packet(
+        self: Arc<Self>,
+        packet: PacketData<'_>,
+        payload_len: usize,
+    ) -> Result<(), Error> {
+        if self.closing.load(Ordering::SeqCst)
+            && matches!(packet, PacketData::Event(..) | PacketData::BinaryEvent(..))
+        {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("socket {} is closing, ignoring event", self.id);
+            return Ok(());
+        }
         match packet {
-            PacketData::Event(e, data, ack) => self.recv_event(&e, data, ack),
+            PacketData::Event(e, data, ack) => self.recv_event(&e, data, ack, payload_len),
             PacketData::EventAck(data, ack_id) => self.recv_ack(data, ack_id),
-            PacketData::BinaryEvent(e, packet, ack) => self.recv_bin_event(&e, packet, ack),
+            PacketData::BinaryEvent(e, packet, ack) => {
+                self.recv_bin_event(&e, packet, ack, payload_len)
+            }
             PacketData::BinaryAck(packet, ack) => self.recv_bin_ack(packet, ack),
             PacketData::Disconnect => self
                 .close(DisconnectReason::ClientNSDisconnect)
@@ -699,45 +2208,445 @@ impl<A: Adapter> Socket<A> {
         }
     }
 
-    /// Gets the request info made by the client to connect
+    /// Gets the request info made by the client to connect
+    ///
+    /// It might be used to retrieve the [`http::Extensions`]
+    pub fn req_parts(&self) -> &http::request::Parts {
+        &self.esocket.req_parts
+    }
+
+    /// Gets the [`TransportType`](crate::TransportType) currently used by the client to connect with this [`Socket`]
+    ///
+    /// It is updated when the connection is upgraded from polling to websocket, see [`Socket::upgraded`].
+    /// It can also be accessed as an extractor:
+    /// ```
+    /// # use socketioxide::{SocketIo, TransportType, extract::*};
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef, transport: TransportType| {
+    ///     assert_eq!(socket.transport_type(), transport);
+    /// });
+    pub fn transport_type(&self) -> crate::TransportType {
+        self.esocket.transport_type()
+    }
+
+    /// Returns true if the underlying connection was upgraded from the polling transport to the websocket transport.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("stats", |socket: SocketRef| {
+    ///         println!("transport: {:?}, upgraded: {}", socket.transport_type(), socket.upgraded());
+    ///     });
+    /// });
+    pub fn upgraded(&self) -> bool {
+        self.esocket.is_upgraded()
+    }
+
+    /// ## Registers a callback called when the underlying connection is upgraded from polling to websocket.
+    ///
+    /// It is called once the engine.io upgrade is complete, so [`Socket::transport_type`] then returns
+    /// [`TransportType::Websocket`](crate::TransportType::Websocket).
+    /// There is no downgrade: if the websocket connection fails, the socket is disconnected.
+    ///
+    /// The callback is not called if the connection was already upgraded when the socket connected to the namespace,
+    /// use [`Socket::upgraded`] to check it.
+    ///
+    /// You can register only one callback per socket. If you register multiple callbacks, only the last one will be used.
+    ///
+    /// ### Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on_upgrade(|socket: SocketRef| {
+    ///         println!("Socket {} upgraded to {:?}", socket.id, socket.transport_type());
+    ///     });
+    /// });
+    /// ```
+    pub fn on_upgrade<C>(&self, callback: C)
+    where
+        C: Fn(SocketRef<A>) + Send + Sync + 'static,
+    {
+        self.upgrade_handler
+            .write()
+            .unwrap()
+            .replace(Box::new(callback));
+    }
+
+    /// Calls the [`Socket::on_upgrade`] callback, if any.
+    pub(crate) fn upgrade(self: Arc<Self>) {
+        if let Some(handler) = self.upgrade_handler.read().unwrap().as_ref() {
+            handler(SocketRef::from(self.clone()));
+        }
+    }
+
+    /// Gets a type map shared by all the sockets of the same underlying connection.
+    ///
+    /// Unlike the [`extensions`](Self::extensions) field that is specific to the socket of this namespace,
+    /// the data inserted here can be read from the sockets of all the namespaces multiplexed
+    /// on the same connection, for example to share the authenticated user between them.
+    /// It is dropped when the connection is closed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// #[derive(Clone)]
+    /// struct User(String);
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/chat", |socket: SocketRef| {
+    ///     socket.connection_data().insert(User("alice".into()));
+    /// });
+    /// io.ns("/game", |socket: SocketRef| {
+    ///     if let Some(user) = socket.connection_data().get::<User>() {
+    ///         println!("{} joined the game", user.0);
+    ///     }
+    /// });
+    #[cfg_attr(docsrs, doc(cfg(feature = "extensions")))]
+    #[cfg(feature = "extensions")]
+    pub fn connection_data(&self) -> &Extensions {
+        &self.esocket.data.extensions
+    }
+
+    /// Gets the socket.io [`ProtocolVersion`](crate::ProtocolVersion) used by the client to connect with this [`Socket`]
+    ///
+    /// It can also be accessed as an extractor:
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, ProtocolVersion, extract::*};
+    ///
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef, v: ProtocolVersion| {
+    ///     assert_eq!(socket.protocol(), v);
+    /// });
+    pub fn protocol(&self) -> crate::ProtocolVersion {
+        self.esocket.protocol.into()
+    }
+
+    /// Gets the engine.io [`ProtocolVersion`](engineioxide::ProtocolVersion) negotiated by the client
+    /// for the underlying connection of this [`Socket`].
+    ///
+    /// The engine.io protocol v3 is used by the socket.io protocol v4 (only available with the feature flag `v4`),
+    /// and the engine.io protocol v4 by the socket.io protocol v5.
+    /// The numeric version can be obtained with `socket.engineio_version() as u8`.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// # use engineioxide::ProtocolVersion;
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     if socket.engineio_version() == ProtocolVersion::V3 {
+    ///         println!("legacy client connected with engine.io v3");
+    ///     }
+    /// });
+    /// ```
+    pub fn engineio_version(&self) -> engineioxide::ProtocolVersion {
+        self.esocket.protocol
+    }
+
+    /// Returns true if the socket is still connected to its namespace.
+    ///
+    /// Messages emitted to a disconnected socket are given to the [`OfflineStore`](crate::offline::OfflineStore)
+    /// set with [`SocketIoBuilder::offline_store`](crate::SocketIoBuilder::offline_store), or dropped by default.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     assert!(socket.connected());
+    /// });
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if this socket recovered the session of a previously disconnected socket.
+    ///
+    /// When a session is recovered, the socket has already rejoined its previous rooms
+    /// and received the packets it missed. See the [`recovery`](crate::recovery) module for more details.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     if !socket.recovered() {
+    ///         socket.join("lobby").ok();
+    ///     }
+    /// });
+    pub fn recovered(&self) -> bool {
+        self.previous_id.is_some()
+    }
+
+    /// Returns the id of the previous socket if this socket recovered its session.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     if let Some(sid) = socket.previous_id() {
+    ///         println!("socket {} reconnected as {}", sid, socket.id);
+    ///     }
+    /// });
+    pub fn previous_id(&self) -> Option<Sid> {
+        self.previous_id
+    }
+
+    /// Sets a correlation id for this socket, for example the id of the upstream request of the handshake.
+    ///
+    /// With the `tracing` feature, the events received by the socket are handled in an `event` span
+    /// recording the socket id, the event name and this `trace_id`.
+    /// The span is also entered by the futures of the async handlers.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     let request_id = socket
+    ///         .req_parts()
+    ///         .headers
+    ///         .get("x-request-id")
+    ///         .and_then(|id| id.to_str().ok())
+    ///         .map(|id| id.to_string());
+    ///     if let Some(request_id) = request_id {
+    ///         socket.set_trace_id(request_id);
+    ///     }
+    /// });
+    pub fn set_trace_id(&self, trace_id: impl Into<String>) {
+        self.trace_id.write().unwrap().replace(trace_id.into());
+    }
+
+    /// Returns the correlation id set with [`Socket::set_trace_id`].
+    pub fn trace_id(&self) -> Option<String> {
+        self.trace_id.read().unwrap().clone()
+    }
+
+    /// Creates the span in which an event received by the socket is handled
+    #[cfg(feature = "tracing")]
+    fn event_span(&self, event: &str) -> tracing::Span {
+        let trace_id = self.trace_id.read().unwrap();
+        tracing::debug_span!(
+            "event",
+            sid = %self.id,
+            event,
+            trace_id = trace_id.as_deref()
+        )
+    }
+
+    /// Gets a [`SocketIo`] handle to the server of this socket.
+    ///
+    /// It can be used to reach the other namespaces, for example to broadcast to them.
+    ///
+    /// ## Panics
+    /// If the server was dropped (i.e. there is no [`SocketIo`] instance nor service left) this fn will panic!
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/notifications", |socket: SocketRef| {});
+    /// io.ns("/chat", |socket: SocketRef| {
+    ///     socket.on("message", |socket: SocketRef, Data::<String>(msg)| {
+    ///         let io = socket.server();
+    ///         io.of("/notifications").unwrap().emit("new message", msg).ok();
+    ///     });
+    /// });
+    pub fn server(&self) -> SocketIo<A> {
+        let client = self.ns.client.upgrade().expect("socket.io server dropped");
+        SocketIo(client)
+    }
+
+    /// Gets the paths of all the namespaces the underlying connection is connected to, sorted alphabetically.
+    ///
+    /// A client multiplexes its namespaces over a single connection, each of them with a [`Socket`]
+    /// sharing the same id. It is useful to act on all of them at once,
+    /// for example to disconnect a user from everything.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/admin", |socket: SocketRef| {});
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("logout", |socket: SocketRef| {
+    ///         let io = socket.server();
+    ///         for ns in socket.namespaces() {
+    ///             if let Some(socket) = io.of(ns.as_str()).ok().and_then(|op| op.get_socket(socket.id)) {
+    ///                 socket.disconnect().ok();
+    ///             }
+    ///         }
+    ///     });
+    /// });
+    pub fn namespaces(&self) -> Vec<String> {
+        match self.ns.client.upgrade() {
+            Some(client) => client.socket_namespaces(self.id),
+            None => vec![self.ns.path.to_string()],
+        }
+    }
+
+    /// Gets the number of packets queued for this socket and not yet flushed to the transport.
+    ///
+    /// Each binary payload is counted as a separate packet. The queue is shared by all the namespaces
+    /// of the underlying connection and is bounded by the [`max_buffer_size`](crate::SocketIoBuilder::max_buffer_size).
+    /// It can be used to detect and disconnect slow clients before the queue is full.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("update", |socket: SocketRef| {
+    ///         if socket.send_buffer_len() > 100 {
+    ///             socket.disconnect().ok();
+    ///         }
+    ///     });
+    /// });
+    pub fn send_buffer_len(&self) -> usize {
+        self.esocket.buffered_packets()
+    }
+
+    /// Gets the number of acknowledgements requested to the client and not yet received.
+    ///
+    /// An acknowledgement stops being pending when it is received, when it times out,
+    /// when its [`AckStream`] is dropped or when the socket is disconnected.
+    /// It can be used to monitor that the acknowledgements don't accumulate.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("stats", |socket: SocketRef| {
+    ///         println!("{} pending acks", socket.pending_acks());
+    ///     });
+    /// });
+    pub fn pending_acks(&self) -> usize {
+        self.ack_message.lock().unwrap().len()
+    }
+
+    /// Gets the number of packets emitted to this socket that were dropped or could not be buffered
+    /// because the buffer of the connection was full.
+    ///
+    /// See [`OutboundPolicy`] for the behavior applied when the buffer is full.
+    ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
+    /// let (_, io) = SocketIo::new_svc();
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("test", |socket: SocketRef| {
+    ///         if socket.dropped_packets() > 100 {
+    ///             socket.disconnect().ok();
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn dropped_packets(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// **Advanced**: sends an already encoded socket.io packet to the client, without any check.
     ///
-    /// It might be used to retrieve the [`http::Extensions`]
-    pub fn req_parts(&self) -> &http::request::Parts {
-        &self.esocket.req_parts
-    }
-
-    /// Gets the [`TransportType`](crate::TransportType) used by the client to connect with this [`Socket`]
+    /// The frame is written as is in the payload of an engine.io `message` packet,
+    /// so it must be a complete socket.io packet in the
+    /// [socket.io protocol](https://socket.io/docs/v4/socket-io-protocol/) text format,
+    /// including the namespace of the socket if it is not the main one (e.g. `2/admin,["event","data"]`).
+    /// The binary payloads announced by a `5` / `6` packet must then be sent with [`Socket::send_raw_bin`].
     ///
-    /// It can also be accessed as an extractor:
-    /// ```
-    /// # use socketioxide::{SocketIo, TransportType, extract::*};
+    /// It is meant to forward frames verbatim, for example in a relay between two servers.
+    /// The frame bypasses everything that is applied to the packets emitted with [`Socket::emit`]:
+    /// outbound middlewares, event prefix, connection state recovery and offline store.
+    /// Sending a malformed frame will most likely make the client close the connection.
     ///
+    /// ## Example
+    /// ```
+    /// # use socketioxide::{SocketIo, extract::*};
     /// let (_, io) = SocketIo::new_svc();
-    /// io.ns("/", |socket: SocketRef, transport: TransportType| {
-    ///     assert_eq!(socket.transport_type(), transport);
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.send_raw(r#"2["hello","world"]"#.to_string()).ok();
     /// });
-    pub fn transport_type(&self) -> crate::TransportType {
-        self.esocket.transport_type()
+    /// ```
+    pub fn send_raw(&self, frame: String) -> Result<(), SocketError<()>> {
+        match self.reserve(1)? {
+            Permits::Reserved(mut permits) => permits.next().unwrap().emit(frame),
+            // Raw frames are not queued with the packets waiting for room in the buffer
+            Permits::Queued(_) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Permits::Dropped => {}
+        }
+        Ok(())
     }
 
-    /// Gets the socket.io [`ProtocolVersion`](crate::ProtocolVersion) used by the client to connect with this [`Socket`]
+    /// **Advanced**: sends a binary payload to the client, without any check.
+    ///
+    /// The bytes are written as is in an engine.io binary packet.
+    /// It must follow a `5` / `6` packet sent with [`Socket::send_raw`] that announces the number of attachments.
+    /// See [`Socket::send_raw`] for more details.
+    pub fn send_raw_bin(&self, bytes: Vec<u8>) -> Result<(), SocketError<()>> {
+        match self.reserve(1)? {
+            Permits::Reserved(mut permits) => permits.next().unwrap().emit_binary(bytes),
+            Permits::Queued(_) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Permits::Dropped => {}
+        }
+        Ok(())
+    }
+
+    /// Gets the round-trip latency measured during the last engine.io heartbeat.
+    ///
+    /// It is updated every [`ping_interval`](crate::SocketIoBuilder::ping_interval).
+    /// Returns `None` until the first Pong is received or if the client uses the v3 engine.io protocol.
     ///
-    /// It can also be accessed as an extractor:
     /// ## Example
     /// ```
-    /// # use socketioxide::{SocketIo, ProtocolVersion, extract::*};
-    ///
+    /// # use socketioxide::{SocketIo, extract::*};
     /// let (_, io) = SocketIo::new_svc();
-    /// io.ns("/", |socket: SocketRef, v: ProtocolVersion| {
-    ///     assert_eq!(socket.protocol(), v);
+    /// io.ns("/", |socket: SocketRef| {
+    ///     socket.on("latency", |socket: SocketRef| {
+    ///         if let Some(latency) = socket.latency() {
+    ///             println!("latency: {}ms", latency.as_millis());
+    ///         }
+    ///     });
     /// });
-    pub fn protocol(&self) -> crate::ProtocolVersion {
-        self.esocket.protocol.into()
+    pub fn latency(&self) -> Option<Duration> {
+        self.esocket.latency()
     }
 
-    fn recv_event(self: Arc<Self>, e: &str, data: Value, ack: Option<i64>) -> Result<(), Error> {
-        if let Some(handler) = self.message_handlers.read().unwrap().get(e) {
-            handler.call(self.clone(), data, vec![], ack);
+    fn recv_event(
+        self: Arc<Self>,
+        e: &str,
+        mut data: Value,
+        ack: Option<i64>,
+        payload_len: usize,
+    ) -> Result<(), Error> {
+        self.touch();
+        let Some(e) = self.ns.strip_event_prefix(e) else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("ignoring event {} without the event prefix", e);
+            return Ok(());
+        };
+        // The handler is cloned so that the lock is not held while it is called,
+        // allowing it or the unhandled callback to register new handlers.
+        let handler = self.message_handlers.read().unwrap().get(e).cloned();
+        if let Some(handler) = handler {
+            #[cfg(feature = "tracing")]
+            let _span = self.event_span(e).entered();
+            if self.payload_too_large(e, payload_len) {
+                self.reject_payload(ack);
+                return Ok(());
+            }
+            let meta = MessageMeta::new(&mut data, BinaryMeta::new(0));
+            handler.call(self.clone(), data, vec![], ack, meta);
+        } else {
+            self.recv_unhandled(e, &data);
         }
         Ok(())
     }
@@ -745,36 +2654,148 @@ impl<A: Adapter> Socket<A> {
     fn recv_bin_event(
         self: Arc<Self>,
         e: &str,
-        packet: BinaryPacket,
+        mut packet: BinaryPacket,
         ack: Option<i64>,
+        payload_len: usize,
     ) -> Result<(), Error> {
-        if let Some(handler) = self.message_handlers.read().unwrap().get(e) {
-            handler.call(self.clone(), packet.data, packet.bin, ack);
+        self.touch();
+        let Some(e) = self.ns.strip_event_prefix(e) else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("ignoring event {} without the event prefix", e);
+            return Ok(());
+        };
+        let handler = self.message_handlers.read().unwrap().get(e).cloned();
+        if let Some(handler) = handler {
+            #[cfg(feature = "tracing")]
+            let _span = self.event_span(e).entered();
+            if self.payload_too_large(e, payload_len) {
+                self.reject_payload(ack);
+                return Ok(());
+            }
+            let binary = packet.meta();
+            let meta = MessageMeta::new(&mut packet.data, binary);
+            handler.call(self.clone(), packet.data, packet.bin, ack, meta);
+        } else {
+            self.recv_unhandled(e, &packet.data);
         }
         Ok(())
     }
 
+    /// Updates the last activity time of the socket
+    fn touch(&self) {
+        if self.config.idle_timeout.is_some() {
+            *self.last_activity.lock().unwrap() = Instant::now();
+        }
+    }
+
+    /// Spawns a task disconnecting the socket when it doesn't send any event during the
+    /// [`idle_timeout`](SocketIoConfig::idle_timeout). The task ends when the socket is disconnected.
+    pub(crate) fn spawn_idle_timeout_task(self: &Arc<Self>) {
+        let Some(timeout) = self.config.idle_timeout else {
+            return;
+        };
+        let socket = Arc::downgrade(self);
+        let disconnected = self.on_disconnect_future();
+        let idle = async move {
+            loop {
+                let Some(deadline) = socket
+                    .upgrade()
+                    .map(|s| *s.last_activity.lock().unwrap() + timeout)
+                else {
+                    return;
+                };
+                tokio::time::sleep_until(deadline).await;
+                let Some(socket) = socket.upgrade() else {
+                    return;
+                };
+                if socket.last_activity.lock().unwrap().elapsed() >= timeout {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("socket {} idle timeout, disconnecting", socket.id);
+                    socket.disconnect_with(DisconnectReason::IdleTimeout).ok();
+                    return;
+                }
+            }
+        };
+        tokio::spawn(async move {
+            futures::pin_mut!(idle, disconnected);
+            futures::future::select(idle, disconnected).await;
+        });
+    }
+
+    /// Returns true if the payload exceeds the limit set with [`Socket::on_with_max_payload`] for this event.
+    /// The size of the payload is the length of the received JSON payload and binary attachments.
+    fn payload_too_large(&self, e: &str, payload_len: usize) -> bool {
+        let Some(&max) = self.payload_limits.read().unwrap().get(e) else {
+            return false;
+        };
+        if payload_len > max {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                "rejecting event {} with a payload of {} bytes (max {})",
+                e,
+                payload_len,
+                max
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    fn reject_payload(self: &Arc<Self>, ack: Option<i64>) {
+        if let Some(ack_id) = ack {
+            Err::<(), _>("payload too large").into_ack(self.clone(), ack_id);
+        }
+    }
+
+    fn recv_unhandled(&self, e: &str, data: &Value) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("no handler registered for event {}", e);
+        if let Some(handler) = self.unhandled_handler.read().unwrap().as_ref() {
+            handler(e, data);
+        }
+    }
+
     fn recv_ack(self: Arc<Self>, data: Value, ack: i64) -> Result<(), Error> {
-        if let Some(tx) = self.ack_message.lock().unwrap().remove(&ack) {
+        let tx = self.ack_message.lock().unwrap().remove(&ack);
+        if let Some(tx) = tx {
             let res = AckResponse {
                 data,
                 binary: vec![],
             };
             tx.send(Ok(res)).ok();
+        } else {
+            self.recv_stray_ack(ack, &data, &[]);
         }
         Ok(())
     }
 
     fn recv_bin_ack(self: Arc<Self>, packet: BinaryPacket, ack: i64) -> Result<(), Error> {
-        if let Some(tx) = self.ack_message.lock().unwrap().remove(&ack) {
+        let tx = self.ack_message.lock().unwrap().remove(&ack);
+        if let Some(tx) = tx {
             let res = AckResponse {
                 data: packet.data,
                 binary: packet.bin,
             };
             tx.send(Ok(res)).ok();
+        } else {
+            self.recv_stray_ack(ack, &packet.data, &packet.bin);
         }
         Ok(())
     }
+
+    /// Calls the [`Socket::on_stray_ack`] callback, if any, with an acknowledgement that has no pending request.
+    fn recv_stray_ack(&self, ack: i64, data: &Value, bin: &[Vec<u8>]) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "socket {} received an ack with an unknown id: {}",
+            self.id,
+            ack
+        );
+        if let Some(handler) = self.stray_ack_handler.read().unwrap().as_ref() {
+            handler(ack, data, bin);
+        }
+    }
 }
 
 impl<A: Adapter> Debug for Socket<A> {
@@ -792,17 +2813,25 @@ impl<A: Adapter> PartialEq for Socket<A> {
         self.id == other.id
     }
 }
+impl<A: Adapter> Eq for Socket<A> {}
+impl<A: Adapter> std::hash::Hash for Socket<A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
 
 #[cfg(test)]
 impl<A: Adapter> Socket<A> {
     pub fn new_dummy(sid: Sid, ns: Arc<Namespace<A>>) -> Socket<A> {
         let close_fn = Box::new(move |_, _| ());
-        Socket::new(
+        let socket = Socket::new(
             sid,
             ns,
             engineioxide::Socket::new_dummy(sid, close_fn).into(),
             Arc::new(SocketIoConfig::default()),
-        )
+        );
+        *socket.pending.lock().unwrap() = None;
+        socket
     }
 }
 
@@ -828,4 +2857,767 @@ mod test {
             Err(SendError::Socket(SocketError::InternalChannelFull(_)))
         ));
     }
+
+    #[tokio::test]
+    async fn ack_disconnected_on_close() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+
+        let ack = socket
+            .emit_with_ack::<_, Value>("test", Value::Null)
+            .unwrap();
+        socket
+            .clone()
+            .close(DisconnectReason::TransportClose)
+            .unwrap();
+        assert!(matches!(ack.await, Err(AckError::Disconnected)));
+    }
+
+    #[tokio::test]
+    async fn on_disconnect_future() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+
+        let disconnected = tokio::spawn(socket.on_disconnect_future());
+        tokio::task::yield_now().await;
+        assert!(!disconnected.is_finished());
+        socket
+            .clone()
+            .close(DisconnectReason::HeartbeatTimeout)
+            .unwrap();
+        assert_eq!(
+            disconnected.await.unwrap(),
+            DisconnectReason::HeartbeatTimeout
+        );
+        // Already disconnected
+        assert_eq!(
+            socket.on_disconnect_future().await,
+            DisconnectReason::HeartbeatTimeout
+        );
+
+        let socket: Arc<Socket> = Socket::new_dummy(sid, Namespace::new_dummy([sid])).into();
+        let disconnected = socket.on_disconnect_future();
+        drop(socket);
+        assert_eq!(disconnected.await, DisconnectReason::ClosingServer);
+    }
+
+    #[tokio::test]
+    async fn pending_ack_removed_on_drop() {
+        let socket = create_socket_with_ack_timeout(Duration::from_millis(10));
+
+        let ack = socket
+            .emit_with_ack::<_, Value>("test", Value::Null)
+            .unwrap();
+        assert_eq!(socket.ack_message.lock().unwrap().len(), 1);
+        drop(ack);
+        assert!(socket.ack_message.lock().unwrap().is_empty());
+
+        // The slot is also freed when the acknowledgement times out
+        let ack = socket
+            .emit_with_ack::<_, Value>("test", Value::Null)
+            .unwrap();
+        assert!(matches!(ack.await, Err(AckError::Timeout)));
+        assert!(socket.ack_message.lock().unwrap().is_empty());
+
+        let packet = Packet::event("/", "test", Value::Null);
+        let stream = AckInnerStream::broadcast(packet, vec![socket.clone().into()], None);
+        assert_eq!(socket.ack_message.lock().unwrap().len(), 1);
+        drop(stream);
+        assert!(socket.ack_message.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pending_acks() {
+        let socket = create_socket_with_ack_timeout(Duration::from_millis(10));
+        let acks: Vec<_> = (0..3)
+            .map(|_| {
+                socket
+                    .emit_with_ack::<_, Value>("test", Value::Null)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(socket.pending_acks(), 3);
+
+        socket
+            .clone()
+            .recv(Packet::ack("/", Value::Null, 1).inner)
+            .unwrap();
+        assert_eq!(socket.pending_acks(), 2);
+        for ack in acks {
+            ack.await.ok();
+        }
+        assert_eq!(socket.pending_acks(), 0);
+    }
+
+    /// Creates a socket connected to a namespace, with a transport that never consumes its buffer
+    fn create_stalled_socket(outbound_policy: OutboundPolicy) -> Arc<Socket> {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([]);
+        let config = SocketIoConfig {
+            outbound_policy,
+            ..Default::default()
+        };
+        let esocket = engineioxide::Socket::new_dummy(sid, Box::new(|_, _| ())).into();
+        ns.clone()
+            .connect(sid, esocket, None, config.into())
+            .unwrap();
+        let socket = ns.get_socket(sid).unwrap();
+        while socket.emit("fill", ()).is_ok() && socket.dropped_packets() == 0 {}
+        socket
+    }
+
+    #[tokio::test]
+    async fn outbound_policy_error() {
+        let socket = create_stalled_socket(OutboundPolicy::Error);
+        assert_eq!(socket.dropped_packets(), 1);
+        assert!(matches!(
+            socket.emit("test", ()),
+            Err(SendError::Socket(SocketError::InternalChannelFull(_)))
+        ));
+        assert_eq!(socket.dropped_packets(), 2);
+        assert!(socket.connected());
+    }
+
+    #[tokio::test]
+    async fn outbound_policy_drop_newest() {
+        let socket = create_stalled_socket(OutboundPolicy::DropNewest);
+        let len = socket.send_buffer_len();
+        socket.emit("test", ()).unwrap();
+        let _ack = socket.emit_with_ack::<_, Value>("test", ()).unwrap();
+        assert_eq!(socket.dropped_packets(), 3);
+        assert_eq!(socket.send_buffer_len(), len);
+        assert!(socket.connected());
+    }
+
+    #[tokio::test]
+    async fn outbound_policy_drop_oldest() {
+        let socket = create_stalled_socket(OutboundPolicy::DropOldest);
+        // The filling emit made room for itself by dropping the oldest queued event
+        assert_eq!(socket.dropped_packets(), 1);
+        let len = socket.send_buffer_len();
+        let max = socket.config.engine_config.max_buffer_size;
+
+        // The acknowledgements are never dropped
+        socket.send(Packet::ack("/", Value::Null, 1)).unwrap();
+        assert_eq!(socket.dropped_packets(), 2);
+        for _ in 0..max {
+            socket.bin(vec![vec![1]]).emit("test", ()).unwrap();
+        }
+        assert_eq!(socket.dropped_packets(), 2 + max as u64);
+        {
+            let overflow = socket.overflow.lock().unwrap();
+            let queue = overflow.as_ref().unwrap();
+            assert_eq!(queue.len(), max);
+            assert!(matches!(queue[0].inner, PacketData::EventAck(_, 1)));
+            assert!(queue
+                .iter()
+                .skip(1)
+                .all(|p| matches!(p.inner, PacketData::BinaryEvent(..))));
+        }
+        // The packets already buffered by the connection are never dropped
+        assert_eq!(socket.send_buffer_len(), len);
+        assert!(socket.connected());
+    }
+
+    #[tokio::test]
+    async fn outbound_policy_disconnect() {
+        let socket = create_stalled_socket(OutboundPolicy::Disconnect);
+        assert_eq!(socket.dropped_packets(), 1);
+        assert!(!socket.connected());
+        assert!(matches!(
+            socket.emit("test", ()),
+            Err(SendError::Socket(SocketError::Closed(_)))
+        ));
+
+        // The socket is closed from another task
+        let reason = tokio::time::timeout(Duration::from_millis(10), socket.on_disconnect_future())
+            .await
+            .unwrap();
+        assert_eq!(reason, DisconnectReason::ServerNSDisconnect);
+        assert!(socket.ns.get_socket(socket.id).is_err());
+    }
+
+    fn create_socket_with_ack_timeout(ack_timeout: Duration) -> Arc<Socket> {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let close_fn = Box::new(move |_, _| ());
+        let config = SocketIoConfig {
+            ack_timeout,
+            ..Default::default()
+        };
+        let esocket = engineioxide::Socket::new_dummy(sid, close_fn).into();
+        let socket = Socket::new(sid, ns, esocket, config.into());
+        *socket.pending.lock().unwrap() = None;
+        socket.into()
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack_retry() {
+        let socket = create_socket_with_ack_timeout(Duration::from_millis(50));
+        let backoff = Duration::from_millis(10);
+        let s = socket.clone();
+        let res = tokio::spawn(async move {
+            s.emit_with_ack_retry::<_, String>("test", Value::Null, 2, backoff)
+                .await
+        });
+
+        // The first attempt times out and the second one is acknowledged
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        let ack = PacketData::EventAck(Value::String("ack".into()), 2);
+        socket.clone().recv(ack).unwrap();
+        assert_eq!(res.await.unwrap().unwrap().data, "ack");
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack_retry_timeout() {
+        let socket = create_socket_with_ack_timeout(Duration::from_millis(5));
+        let res = socket
+            .emit_with_ack_retry::<_, Value>("test", Value::Null, 2, Duration::from_millis(1))
+            .await;
+        assert!(matches!(res, Err(AckError::Timeout)));
+        assert_eq!(socket.ack_counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn on_ack_sent() {
+        use crate::extract::AckSender;
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let acks = Arc::new(Mutex::new(Vec::new()));
+        let acks_clone = acks.clone();
+        socket.on_ack_sent(move |id, data, bin| {
+            acks_clone
+                .lock()
+                .unwrap()
+                .push((id, data.clone(), bin.len()));
+        });
+
+        AckSender::new(socket.clone(), Some(1)).send("ok").unwrap();
+        AckSender::new(socket.clone(), Some(2))
+            .bin(vec![vec![1, 2, 3]])
+            .send_binary()
+            .unwrap();
+        // Events are not observed
+        socket.emit("test", "data").unwrap();
+
+        let placeholder = serde_json::json!([{ "_placeholder": true, "num": 0 }]);
+        assert_eq!(
+            *acks.lock().unwrap(),
+            [(1, Value::from("ok"), 0), (2, placeholder, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn on_stray_ack() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let acks = Arc::new(Mutex::new(Vec::new()));
+        let acks_clone = acks.clone();
+        socket.on_stray_ack(move |id, data, bin| {
+            acks_clone
+                .lock()
+                .unwrap()
+                .push((id, data.clone(), bin.len()));
+        });
+
+        let _stream = socket.emit_with_ack::<_, Value>("test", ()).unwrap();
+        let id = socket.ack_counter.load(Ordering::SeqCst);
+        socket.clone().recv_ack("first".into(), id).unwrap();
+        assert!(acks.lock().unwrap().is_empty());
+
+        // A second ack for the same id and an ack for an unknown id are stray
+        socket.clone().recv_ack("second".into(), id).unwrap();
+        let mut packet = BinaryPacket::incoming(Value::Null);
+        packet.bin = vec![vec![1]];
+        socket.clone().recv_bin_ack(packet, 42).unwrap();
+        assert_eq!(
+            *acks.lock().unwrap(),
+            [(id, Value::from("second"), 0), (42, Value::Null, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn to_my_rooms() {
+        let [sid, peer1, peer2, other] = [Sid::new(), Sid::new(), Sid::new(), Sid::new()];
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid, peer1, peer2, other]);
+        for id in [sid, peer1, peer2, other] {
+            ns.adapter.add_all(id, id.to_string()).unwrap();
+        }
+        ns.adapter.add_all(sid, ["room1", "room2"]).unwrap();
+        ns.adapter.add_all(peer1, ["room1", "room2"]).unwrap();
+        ns.adapter.add_all(peer2, "room2").unwrap();
+        ns.adapter.add_all(other, "room3").unwrap();
+
+        let socket = ns.get_socket(sid).unwrap();
+        socket.to_my_rooms().unwrap().emit("typing", ()).unwrap();
+        let len = |id| ns.get_socket(id).unwrap().send_buffer_len();
+        assert_eq!(len(sid), 0);
+        assert_eq!(len(peer1), 1);
+        assert_eq!(len(peer2), 1);
+        assert_eq!(len(other), 0);
+
+        // Alone in its own room, nobody is selected
+        let socket = ns.get_socket(other).unwrap();
+        ns.adapter.del(other, "room3").unwrap();
+        socket.to_my_rooms().unwrap().emit("typing", ()).unwrap();
+        assert_eq!([len(sid), len(peer1), len(peer2)], [0, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn emit_with_ack_cb() {
+        let sids = [Sid::new(), Sid::new(), Sid::new()];
+        let ns = Namespace::<LocalAdapter>::new_dummy(sids);
+        for sid in sids {
+            ns.adapter.add_all(sid, sid).unwrap();
+        }
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        ns.get_socket(sids[0])
+            .unwrap()
+            .within(sids.map(|sid| sid.to_string()))
+            .timeout(Duration::from_millis(50))
+            .emit_with_ack_cb::<u32>("test", (), move |acks| tx.send(acks).unwrap())
+            .unwrap();
+
+        for (i, sid) in sids.iter().take(2).enumerate() {
+            let socket = ns.get_socket(*sid).unwrap();
+            socket.recv(Packet::ack("/", i.into(), 1).inner).unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+
+        let acks = rx.recv().await.unwrap();
+        assert_eq!(acks.len(), 3);
+        let ack = |sid| &acks.iter().find(|(id, _)| *id == sid).unwrap().1;
+        assert_eq!(ack(sids[0]).as_ref().unwrap().data, 0);
+        assert_eq!(ack(sids[1]).as_ref().unwrap().data, 1);
+        assert!(matches!(ack(sids[2]), Err(AckError::Timeout)));
+        // The callback is only called once
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn typed_rooms() {
+        use crate::operators::ToRoom;
+        enum Channel {
+            Lobby,
+            Game(u32),
+        }
+        impl ToRoom for Channel {
+            fn to_room(&self) -> Room {
+                match self {
+                    Channel::Lobby => "lobby".into(),
+                    Channel::Game(id) => format!("game:{id}").into(),
+                }
+            }
+        }
+
+        let [sid, peer] = [Sid::new(), Sid::new()];
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid, peer]);
+        let socket = ns.get_socket(sid).unwrap();
+        socket.join(Channel::Lobby).unwrap();
+        socket.join(Channel::Game(1)).unwrap();
+        let mut rooms = socket.rooms().unwrap();
+        rooms.sort();
+        assert_eq!(rooms, ["game:1", "lobby"]);
+
+        ns.get_socket(peer).unwrap().join(Channel::Game(1)).unwrap();
+        socket.to(Channel::Game(1)).emit("test", ()).unwrap();
+        socket
+            .within(Channel::Lobby)
+            .except(Channel::Game(1))
+            .emit("test", ())
+            .unwrap();
+        let len = |id| ns.get_socket(id).unwrap().send_buffer_len();
+        assert_eq!([len(sid), len(peer)], [0, 1]);
+
+        socket.leave(Channel::Lobby).unwrap();
+        assert_eq!(socket.rooms().unwrap(), ["game:1"]);
+    }
+
+    #[tokio::test]
+    async fn emit_after_disconnect() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        socket.clone().disconnect().unwrap();
+        let len = socket.send_buffer_len();
+
+        let res = socket.emit("test", "data");
+        assert!(matches!(
+            res,
+            Err(SendError::Socket(SocketError::Closed("data")))
+        ));
+        let res = socket.emit_bin("test", "data", vec![vec![1]]);
+        assert!(matches!(
+            res,
+            Err(SendError::Socket(SocketError::Closed("data")))
+        ));
+        let res = socket.emit_with_ack::<_, Value>("test", "data");
+        assert!(matches!(
+            res,
+            Err(SendError::Socket(SocketError::Closed("data")))
+        ));
+        let res = socket.send_raw(r#"2["test"]"#.to_string());
+        assert!(matches!(res, Err(SocketError::Closed(()))));
+        assert_eq!(socket.send_buffer_len(), len);
+    }
+
+    #[tokio::test]
+    async fn drain_and_close_ignore_events() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let (tx, mut rx) = mpsc::channel(1);
+        socket.on("test", move || {
+            tx.try_send(()).unwrap();
+        });
+        // The dummy transport never takes the queued packets
+        socket
+            .send(Packet::event("/", "test", Value::Null))
+            .unwrap();
+
+        let drain = tokio::spawn(socket.clone().drain_and_close(Duration::from_millis(50)));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let event = PacketData::Event("test".into(), Value::Null, None);
+        socket.clone().recv(event).unwrap();
+
+        drain.await.unwrap().unwrap();
+        assert!(!socket.connected());
+        assert!(rx.try_recv().is_err());
+    }
+
+    // Sockets are only hashed by their immutable id
+    #[allow(clippy::mutable_key_type)]
+    #[tokio::test]
+    async fn socket_set_by_sid() {
+        use crate::extract::SocketRef;
+        use std::collections::HashSet;
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns.clone()).into();
+        let other: Arc<Socket> = Socket::new_dummy(Sid::new(), ns).into();
+
+        let mut set = HashSet::new();
+        assert!(set.insert(SocketRef::from(socket.clone())));
+        assert!(!set.insert(SocketRef::from(socket.clone())));
+        assert!(set.insert(SocketRef::from(other)));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&SocketRef::from(socket)));
+    }
+
+    #[tokio::test]
+    async fn send_buffer_len() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        assert_eq!(socket.send_buffer_len(), 0);
+
+        // The dummy transport never takes the queued packets
+        socket.emit("test", "data").unwrap();
+        assert_eq!(socket.send_buffer_len(), 1);
+        socket
+            .bin(vec![vec![1, 2], vec![3]])
+            .emit("test", "data")
+            .unwrap();
+        assert_eq!(socket.send_buffer_len(), 4);
+    }
+
+    #[tokio::test]
+    async fn on_unhandled() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        socket.on("handled", || {});
+        socket.on_unhandled(move |event, data| tx.send((event.to_string(), data.clone())).unwrap());
+
+        socket
+            .clone()
+            .recv(PacketData::Event("handled".into(), Value::Null, None))
+            .unwrap();
+        socket
+            .clone()
+            .recv(PacketData::Event(
+                "unknown".into(),
+                serde_json::json!([1]),
+                None,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            ("unknown".into(), serde_json::json!([1]))
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn on_unhandled_registers_handler() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let weak = Arc::downgrade(&socket);
+        socket.on_unhandled(move |event, _| {
+            let tx = tx.clone();
+            weak.upgrade()
+                .unwrap()
+                .on(event.to_string(), move || tx.send(()).unwrap());
+        });
+
+        socket
+            .clone()
+            .recv(PacketData::Event("late".into(), Value::Null, None))
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        socket
+            .clone()
+            .recv(PacketData::Event("late".into(), Value::Null, None))
+            .unwrap();
+        rx.try_recv().unwrap();
+    }
+
+    #[tokio::test]
+    async fn on_events() {
+        use crate::extract::Data;
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        socket.on_events(["chat", "message"], move |Data::<u32>(n)| {
+            tx.send(n).unwrap()
+        });
+        for (i, e) in ["chat", "message", "unknown"].into_iter().enumerate() {
+            socket
+                .clone()
+                .recv(PacketData::Event(e.into(), serde_json::json!(i), None))
+                .unwrap();
+        }
+        assert_eq!(rx.try_recv().unwrap(), 0);
+        assert_eq!(rx.try_recv().unwrap(), 1);
+        assert!(rx.try_recv().is_err());
+
+        // Both events share the same handler
+        let handlers = socket.message_handlers.read().unwrap();
+        assert!(Arc::ptr_eq(&handlers["chat"], &handlers["message"]));
+    }
+
+    #[tokio::test]
+    async fn pause_resume() {
+        use crate::extract::Data;
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        socket.on("test", move |Data::<u32>(n)| tx.send(n).unwrap());
+        let recv = |n: u32| {
+            socket
+                .clone()
+                .recv(PacketData::Event("test".into(), n.into(), None))
+                .unwrap()
+        };
+
+        socket.pause(2, OverflowPolicy::Drop);
+        assert!(socket.is_paused());
+        (0..3).for_each(recv);
+        assert!(rx.try_recv().is_err());
+
+        // The buffered events are dispatched in order, the overflowing one is dropped
+        socket.clone().resume();
+        assert!(!socket.is_paused());
+        recv(3);
+        assert_eq!(rx.try_recv().unwrap(), 0);
+        assert_eq!(rx.try_recv().unwrap(), 1);
+        assert_eq!(rx.try_recv().unwrap(), 3);
+        assert!(rx.try_recv().is_err());
+
+        socket.pause(1, OverflowPolicy::Disconnect);
+        recv(4);
+        assert!(socket.connected());
+        recv(5);
+        assert!(!socket.connected());
+        socket.clone().resume();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn pending_buffer_overflow() {
+        use crate::extract::Data;
+        let create_socket = |overflow| {
+            let sid = Sid::new();
+            let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+            let config = SocketIoConfig {
+                max_pending_events: 2,
+                pending_overflow: overflow,
+                ..Default::default()
+            };
+            let esocket = engineioxide::Socket::new_dummy(sid, Box::new(|_, _| ())).into();
+            Arc::new(Socket::new(sid, ns, esocket, config.into()))
+        };
+        let recv = |socket: &Arc<Socket>, n: u32| {
+            socket
+                .clone()
+                .recv(PacketData::Event("test".into(), n.into(), None))
+                .unwrap()
+        };
+
+        // The overflowing event is dropped
+        let socket = create_socket(OverflowPolicy::Drop);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        socket.on("test", move |Data::<u32>(n)| tx.send(n).unwrap());
+        (0..3).for_each(|n| recv(&socket, n));
+        assert!(rx.try_recv().is_err());
+        socket.set_ready();
+        assert_eq!(rx.try_recv().unwrap(), 0);
+        assert_eq!(rx.try_recv().unwrap(), 1);
+        assert!(rx.try_recv().is_err());
+
+        // The overflowing event disconnects the socket
+        let socket = create_socket(OverflowPolicy::Disconnect);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        socket.on("test", move |Data::<u32>(n)| tx.send(n).unwrap());
+        (0..2).for_each(|n| recv(&socket, n));
+        assert!(socket.connected());
+        recv(&socket, 2);
+        assert!(!socket.connected());
+        socket.set_ready();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn trace_id_span() {
+        use std::io::Write;
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+        impl Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        socket.on("test", || tracing::info!("handled"));
+        assert_eq!(socket.trace_id(), None);
+        socket.set_trace_id("req-1");
+        assert_eq!(socket.trace_id().as_deref(), Some("req-1"));
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            socket
+                .clone()
+                .recv(PacketData::Event("test".into(), Value::Null, None))
+                .unwrap();
+        });
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = logs.lines().find(|l| l.contains("handled")).unwrap();
+        assert!(line.contains(&format!("sid={sid}")), "{line}");
+        assert!(line.contains(r#"event="test""#), "{line}");
+        assert!(line.contains(r#"trace_id="req-1""#), "{line}");
+    }
+
+    #[tokio::test]
+    async fn in_room() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket = ns.get_socket(sid).unwrap();
+        assert!(!socket.in_room("room1").unwrap());
+        socket.join("room1").unwrap();
+        assert!(socket.in_room("room1").unwrap());
+        assert!(!socket.in_room("unknown").unwrap());
+
+        socket
+            .clone()
+            .close(DisconnectReason::TransportClose)
+            .unwrap();
+        assert!(!socket.in_room("room1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn engineio_version() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket = ns.get_socket(sid).unwrap();
+        assert_eq!(socket.engineio_version(), engineioxide::ProtocolVersion::V4);
+        assert_eq!(socket.engineio_version() as u8, 4);
+        assert_eq!(socket.protocol(), ProtocolVersion::V5);
+    }
+
+    #[tokio::test]
+    async fn adapter() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        socket.join("room1").unwrap();
+        assert_eq!(socket.adapter().sockets("room1").unwrap(), [sid]);
+    }
+
+    #[tokio::test]
+    async fn join_with_ack() {
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns).into();
+        socket.join_with_ack(["room1", "room2"]).await.unwrap();
+        let mut rooms = socket.rooms().unwrap();
+        rooms.sort();
+        assert_eq!(rooms, ["room1", "room2"]);
+    }
+
+    #[tokio::test]
+    async fn unwrap_single_element() {
+        use crate::extract::Data;
+        let sid = Sid::new();
+        let ns = Namespace::<LocalAdapter>::new_dummy([sid]);
+        let socket: Arc<Socket> = Socket::new_dummy(sid, ns.clone()).into();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tx1 = tx.clone();
+        socket.on("args", move |Data::<Vec<u32>>(args)| {
+            tx1.send(args).unwrap()
+        });
+        socket.on("arg", move |Data::<u32>(arg)| tx.send(vec![arg]).unwrap());
+        let event = |e: &'static str, data| PacketData::Event(e.into(), data, None);
+
+        // Single element arrays are unwrapped by default
+        socket
+            .clone()
+            .recv(event("args", serde_json::json!([1])))
+            .unwrap();
+        socket
+            .clone()
+            .recv(event("arg", serde_json::json!([1])))
+            .unwrap();
+        assert_eq!(rx.try_recv().unwrap(), vec![1]);
+        assert!(rx.try_recv().is_err());
+
+        ns.set_unwrap_single_element(false);
+        socket
+            .clone()
+            .recv(event("args", serde_json::json!([1])))
+            .unwrap();
+        socket
+            .clone()
+            .recv(event("arg", serde_json::json!([1])))
+            .unwrap();
+        socket
+            .clone()
+            .recv(event("args", serde_json::json!([1, 2])))
+            .unwrap();
+        assert_eq!(rx.try_recv().unwrap(), vec![1]);
+        assert_eq!(rx.try_recv().unwrap(), vec![1, 2]);
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,66 @@
+//! Tests for the acknowledgements owed to the client when the server closes
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection, recv};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::{AckSender, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn ack_flushed_on_close() {
+    let io = create_server(2260).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("test", |ack: AckSender| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            ack.send("done").ok();
+        });
+    });
+
+    let mut ws = create_ws_connection(2260).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["test"]"#.into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    tokio::spawn(async move { io.close().await });
+
+    let msg = tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg, Message::Text(r#"431["done"]"#.into()));
+}
+
+#[tokio::test]
+pub async fn handler_result_flushed_on_drain() {
+    let io = create_server(2433).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("test", || async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok::<_, String>("done")
+        });
+    });
+
+    let mut ws = create_ws_connection(2433).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["test"]"#.into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let socket = io.sockets().unwrap().remove(0);
+    tokio::spawn(socket.drain_and_close(Duration::from_millis(200)));
+
+    // The result of the handler is sent before the disconnect packet
+    let msg = recv(&mut ws).await;
+    assert_eq!(msg, r#"431[null,"done"]"#);
+    assert_eq!(recv(&mut ws).await, "41");
+}
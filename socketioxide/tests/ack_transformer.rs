@@ -0,0 +1,51 @@
+//! Tests for the ack transformer of a namespace
+mod fixture;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use socketioxide::extract::{AckSender, Bin, Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn ack_transformer() {
+    let io = create_server(2230).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("echo", |Data::<Value>(data), ack: AckSender, Bin(bin)| {
+            ack.bin(bin).send(data).ok();
+        });
+    });
+    io.set_ack_transformer("/", |data| json!({ "status": "ok", "body": data }))
+        .unwrap();
+    assert!(io.set_ack_transformer("/unknown", |data| data).is_err());
+
+    let mut ws = create_ws_connection(2230).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["echo",1]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"431[{"body":1,"status":"ok"}]"#.into())
+    );
+
+    ws.send(Message::Text(
+        r#"451-2["echo","bin",{"_placeholder":true,"num":0}]"#.into(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(
+            r#"461-2[{"body":"bin","status":"ok"},{"_placeholder":true,"num":0}]"#.into()
+        )
+    );
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Binary(vec![1, 2, 3]));
+}
@@ -0,0 +1,140 @@
+//! Tests for binary payloads
+mod fixture;
+
+use fixture::{create_server, create_ws_connection, recv_msg};
+use futures::SinkExt;
+use serde_json::Value;
+use socketioxide::extract::{AckSender, Bin, Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn emit_bin_to_socket() {
+    let io = create_server(2130).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on(
+            "echo",
+            |socket: SocketRef, Data::<Value>(data), Bin(bin)| {
+                socket.emit_bin("echo", data, bin).unwrap();
+            },
+        );
+    });
+
+    let mut ws = create_ws_connection(2130).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    recv_msg(&mut ws).await;
+    recv_msg(&mut ws).await;
+
+    let packet = r#"451-["echo","foo",{"_placeholder":true,"num":0}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+
+    assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+    assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![1, 2, 3]));
+}
+
+#[tokio::test]
+pub async fn emit_binary_only() {
+    let io = create_server(2131).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("echo", |socket: SocketRef, Bin(bin)| {
+            socket.emit_binary("echo", bin.clone()).unwrap();
+            socket.join("room").unwrap();
+            socket.within("room").emit_binary("echo", bin).unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2131).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    recv_msg(&mut ws).await;
+    recv_msg(&mut ws).await;
+
+    let packet = r#"452-["echo",{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+    ws.send(Message::Binary(vec![4, 5, 6])).await.unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+        assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![1, 2, 3]));
+        assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![4, 5, 6]));
+    }
+}
+
+#[tokio::test]
+pub async fn ack_binary_only() {
+    let io = create_server(2330).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("echo", |ack: AckSender, Bin(bin)| {
+            ack.bin(bin).send_binary().unwrap();
+        });
+        socket.on("echo_null", |ack: AckSender, Bin(bin)| {
+            ack.bin(bin).send(()).unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2330).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    recv_msg(&mut ws).await;
+    recv_msg(&mut ws).await;
+
+    ws.send(Message::Text(
+        r#"451-2["echo",{"_placeholder":true,"num":0}]"#.to_string(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+
+    // The reference client decodes this packet as an ack with the binary payload as its only argument
+    let packet = r#"461-2[{"_placeholder":true,"num":0}]"#;
+    assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+    assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![1, 2, 3]));
+
+    // A null data is still sent as the first argument
+    ws.send(Message::Text(
+        r#"451-3["echo_null",{"_placeholder":true,"num":0}]"#.to_string(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![4, 5, 6])).await.unwrap();
+    let packet = r#"461-3[null,{"_placeholder":true,"num":0}]"#;
+    assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+    assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![4, 5, 6]));
+}
+
+#[tokio::test]
+pub async fn ack_binary_namespace() {
+    let io = create_server(2420).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("echo", |ack: AckSender| {
+            ack.send("wrong namespace").unwrap();
+        });
+    });
+    io.ns("/foo", |socket: SocketRef| {
+        socket.on("echo", |ack: AckSender, Bin(bin)| {
+            ack.bin(bin).send("foo").unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2420).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    recv_msg(&mut ws).await;
+    recv_msg(&mut ws).await;
+
+    ws.send(Message::Text("40/foo,".to_string())).await.unwrap();
+    let Message::Text(connect) = recv_msg(&mut ws).await else {
+        panic!("expected a connect packet");
+    };
+    assert!(connect.starts_with("40/foo,"), "{connect}");
+
+    ws.send(Message::Text(
+        r#"451-/foo,2["echo",{"_placeholder":true,"num":0}]"#.to_string(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+
+    // The ack is encoded with the namespace of the event, not the default one
+    let packet = r#"461-/foo,2["foo",{"_placeholder":true,"num":0}]"#;
+    assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+    assert_eq!(recv_msg(&mut ws).await, Message::Binary(vec![1, 2, 3]));
+}
@@ -0,0 +1,66 @@
+//! Tests for the compression of the binary attachments sent over the polling transport
+#![cfg(feature = "compress-binary")]
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{
+    create_polling_connection_with_params, create_server_with_config, open_ws, poll_until,
+};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::SocketRef;
+use tokio_tungstenite::tungstenite::Message;
+
+const COMPRESSED: &str = r#"{"_placeholder":true,"gzip":true,"num":0}"#;
+const UNCOMPRESSED: &str = r#"{"_placeholder":true,"num":0}"#;
+
+#[tokio::test]
+pub async fn compression_negotiated_over_polling() {
+    let io = create_server_with_config(2427, |b| b.compress_binary(16)).await;
+    io.ns("/", |_: SocketRef| {});
+
+    let gzip = create_polling_connection_with_params(2427, "&binaryCompression=gzip").await;
+    let plain = create_polling_connection_with_params(2427, "").await;
+    // The connect packets
+    poll_until(2427, &gzip, "sid").await;
+    poll_until(2427, &plain, "sid").await;
+
+    // The broadcasted attachment is only compressed for the client that negotiated it
+    io.bin(vec![vec![1; 64]]).emit("bin", 1).unwrap();
+    let body = poll_until(2427, &gzip, "bin").await;
+    assert!(body.contains(COMPRESSED), "{body}");
+    // The base64 encoding of the gzip magic number
+    assert!(body.contains("\u{1e}bH4sI"), "{body}");
+
+    let body = poll_until(2427, &plain, "bin").await;
+    assert!(body.contains(UNCOMPRESSED), "{body}");
+    assert!(body.contains("\u{1e}bAQEB"), "{body}");
+}
+
+#[tokio::test]
+pub async fn no_compression_over_websocket() {
+    let io = create_server_with_config(2428, |b| b.compress_binary(16)).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.bin(vec![vec![1; 64]]).emit("bin", 1).unwrap();
+    });
+
+    let mut ws = open_ws(2428, "&binaryCompression=gzip").await;
+    ws.send(Message::Text("40{}".to_string())).await.unwrap();
+
+    let packet = format!(r#"451-["bin",1,{UNCOMPRESSED}]"#);
+    let recv = async {
+        loop {
+            if let Message::Text(msg) = ws.next().await.unwrap().unwrap() {
+                if msg.starts_with("451-") {
+                    break msg;
+                }
+            }
+        }
+    };
+    let msg = tokio::time::timeout(Duration::from_millis(200), recv)
+        .await
+        .expect("timeout waiting for the binary event");
+    assert_eq!(msg, packet);
+    let bin = ws.next().await.unwrap().unwrap();
+    assert_eq!(bin, Message::Binary(vec![1; 64]));
+}
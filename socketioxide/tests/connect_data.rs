@@ -0,0 +1,26 @@
+//! Tests for the custom data of the connect packets
+mod fixture;
+
+use fixture::{create_server, create_ws_connection};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use socketioxide::extract::SocketRef;
+
+#[tokio::test]
+pub async fn connect_data() {
+    let io = create_server(2250).await;
+    io.ns("/", |_: SocketRef| {});
+    io.set_connect_data("/", json!({ "features": ["chat"], "sid": "overridden" }))
+        .unwrap();
+    assert!(io.set_connect_data("/", json!(["not an object"])).is_err());
+    assert!(io.set_connect_data("/unknown", json!({})).is_err());
+
+    let mut ws = create_ws_connection(2250).await;
+    // The engine.io open packet
+    ws.next().await.unwrap().unwrap();
+
+    let msg = ws.next().await.unwrap().unwrap().to_string();
+    let payload: Value = serde_json::from_str(msg.strip_prefix("40").unwrap()).unwrap();
+    assert_eq!(payload["features"], json!(["chat"]));
+    assert_ne!(payload["sid"], "overridden");
+}
@@ -0,0 +1,95 @@
+//! Tests for the connect handler
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::SocketRef;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn async_connect_handler_completes_before_events() {
+    let io = create_server(2140).await;
+    let io2 = io.clone();
+    io.ns("/", move |socket: SocketRef| async move {
+        let io = io2.clone();
+        socket.on("test", move || {
+            io.to("room1").emit("room1", "hello").unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        socket.join("room1").unwrap();
+    });
+
+    let mut ws = create_ws_connection(2140).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    // The event is sent while the connect handler is still running
+    ws.send(Message::Text(r#"42["test"]"#.to_string()))
+        .await
+        .unwrap();
+    let msg = tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .expect("timeout waiting for a message")
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg, Message::Text(r#"42["room1","hello"]"#.to_string()));
+}
+
+#[cfg(feature = "test-utils")]
+#[tokio::test]
+pub async fn await_connect_handler_completion() {
+    let io = create_server(2350).await;
+    io.ns("/", |socket: SocketRef| async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        socket.join("room1").unwrap();
+    });
+
+    let mut ws = create_ws_connection(2350).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    let socket = io.sockets().unwrap().pop().unwrap();
+    assert!(!socket.rooms().unwrap().contains(&"room1".into()));
+    tokio::time::timeout(
+        Duration::from_millis(200),
+        socket.connect_handler_completed(),
+    )
+    .await
+    .expect("timeout waiting for the connect handler");
+    assert!(socket.rooms().unwrap().contains(&"room1".into()));
+
+    // It resolves immediately once the handler has completed
+    socket.connect_handler_completed().await;
+}
+
+#[tokio::test]
+pub async fn events_dispatched_after_connect_handler_panic() {
+    let io = create_server(2426).await;
+    io.ns("/", |socket: SocketRef| async move {
+        socket.on("test", |socket: SocketRef| {
+            socket.emit("test", "hello").unwrap();
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        panic!("connect handler failure");
+    });
+
+    let mut ws = create_ws_connection(2426).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    // The event is buffered until the connect handler panics
+    ws.send(Message::Text(r#"42["test"]"#.to_string()))
+        .await
+        .unwrap();
+    let msg = tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .expect("timeout waiting for a message")
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg, Message::Text(r#"42["test","hello"]"#.to_string()));
+}
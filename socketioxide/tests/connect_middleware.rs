@@ -0,0 +1,112 @@
+//! Tests for the connect middlewares
+mod fixture;
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use fixture::create_server;
+use futures::{SinkExt, StreamExt};
+use socketioxide::{extract::SocketRef, handler::ConnectContext};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Connects to the main namespace with the given cookie, query and auth payload
+/// and returns the socket.io connect response
+async fn connect(port: u16, cookie: Option<&str>, query: &str, auth: &str) -> (WsStream, String) {
+    let mut req = format!("ws://127.0.0.1:{port}/socket.io/?EIO=4&transport=websocket{query}")
+        .into_client_request()
+        .unwrap();
+    if let Some(cookie) = cookie {
+        req.headers_mut().insert("cookie", cookie.parse().unwrap());
+    }
+    let mut ws: WsStream = tokio_tungstenite::connect_async(req).await.unwrap().0;
+    ws.next().await.unwrap().unwrap(); // engine.io open packet
+    ws.send(Message::Text(format!("40{auth}"))).await.unwrap();
+
+    let msg = tokio::time::timeout(Duration::from_millis(100), ws.next())
+        .await
+        .expect("timeout waiting for a message")
+        .unwrap()
+        .unwrap()
+        .to_string();
+    (ws, msg)
+}
+
+fn authorize(ctx: &ConnectContext<'_>) -> Result<(), &'static str> {
+    let cookie = ctx
+        .headers
+        .get("cookie")
+        .and_then(|c| c.to_str().ok())
+        .and_then(|c| c.strip_prefix("session="));
+    let token = ctx
+        .auth
+        .get("token")
+        .and_then(|t| t.as_str())
+        .and_then(|t| t.strip_prefix("Bearer "));
+    match cookie.or(token) {
+        Some("secret") => Ok(()),
+        _ => Err("Unauthorized"),
+    }
+}
+
+#[tokio::test]
+pub async fn connect_middleware_auth() {
+    let io = create_server(2410).await;
+    io.ns("/", |_: SocketRef| {});
+    io.use_connect("/", authorize).unwrap();
+    assert!(io.use_connect("/unknown", authorize).is_err());
+
+    let (_ws1, msg) = connect(2410, Some("session=secret"), "", "").await;
+    assert!(msg.starts_with("40{"), "{msg}");
+    let (_ws2, msg) = connect(2410, None, "", r#"{"token":"Bearer secret"}"#).await;
+    assert!(msg.starts_with("40{"), "{msg}");
+
+    let unauthorized = r#"44{"message":"Unauthorized"}"#;
+    assert_eq!(connect(2410, None, "", "").await.1, unauthorized);
+    let msg = connect(2410, Some("session=invalid"), "", "").await.1;
+    assert_eq!(msg, unauthorized);
+    let auth = r#"{"token":"Bearer invalid"}"#;
+    assert_eq!(connect(2410, None, "", auth).await.1, unauthorized);
+    assert_eq!(io.sockets().unwrap().len(), 2);
+}
+
+#[tokio::test]
+pub async fn connect_middleware_order() {
+    let io = create_server(2411).await;
+    io.ns("/", |_: SocketRef| {});
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls1 = calls.clone();
+    io.use_connect("/", move |ctx: &ConnectContext<'_>| {
+        calls1.fetch_add(1, Ordering::SeqCst);
+        match ctx.query {
+            Some(q) if q.contains("room=") => Ok(()),
+            _ => Err("Missing room"),
+        }
+    })
+    .unwrap();
+    let calls2 = calls.clone();
+    io.use_connect("/", move |_: &ConnectContext<'_>| {
+        calls2.fetch_add(10, Ordering::SeqCst);
+        Ok::<_, &str>(())
+    })
+    .unwrap();
+
+    // The next middlewares are not called once the connection is refused
+    let msg = connect(2411, None, "", "").await.1;
+    assert_eq!(msg, r#"44{"message":"Missing room"}"#);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let (_ws, msg) = connect(2411, None, "&room=general", "").await;
+    assert!(msg.starts_with("40{"), "{msg}");
+    assert_eq!(calls.load(Ordering::SeqCst), 12);
+}
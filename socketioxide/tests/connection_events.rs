@@ -0,0 +1,53 @@
+//! Tests for the namespace connection events stream
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use socketioxide::{
+    extract::SocketRef,
+    socket::{ConnectionEvent, DisconnectReason},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn connection_events() {
+    let io = create_server(2120).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("disconnect_me", |socket: SocketRef| {
+            socket.disconnect().ok();
+        });
+    });
+    let mut events = io.connection_events("/").unwrap();
+    assert!(io.connection_events("/unknown").is_err());
+
+    let mut ws = create_ws_connection(2120).await;
+    let sid = match timeout(events.next()).await.unwrap() {
+        ConnectionEvent::Connected(sid) => sid,
+        event => panic!("unexpected event {:?}", event),
+    };
+
+    ws.send(Message::Text(r#"42["disconnect_me"]"#.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(
+        timeout(events.next()).await.unwrap(),
+        ConnectionEvent::Disconnected(sid, DisconnectReason::ServerNSDisconnect)
+    );
+
+    // Dropped streams are cleaned up and new streams only receive the next events
+    drop(events);
+    let mut events = io.connection_events("/").unwrap();
+    let _ws = create_ws_connection(2120).await;
+    assert!(matches!(
+        timeout(events.next()).await.unwrap(),
+        ConnectionEvent::Connected(_)
+    ));
+}
+
+async fn timeout<F: std::future::Future>(fut: F) -> F::Output {
+    tokio::time::timeout(Duration::from_millis(200), fut)
+        .await
+        .expect("timeout waiting for an event")
+}
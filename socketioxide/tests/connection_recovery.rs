@@ -0,0 +1,189 @@
+//! Tests for the connection state recovery
+mod fixture;
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use fixture::{connect_ws, create_server_with_config, recv, WsStream};
+use futures::StreamExt;
+use serde_json::Value;
+use socketioxide::{
+    extract::SocketRef,
+    offline::OfflineStore,
+    packet::{Packet, PacketData},
+    socket::{ConnectionEvent, DisconnectReason, Sid},
+};
+
+#[derive(Debug, Default)]
+struct MemoryStore(Mutex<HashMap<(String, Sid), Vec<Packet<'static>>>>);
+
+impl OfflineStore for MemoryStore {
+    fn store(&self, ns: &str, sid: Sid, packet: Packet<'static>) -> bool {
+        let mut map = self.0.lock().unwrap();
+        map.entry((ns.to_string(), sid)).or_default().push(packet);
+        true
+    }
+    fn take(&self, ns: &str, sid: Sid) -> Vec<Packet<'static>> {
+        let mut map = self.0.lock().unwrap();
+        map.remove(&(ns.to_string(), sid)).unwrap_or_default()
+    }
+}
+
+/// Connects to the root namespace with the given auth payload and returns the connect packet payload
+async fn connect(port: u16, auth: &str) -> (WsStream, Value) {
+    let mut ws = connect_ws(port, "/", auth).await;
+    let msg = recv(&mut ws).await;
+    let packet = serde_json::from_str(msg.strip_prefix("40").unwrap()).unwrap();
+    (ws, packet)
+}
+
+#[tokio::test]
+pub async fn recover_session() {
+    let io = create_server_with_config(2100, |b| b.connection_recovery(Duration::from_secs(1), 10))
+        .await;
+    io.ns("/", |socket: SocketRef| {
+        if socket.recovered() {
+            let mut rooms = socket.rooms().unwrap();
+            rooms.retain(|room| room != socket.id.as_str());
+            socket.emit("recovered", rooms).ok();
+        } else {
+            socket.join("room1").ok();
+            socket.emit("hello", "world").ok();
+        }
+    });
+
+    let (mut ws, packet) = connect(2100, "").await;
+    let pid = packet["pid"].as_str().unwrap().to_string();
+    assert_eq!(recv(&mut ws).await, r#"42["hello","world","1"]"#);
+
+    ws.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // Emitted while the socket is disconnected
+    io.to("room1").emit("missed", 1).unwrap();
+    io.to("room2").emit("not-missed", 2).unwrap();
+    io.emit("missed", 3).unwrap();
+
+    let auth = format!(r#"{{"pid":"{pid}","offset":"1"}}"#);
+    let (mut ws, packet) = connect(2100, &auth).await;
+    assert_eq!(packet["pid"], pid.as_str());
+    assert_eq!(recv(&mut ws).await, r#"42["missed",1,"2"]"#);
+    assert_eq!(recv(&mut ws).await, r#"42["missed",3,"3"]"#);
+    assert_eq!(recv(&mut ws).await, r#"42["recovered","room1","4"]"#);
+}
+
+#[tokio::test]
+pub async fn unknown_session() {
+    let io = create_server_with_config(2101, |b| b.connection_recovery(Duration::from_secs(1), 10))
+        .await;
+    io.ns("/", |socket: SocketRef| {
+        socket.emit("recovered", socket.recovered()).ok();
+    });
+
+    let pid = Sid::new().to_string();
+    let auth = format!(r#"{{"pid":"{pid}","offset":"1"}}"#);
+    let (mut ws, packet) = connect(2101, &auth).await;
+    assert!(packet["pid"].is_string());
+    assert_ne!(packet["pid"], pid.as_str());
+    assert_eq!(recv(&mut ws).await, r#"42["recovered",false,"1"]"#);
+}
+
+#[tokio::test]
+pub async fn flush_offline_messages() {
+    let io = create_server_with_config(2102, |b| {
+        b.connection_recovery(Duration::from_secs(1), 10)
+            .offline_store(MemoryStore::default())
+    })
+    .await;
+    io.ns("/", |socket: SocketRef| {
+        if socket.recovered() {
+            socket.emit("recovered", true).ok();
+        }
+        socket.on_disconnect(|socket: SocketRef| {
+            assert!(!socket.connected());
+            socket.emit("offline", 1).unwrap();
+            socket.bin(vec![vec![1, 2]]).emit("offline", 2).unwrap();
+        });
+    });
+
+    let (mut ws, packet) = connect(2102, "").await;
+    let pid = packet["pid"].as_str().unwrap().to_string();
+    ws.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let auth = format!(r#"{{"pid":"{pid}"}}"#);
+    let (mut ws, _) = connect(2102, &auth).await;
+    assert_eq!(recv(&mut ws).await, r#"42["offline",1,"1"]"#);
+    assert_eq!(
+        recv(&mut ws).await,
+        r#"451-["offline",2,{"_placeholder":true,"num":0},"2"]"#
+    );
+    assert_eq!(recv(&mut ws).await, "\u{1}\u{2}");
+    assert_eq!(recv(&mut ws).await, r#"42["recovered",true,"3"]"#);
+}
+
+#[cfg(feature = "extensions")]
+#[tokio::test]
+pub async fn session_continuity() {
+    let io = create_server_with_config(2160, |b| b.connection_recovery(Duration::from_secs(1), 10))
+        .await;
+    io.ns("/", |socket: SocketRef| match socket.previous_id() {
+        Some(sid) => {
+            let user = socket.extensions.get::<String>().map(|u| u.clone());
+            socket.emit("reconnected", (sid.to_string(), user)).ok();
+        }
+        None => {
+            socket.extensions.insert("alice".to_string());
+        }
+    });
+    let mut events = io.connection_events("/").unwrap();
+
+    let (ws, packet) = connect(2160, "").await;
+    let pid = packet["pid"].as_str().unwrap().to_string();
+    let sid = match events.next().await.unwrap() {
+        ConnectionEvent::Connected(sid) => sid,
+        event => panic!("unexpected event {:?}", event),
+    };
+    drop(ws);
+    assert!(matches!(
+        events.next().await.unwrap(),
+        ConnectionEvent::Disconnected(s, DisconnectReason::TransportError) if s == sid
+    ));
+
+    let auth = format!(r#"{{"pid":"{pid}"}}"#);
+    let (mut ws, packet) = connect(2160, &auth).await;
+    let new_sid = packet["sid"].as_str().unwrap().parse().unwrap();
+    assert_eq!(
+        events.next().await.unwrap(),
+        ConnectionEvent::Reconnected(new_sid, sid)
+    );
+    assert_eq!(
+        recv(&mut ws).await,
+        format!(r#"42["reconnected","{sid}","alice","1"]"#)
+    );
+}
+
+#[tokio::test]
+pub async fn missed_packets_are_prepared() {
+    let io = create_server_with_config(2424, |b| b.connection_recovery(Duration::from_secs(1), 10))
+        .await;
+    io.ns("/", || {});
+    io.set_event_prefix("/", "v2:").unwrap();
+    io.use_outbound("/", |packet: &mut Packet<'_>| {
+        if let PacketData::Event(_, data, _) = &mut packet.inner {
+            *data = "outbound".into();
+        }
+    })
+    .unwrap();
+
+    let (mut ws, packet) = connect(2424, "").await;
+    let pid = packet["pid"].as_str().unwrap().to_string();
+    ws.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // Broadcasted while the socket is disconnected
+    io.emit("missed", 1).unwrap();
+
+    let auth = format!(r#"{{"pid":"{pid}"}}"#);
+    let (mut ws, _) = connect(2424, &auth).await;
+    assert_eq!(recv(&mut ws).await, r#"42["v2:missed","outbound","1"]"#);
+}
@@ -0,0 +1,45 @@
+//! Tests for targeting a single socket with its id
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection, recv_event};
+use futures::{SinkExt, StreamExt};
+use socketioxide::{
+    extract::{Data, SocketRef},
+    socket::Sid,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn emit_to_sid() {
+    let io = create_server(2200).await;
+    let io2 = io.clone();
+    io.ns("/", move |socket: SocketRef| {
+        assert!(socket
+            .rooms()
+            .unwrap()
+            .contains(&socket.id.to_string().into()));
+        socket.emit("id", socket.id.as_str()).ok();
+        let io = io2.clone();
+        socket.on("dm", move |Data::<(Sid, String)>((sid, msg))| {
+            io.to_sid(sid).emit("dm", msg).unwrap();
+        });
+    });
+
+    let mut ws1 = create_ws_connection(2200).await;
+    let mut ws2 = create_ws_connection(2200).await;
+    let msg = recv_event(&mut ws2).await;
+    let sid2 = msg
+        .strip_prefix(r#"42["id",""#)
+        .and_then(|s| s.strip_suffix(r#""]"#))
+        .unwrap();
+    recv_event(&mut ws1).await;
+
+    let dm = format!(r#"42["dm","{sid2}","hello"]"#);
+    ws1.send(Message::Text(dm)).await.unwrap();
+    assert_eq!(recv_event(&mut ws2).await, r#"42["dm","hello"]"#);
+    assert!(tokio::time::timeout(Duration::from_millis(20), ws1.next())
+        .await
+        .is_err());
+}
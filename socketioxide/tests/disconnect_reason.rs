@@ -7,6 +7,10 @@
 //!
 //! * Client namespace disconnect
 //! * Server namespace disconnect
+//! * Server namespace disconnect with a custom reason
+//! * Server namespace graceful disconnect
+//! * Server closing
+//! * Idle timeout
 
 use std::time::Duration;
 
@@ -16,8 +20,8 @@ use tokio::sync::mpsc;
 
 mod fixture;
 
-use fixture::{create_server, send_req};
-use tokio_tungstenite::tungstenite::Message;
+use fixture::{connect_ws, create_server, create_server_with_config, recv, recv_msg, send_req};
+use tokio_tungstenite::tungstenite::{protocol::frame::coding::CloseCode, Message};
 
 use crate::fixture::{create_polling_connection, create_ws_connection};
 
@@ -228,6 +232,150 @@ pub async fn server_ns_disconnect() {
     assert_eq!(data, DisconnectReason::ServerNSDisconnect);
 }
 
+#[tokio::test]
+pub async fn server_ns_disconnect_close_frame() {
+    let io = create_server(2431).await;
+    let handler = |socket: SocketRef| {
+        socket.on("leave", |socket: SocketRef| {
+            socket.disconnect().unwrap();
+        });
+    };
+    io.ns("/", handler);
+    io.ns("/admin", handler);
+
+    let mut stream = connect_ws(2431, "/", "{}").await;
+    assert!(recv(&mut stream).await.starts_with("40{"));
+    stream
+        .send(Message::Text("40/admin,".into()))
+        .await
+        .unwrap();
+    assert!(recv(&mut stream).await.starts_with("40/admin,{"));
+
+    // The connection is kept while it is still used by another namespace
+    stream
+        .send(Message::Text(r#"42["leave"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv(&mut stream).await, "41");
+    stream
+        .send(Message::Text(r#"42/admin,["leave"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(recv(&mut stream).await, "41/admin,");
+
+    let Message::Close(Some(frame)) = recv_msg(&mut stream).await else {
+        panic!("expected a close frame with a code");
+    };
+    assert_eq!(frame.code, CloseCode::Normal);
+    assert_eq!(frame.reason, "server namespace disconnect");
+}
+
+#[tokio::test]
+pub async fn server_ns_disconnect_with_reason() {
+    let (tx, mut rx) = mpsc::channel::<DisconnectReason>(1);
+    let io = create_server(12360).await;
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on("forbidden", |socket: SocketRef| {
+            socket
+                .disconnect_with(DisconnectReason::PacketParsingError)
+                .unwrap();
+        });
+        socket.on_disconnect(move |socket: SocketRef, reason: DisconnectReason| {
+            println!("Socket.IO disconnected: {} {}", socket.id, reason);
+            tx.try_send(reason).unwrap();
+        });
+    });
+
+    let mut stream = create_ws_connection(12360).await;
+    stream
+        .send(Message::Text("42[\"forbidden\"]".into()))
+        .await
+        .unwrap();
+
+    let data = tokio::time::timeout(Duration::from_millis(20), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::PacketParsingError")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::PacketParsingError);
+}
+
+#[tokio::test]
+pub async fn server_ns_drain_and_close() {
+    let (tx, mut rx) = mpsc::channel::<DisconnectReason>(1);
+    let io = create_server(2180).await;
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on("maintenance", |socket: SocketRef| async move {
+            socket.emit("bye", ()).unwrap();
+            socket
+                .drain_and_close(Duration::from_millis(100))
+                .await
+                .unwrap();
+        });
+        socket.on_disconnect(move |socket: SocketRef, reason: DisconnectReason| {
+            println!("Socket.IO disconnected: {} {}", socket.id, reason);
+            tx.try_send(reason).unwrap();
+        });
+    });
+
+    let mut stream = create_ws_connection(2180).await;
+    stream
+        .send(Message::Text("42[\"maintenance\"]".into()))
+        .await
+        .unwrap();
+
+    let data = tokio::time::timeout(Duration::from_millis(50), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::ServerNSDisconnect")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::ServerNSDisconnect);
+
+    // The queued event is received before the disconnect packet
+    let mut msgs = Vec::new();
+    while let Some(Ok(Message::Text(msg))) = stream.next().await {
+        let end = msg == "41";
+        msgs.push(msg);
+        if end {
+            break;
+        }
+    }
+    assert!(msgs.ends_with(&["42[\"bye\",null]".to_string(), "41".to_string()]));
+}
+
+#[tokio::test]
+pub async fn idle_timeout() {
+    let io = create_server_with_config(2310, |b| b.idle_timeout(Duration::from_millis(150))).await;
+    let mut rx = attach_handler(&io, 1);
+    let mut stream = create_ws_connection(2310).await;
+    stream.next().await; // engine.io open packet
+    stream.next().await; // socket.io open packet
+
+    // Any event, even without handler, resets the idle timer
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    stream
+        .send(Message::Text(r#"42["activity"]"#.into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(rx.try_recv().is_err());
+
+    let data = tokio::time::timeout(Duration::from_millis(200), rx.recv())
+        .await
+        .expect("timeout waiting for DisconnectReason::IdleTimeout")
+        .unwrap();
+    assert_eq!(data, DisconnectReason::IdleTimeout);
+    let msg = stream.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text("41".into()));
+
+    // The connection is closed along with its only namespace
+    let Message::Close(Some(frame)) = stream.next().await.unwrap().unwrap() else {
+        panic!("expected a close frame with a code");
+    };
+    assert_eq!(frame.code, CloseCode::Policy);
+    assert_eq!(frame.reason, "idle timeout");
+}
+
 #[tokio::test]
 pub async fn server_ws_closing() {
     let io = create_server(12350).await;
@@ -254,6 +402,25 @@ pub async fn server_ws_closing() {
     }
 }
 
+#[tokio::test]
+pub async fn server_ws_closing_close_frame() {
+    let io = create_server(12354).await;
+    let _rx = attach_handler(&io, 1);
+
+    let mut stream = create_ws_connection(12354).await;
+    stream.next().await; // engine.io open packet
+    stream.next().await; // socket.io open packet
+
+    tokio::time::timeout(Duration::from_millis(20), io.close())
+        .await
+        .expect("timeout waiting for server closing");
+    let Message::Close(Some(frame)) = stream.next().await.unwrap().unwrap() else {
+        panic!("expected a close frame with a code");
+    };
+    assert_eq!(frame.code, CloseCode::Away);
+    assert_eq!(frame.reason, "server closing");
+}
+
 #[tokio::test]
 pub async fn server_http_closing() {
     let io = create_server(12351).await;
@@ -0,0 +1,75 @@
+//! Tests for dynamic namespaces
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{connect_ws, create_server, create_server_with_config, recv};
+use socketioxide::extract::SocketRef;
+
+#[tokio::test]
+pub async fn dynamic_ns_auto_create() {
+    let io = create_server(2150).await;
+    io.dynamic_ns(|socket: SocketRef| {
+        socket.emit("ns", socket.ns()).ok();
+    });
+    assert!(io.of("/doc-1").is_err());
+
+    let mut ws = connect_ws(2150, "/doc-1", "").await;
+    assert!(recv(&mut ws).await.starts_with("40/doc-1,{"));
+    assert_eq!(recv(&mut ws).await, r#"42/doc-1,["ns","/doc-1"]"#);
+
+    // The second socket joins the same namespace
+    let mut ws = connect_ws(2150, "/doc-1", "").await;
+    assert!(recv(&mut ws).await.starts_with("40/doc-1,{"));
+    recv(&mut ws).await;
+    assert_eq!(io.of("/doc-1").unwrap().sockets().unwrap().len(), 2);
+}
+
+#[tokio::test]
+pub async fn unknown_ns_rejected() {
+    let io = create_server(2151).await;
+    io.ns("/", |_: SocketRef| {});
+
+    let mut ws = connect_ws(2151, "/doc-1", "").await;
+    assert_eq!(
+        recv(&mut ws).await,
+        r#"44/doc-1,{"message":"Invalid namespace"}"#
+    );
+    assert!(io.of("/doc-1").is_err());
+}
+
+#[tokio::test]
+pub async fn idle_dynamic_ns_removed() {
+    let io = create_server(2429).await;
+    io.dynamic_ns(|_: SocketRef| {});
+
+    let mut ws = connect_ws(2429, "/doc-1", "").await;
+    assert!(recv(&mut ws).await.starts_with("40/doc-1,{"));
+    assert!(io.of("/doc-1").is_ok());
+
+    ws.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(io.of("/doc-1").is_err());
+}
+
+#[tokio::test]
+pub async fn max_dynamic_namespaces() {
+    let io = create_server_with_config(2430, |b| b.max_dynamic_namespaces(1)).await;
+    io.dynamic_ns(|_: SocketRef| {});
+
+    let mut ws1 = connect_ws(2430, "/doc-1", "").await;
+    assert!(recv(&mut ws1).await.starts_with("40/doc-1,{"));
+
+    let mut ws2 = connect_ws(2430, "/doc-2", "").await;
+    assert_eq!(
+        recv(&mut ws2).await,
+        r#"44/doc-2,{"message":"Invalid namespace"}"#
+    );
+    assert!(io.of("/doc-2").is_err());
+
+    // The slot of the first namespace is freed once it is idle
+    ws1.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let mut ws2 = connect_ws(2430, "/doc-2", "").await;
+    assert!(recv(&mut ws2).await.starts_with("40/doc-2,{"));
+}
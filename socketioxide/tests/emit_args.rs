@@ -0,0 +1,43 @@
+//! Tests for the emission of an explicit list of arguments
+mod fixture;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use socketioxide::extract::{Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn emit_args() {
+    let io = create_server(2300).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on(
+            "args",
+            |socket: SocketRef, Data::<(i32, Vec<i32>)>((a, b))| {
+                // A single array argument
+                socket.emit_args("single", &[json!(b)]).unwrap();
+                // The same arguments as received
+                socket.emit_args("multi", &[json!(a), json!(b)]).unwrap();
+                socket
+                    .within(socket.id)
+                    .emit_args("room", &[json!(a)])
+                    .unwrap();
+            },
+        );
+    });
+
+    let mut ws = create_ws_connection(2300).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"42["args",1,[2,3]]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"42["single",[2,3]]"#.into()));
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"42["multi",1,[2,3]]"#.into()));
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"42["room",1]"#.into()));
+}
@@ -0,0 +1,42 @@
+//! Tests for the event name prefix of a namespace
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection, try_recv_event};
+use futures::SinkExt;
+use serde_json::Value;
+use socketioxide::extract::{Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn event_prefix() {
+    let io = create_server(2210).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("chat", |socket: SocketRef, Data::<Value>(data)| {
+            socket.emit("chat", data).ok();
+        });
+    });
+    io.set_event_prefix("/", "v2:").unwrap();
+    assert!(io.set_event_prefix("/unknown", "v2:").is_err());
+
+    let mut ws = create_ws_connection(2210).await;
+    ws.send(Message::Text(r#"42["v2:chat",1]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        try_recv_event(&mut ws, Duration::from_millis(50))
+            .await
+            .unwrap(),
+        r#"42["v2:chat",1]"#
+    );
+
+    // Events without the prefix are ignored
+    ws.send(Message::Text(r#"42["chat",2]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        try_recv_event(&mut ws, Duration::from_millis(50)).await,
+        None
+    );
+}
@@ -1,11 +1,13 @@
 //! Tests for extractors
 use std::time::Duration;
 
+use futures::SinkExt;
 use serde_json::json;
-use socketioxide::extract::{Data, SocketRef, State, TryData};
+use socketioxide::extract::{Binary, Data, SocketRef, State, TryData};
 use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
 
-use fixture::{create_server, create_server_with_state};
+use fixture::{create_server, create_server_with_state, create_ws_connection};
 
 use crate::fixture::socketio_client;
 
@@ -112,3 +114,37 @@ pub async fn try_data_extractor() {
 
     assert_ok!(client.disconnect().await);
 }
+
+#[tokio::test]
+pub async fn binary_extractor() {
+    const PORT: u16 = 2240;
+    let io = create_server(PORT).await;
+    let (tx, mut rx) = mpsc::channel::<Binary>(4);
+    io.ns("/", move |s: SocketRef| {
+        s.on("test", move |bin: Binary| {
+            assert_ok!(tx.try_send(bin));
+        });
+    });
+
+    let mut ws = create_ws_connection(PORT).await;
+    assert_ok!(
+        ws.send(Message::Text(
+            r#"452-["test",{"_placeholder":true,"num":0},{"_placeholder":true,"num":1}]"#.into()
+        ))
+        .await
+    );
+    assert_ok!(ws.send(Message::Binary(vec![1, 2])).await);
+    assert_ok!(ws.send(Message::Binary(vec![3])).await);
+
+    let bin = rx.recv().await.unwrap();
+    assert_eq!(bin.len(), 2);
+    assert_eq!(bin.get(0), Some(&[1, 2][..]));
+    assert_eq!(bin.get(1), Some(&[3][..]));
+    assert_eq!(bin.get(2), None);
+    assert_eq!(bin.iter().map(<[u8]>::len).sum::<usize>(), 3);
+    assert_eq!(bin.into_inner(), vec![vec![1, 2], vec![3]]);
+
+    // An event without binary attachments
+    assert_ok!(ws.send(Message::Text(r#"42["test"]"#.into())).await);
+    assert!(rx.recv().await.unwrap().is_empty());
+}
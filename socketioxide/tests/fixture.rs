@@ -7,7 +7,7 @@ use std::{
 };
 
 use engineioxide::service::NotFoundService;
-use futures::{future::BoxFuture, SinkExt};
+use futures::{future::BoxFuture, SinkExt, StreamExt};
 use http::Request;
 use http_body_util::{BodyExt, Either, Empty, Full};
 use hyper::server::conn::http1;
@@ -20,10 +20,12 @@ use rust_socketio::{
     Payload,
 };
 use serde::{Deserialize, Serialize};
-use socketioxide::{adapter::LocalAdapter, service::SocketIoService, SocketIo};
+use socketioxide::{adapter::LocalAdapter, service::SocketIoService, SocketIo, SocketIoBuilder};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 /// An OpenPacket is used to initiate a connection
 #[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
 #[serde(rename_all = "camelCase")]
@@ -77,7 +79,14 @@ pub async fn send_req(
 }
 
 pub async fn create_polling_connection(port: u16) -> String {
-    let body = send_req(port, format!("transport=polling"), http::Method::GET, None).await;
+    create_polling_connection_with_params(port, "").await
+}
+
+/// Opens a polling connection with the given query parameters (e.g. `&key=value`)
+/// and connects it to the main namespace
+pub async fn create_polling_connection_with_params(port: u16, params: &str) -> String {
+    let params = format!("transport=polling{params}");
+    let body = send_req(port, params, http::Method::GET, None).await;
     let open_packet: OpenPacket = serde_json::from_str(&body).unwrap();
 
     send_req(
@@ -90,7 +99,23 @@ pub async fn create_polling_connection(port: u16) -> String {
 
     open_packet.sid
 }
-pub async fn create_ws_connection(port: u16) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+/// Polls the connection `sid` until it receives a payload containing `pattern`
+pub async fn poll_until(port: u16, sid: &str, pattern: &str) -> String {
+    let poll = async {
+        loop {
+            let params = format!("transport=polling&sid={sid}");
+            let body = send_req(port, params, http::Method::GET, None).await;
+            if body.contains(pattern) {
+                return body;
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_millis(500), poll)
+        .await
+        .expect("timeout waiting for the packet")
+}
+
+pub async fn create_ws_connection(port: u16) -> WsStream {
     let mut ws = tokio_tungstenite::connect_async(format!(
         "ws://127.0.0.1:{port}/socket.io/?EIO=4&transport=websocket"
     ))
@@ -103,23 +128,81 @@ pub async fn create_ws_connection(port: u16) -> WebSocketStream<MaybeTlsStream<T
     ws
 }
 
-pub async fn create_server_with_state<T: Send + Sync + 'static>(port: u16, state: T) -> SocketIo {
-    let (svc, io) = SocketIo::builder()
-        .ping_interval(Duration::from_millis(300))
-        .ping_timeout(Duration::from_millis(200))
-        .with_state(state)
-        .build_svc();
+/// Opens a websocket connection with the given query parameters (e.g. `&key=value`)
+pub async fn open_ws(port: u16, params: &str) -> WsStream {
+    tokio_tungstenite::connect_async(format!(
+        "ws://127.0.0.1:{port}/socket.io/?EIO=4&transport=websocket{params}"
+    ))
+    .await
+    .unwrap()
+    .0
+}
 
-    spawn_server(port, svc).await;
+/// Opens a websocket connection, skips the engine.io open packet
+/// and sends a connect packet to the namespace `ns` with the `auth` payload
+pub async fn connect_ws(port: u16, ns: &str, auth: &str) -> WsStream {
+    let mut ws = open_ws(port, "").await;
+    ws.next().await.unwrap().unwrap();
+    let packet = match ns {
+        "/" => format!("40{auth}"),
+        ns => format!("40{ns},{auth}"),
+    };
+    ws.send(Message::Text(packet)).await.unwrap();
+    ws
+}
 
-    io
+/// Receives the next websocket message
+pub async fn recv_msg(ws: &mut WsStream) -> Message {
+    tokio::time::timeout(Duration::from_millis(200), ws.next())
+        .await
+        .expect("timeout waiting for a message")
+        .unwrap()
+        .unwrap()
+}
+
+/// Receives the next websocket message as a string
+pub async fn recv(ws: &mut WsStream) -> String {
+    recv_msg(ws).await.to_string()
+}
+
+/// Receives the next socket.io event of the main namespace, skipping the other packets.
+/// Returns `None` if no event is received within the timeout.
+pub async fn try_recv_event(ws: &mut WsStream, timeout: Duration) -> Option<String> {
+    let recv = async {
+        loop {
+            let msg = ws.next().await.unwrap().unwrap().to_string();
+            if msg.starts_with("42") {
+                break msg;
+            }
+        }
+    };
+    tokio::time::timeout(timeout, recv).await.ok()
+}
+
+/// Receives the next socket.io event of the main namespace, skipping the other packets
+pub async fn recv_event(ws: &mut WsStream) -> String {
+    try_recv_event(ws, Duration::from_millis(200))
+        .await
+        .expect("timeout waiting for an event")
+}
+
+pub async fn create_server_with_state<T: Send + Sync + 'static>(port: u16, state: T) -> SocketIo {
+    create_server_with_config(port, |b| b.with_state(state)).await
 }
 
 pub async fn create_server(port: u16) -> SocketIo {
-    let (svc, io) = SocketIo::builder()
+    create_server_with_config(port, |b| b).await
+}
+
+/// Creates a server with the fixture heartbeat, and the options set by `config`
+pub async fn create_server_with_config(
+    port: u16,
+    config: impl FnOnce(SocketIoBuilder) -> SocketIoBuilder,
+) -> SocketIo {
+    let builder = SocketIo::builder()
         .ping_interval(Duration::from_millis(300))
-        .ping_timeout(Duration::from_millis(200))
-        .build_svc();
+        .ping_timeout(Duration::from_millis(200));
+    let (svc, io) = config(builder).build_svc();
 
     spawn_server(port, svc).await;
     io
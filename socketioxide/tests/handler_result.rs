@@ -0,0 +1,157 @@
+//! Tests for the acknowledgement of the results returned by message handlers
+mod fixture;
+
+use std::num::ParseIntError;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::{AckSender, Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+async fn parse(Data(data): Data<String>) -> Result<u32, ParseIntError> {
+    data.parse()
+}
+
+#[tokio::test]
+pub async fn handler_result_ack() {
+    let io = create_server(2270).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on("parse", parse);
+        socket.on("sync_parse", |Data::<String>(data)| {
+            data.parse::<u32>().map_err(|e| e.to_string())
+        });
+    });
+
+    let mut ws = create_ws_connection(2270).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["parse","12"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"431[null,12]"#.into()));
+
+    ws.send(Message::Text(r#"422["parse","abc"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"432[{"message":"invalid digit found in string"}]"#.into())
+    );
+
+    // Without an ack id, the result is not sent
+    ws.send(Message::Text(r#"42["sync_parse","abc"]"#.into()))
+        .await
+        .unwrap();
+    ws.send(Message::Text(r#"423["sync_parse",""]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"433[{"message":"cannot parse integer from empty string"}]"#.into())
+    );
+}
+
+/// A future holding a `!Sync` value across an await point is `Send` but not `Sync`
+async fn non_sync_state(socket: SocketRef) {
+    let count = std::cell::Cell::new(1);
+    tokio::task::yield_now().await;
+    count.set(count.get() + 1);
+    socket.emit("count", count.get()).ok();
+}
+
+#[tokio::test]
+pub async fn non_sync_future_handlers() {
+    let io = create_server(2370).await;
+    io.ns("/", |socket: SocketRef| async move {
+        let ready = std::cell::Cell::new(false);
+        tokio::task::yield_now().await;
+        ready.set(true);
+        socket.on("count", non_sync_state);
+        socket.on_disconnect(non_sync_state);
+        assert!(ready.get());
+    });
+
+    let mut ws = create_ws_connection(2370).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"42["count"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"42["count",2]"#.into()));
+}
+
+#[tokio::test]
+pub async fn unserializable_ack() {
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+
+    let io = create_server(2390).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on_error(move |ack_id, err| tx.send((ack_id, err.to_string())).unwrap());
+        // Maps with non-string keys can't be serialized to JSON
+        socket.on("map", || async {
+            Ok::<_, ParseIntError>(HashMap::from([((1, 2), 3)]))
+        });
+    });
+
+    let mut ws = create_ws_connection(2390).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["map"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"431[{"message":"key must be a string"}]"#.into())
+    );
+    let (ack_id, err) = rx.recv().await.unwrap();
+    assert_eq!(ack_id, 1);
+    assert!(err.contains("key must be a string"), "{}", err);
+}
+
+#[tokio::test]
+pub async fn unserializable_ack_sender() {
+    use std::collections::HashMap;
+    use tokio::sync::mpsc;
+
+    let io = create_server(2432).await;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on_error(move |ack_id, err| tx.send((ack_id, err.to_string())).unwrap());
+        socket.on("map", |ack: AckSender| {
+            ack.send(HashMap::from([((1, 2), 3)])).ok();
+        });
+    });
+
+    let mut ws = create_ws_connection(2432).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text(r#"421["map"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"431[{"message":"key must be a string"}]"#.into())
+    );
+    let (ack_id, err) = rx.recv().await.unwrap();
+    assert_eq!(ack_id, 1);
+    assert!(err.contains("key must be a string"), "{}", err);
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,78 @@
+//! Tests for the idempotency keys attached to the messages
+mod fixture;
+
+use fixture::{create_server, create_ws_connection, recv_msg};
+use futures::SinkExt;
+use serde_json::Value;
+use socketioxide::extract::{Data, IdempotencyKey, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn idempotency_key() {
+    let io = create_server(2340).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on(
+            "order",
+            |socket: SocketRef, IdempotencyKey(key), Data::<Value>(data)| {
+                let key = key.unwrap_or_else(|| "none".to_string());
+                socket.with_id(key).emit("order", data).unwrap();
+            },
+        );
+        // The key is removed from the data whatever the order of the extractors
+        socket.on(
+            "data_first",
+            |socket: SocketRef, Data::<Value>(data), IdempotencyKey(key)| {
+                socket.emit("data_first", (data, key)).unwrap();
+            },
+        );
+        socket.on("data_only", |socket: SocketRef, Data::<Value>(data)| {
+            socket.emit("data_only", data).unwrap();
+        });
+        socket.on("broadcast", |socket: SocketRef| {
+            socket
+                .within(socket.id)
+                .with_id("b1")
+                .emit("broadcast", [1, 2])
+                .unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2340).await;
+    // Skip the engine.io open packet and the socket.io connect packet
+    recv_msg(&mut ws).await;
+    recv_msg(&mut ws).await;
+
+    let packet = r#"42["order","foo",{"_idempotencyKey":"k1"}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    assert_eq!(recv_msg(&mut ws).await, Message::Text(packet.to_string()));
+
+    // Without a key, the data is left untouched
+    let packet = r#"42["order","foo",{"key":"k1"}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    assert_eq!(
+        recv_msg(&mut ws).await,
+        Message::Text(r#"42["order","foo",{"key":"k1"},{"_idempotencyKey":"none"}]"#.to_string())
+    );
+
+    let packet = r#"42["data_first","foo",{"_idempotencyKey":"k2"}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    assert_eq!(
+        recv_msg(&mut ws).await,
+        Message::Text(r#"42["data_first","foo","k2"]"#.to_string())
+    );
+
+    let packet = r#"42["data_only","foo",{"_idempotencyKey":"k3"}]"#;
+    ws.send(Message::Text(packet.to_string())).await.unwrap();
+    assert_eq!(
+        recv_msg(&mut ws).await,
+        Message::Text(r#"42["data_only","foo"]"#.to_string())
+    );
+
+    ws.send(Message::Text(r#"42["broadcast"]"#.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(
+        recv_msg(&mut ws).await,
+        Message::Text(r#"42["broadcast",1,2,{"_idempotencyKey":"b1"}]"#.to_string())
+    );
+}
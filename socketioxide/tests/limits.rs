@@ -0,0 +1,171 @@
+//! Tests for the per-namespace and global socket limits
+mod fixture;
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use fixture::{connect_ws, create_server, create_server_with_config, WsStream};
+use futures::{SinkExt, StreamExt};
+use socketioxide::{extract::SocketRef, MaxSocketsPolicy};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Opens a websocket connection, connects to the given namespace and returns the response packet
+async fn connect(port: u16, ns: &str) -> (WsStream, String) {
+    let mut ws = connect_ws(port, ns, "").await;
+    let msg = next(&mut ws, Duration::from_millis(100))
+        .await
+        .expect("timeout waiting for a message");
+    (ws, msg)
+}
+
+/// Returns the next socket.io packet received within the timeout, answering the engine.io pings
+async fn next(ws: &mut WsStream, timeout: Duration) -> Option<String> {
+    tokio::time::timeout(timeout, async {
+        loop {
+            let msg = ws.next().await.unwrap().unwrap().to_string();
+            if msg == "2" {
+                ws.send(Message::Text("3".into())).await.unwrap();
+            } else {
+                break msg;
+            }
+        }
+    })
+    .await
+    .ok()
+}
+
+#[tokio::test]
+pub async fn max_sockets_per_ns() {
+    let io = create_server_with_config(2110, |b| b.max_sockets_per_ns(1).max_connections(10)).await;
+    io.ns("/", |_: SocketRef| {});
+    io.ns("/admin", |_: SocketRef| {});
+
+    let (_ws1, msg) = connect(2110, "/").await;
+    assert!(msg.starts_with("40{"));
+    let (_ws2, msg) = connect(2110, "/").await;
+    assert_eq!(msg, r#"44{"message":"Namespace is full"}"#);
+    let (_ws3, msg) = connect(2110, "/admin").await;
+    assert!(msg.starts_with("40/admin,{"));
+    assert_eq!(io.of("/").unwrap().sockets().unwrap().len(), 1);
+}
+
+#[tokio::test]
+pub async fn max_connections() {
+    let io = create_server_with_config(2111, |b| b.max_sockets_per_ns(10).max_connections(2)).await;
+    io.ns("/", |_: SocketRef| {});
+    io.ns("/admin", |_: SocketRef| {});
+
+    let (mut ws1, msg) = connect(2111, "/").await;
+    assert!(msg.starts_with("40{"));
+    let (mut ws2, msg) = connect(2111, "/admin").await;
+    assert!(msg.starts_with("40/admin,{"));
+    let (_ws3, msg) = connect(2111, "/").await;
+    assert_eq!(msg, r#"44{"message":"Too many connections"}"#);
+
+    // A connection is counted once, whatever the number of namespaces it is connected to
+    ws1.send(Message::Text("40/admin,".into())).await.unwrap();
+    let msg = next(&mut ws1, Duration::from_millis(100)).await.unwrap();
+    assert!(msg.starts_with("40/admin,{"));
+
+    // A slot is released once a socket is disconnected
+    ws2.close(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let (_ws4, msg) = connect(2111, "/").await;
+    assert!(msg.starts_with("40{"));
+}
+
+#[tokio::test]
+pub async fn max_sockets_queue() {
+    const TIMEOUT: Duration = Duration::from_millis(50);
+    let io = create_server(2400).await;
+    io.ns("/lobby", |_: SocketRef| {});
+    let policy = MaxSocketsPolicy::Queue(Duration::from_secs(1));
+    io.set_max_sockets("/lobby", 1, policy).unwrap();
+
+    let (mut ws1, msg) = connect(2400, "/lobby").await;
+    assert!(msg.starts_with("40/lobby,{"));
+    let mut queued = Vec::new();
+    for _ in 0..3 {
+        queued.push(connect_ws(2400, "/lobby", "").await);
+        // Let the server queue the connections in order
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let [mut ws2, ws3, mut ws4]: [WsStream; 3] = queued.try_into().unwrap();
+    assert_eq!(next(&mut ws2, TIMEOUT).await, None);
+    assert_eq!(io.of("/lobby").unwrap().sockets().unwrap().len(), 1);
+
+    // The first queued connection is admitted when a socket leaves
+    ws1.send(Message::Text("41/lobby,".into())).await.unwrap();
+    let msg = next(&mut ws2, TIMEOUT).await.unwrap();
+    assert!(msg.starts_with("40/lobby,{"));
+    assert_eq!(next(&mut ws4, TIMEOUT).await, None);
+
+    // A closed connection is skipped
+    drop(ws3);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    ws2.close(None).await.unwrap();
+    let msg = next(&mut ws4, TIMEOUT).await.unwrap();
+    assert!(msg.starts_with("40/lobby,{"));
+    assert_eq!(io.of("/lobby").unwrap().sockets().unwrap().len(), 1);
+}
+
+#[tokio::test]
+pub async fn max_sockets_queue_timeout() {
+    let io = create_server(2401).await;
+    io.ns("/lobby", |_: SocketRef| {});
+    io.ns("/full", |_: SocketRef| {});
+    let policy = MaxSocketsPolicy::Queue(Duration::from_millis(50));
+    io.set_max_sockets("/lobby", 1, policy).unwrap();
+    io.set_max_sockets("/full", 1, MaxSocketsPolicy::Reject)
+        .unwrap();
+
+    let (_ws1, msg) = connect(2401, "/lobby").await;
+    assert!(msg.starts_with("40/lobby,{"));
+    let mut ws2 = connect_ws(2401, "/lobby", "").await;
+    let msg = next(&mut ws2, Duration::from_millis(200)).await.unwrap();
+    assert_eq!(msg, r#"44/lobby,{"message":"Namespace queue timeout"}"#);
+
+    let (_ws3, msg) = connect(2401, "/full").await;
+    assert!(msg.starts_with("40/full,{"));
+    let (_ws4, msg) = connect(2401, "/full").await;
+    assert_eq!(msg, r#"44/full,{"message":"Namespace is full"}"#);
+
+    assert!(io
+        .set_max_sockets("/unknown", 1, MaxSocketsPolicy::Reject)
+        .is_err());
+}
+
+#[tokio::test]
+pub async fn max_sockets_queue_disconnect_from_connect_handler() {
+    const TIMEOUT: Duration = Duration::from_millis(50);
+    let io = create_server(2425).await;
+    let connected = Arc::new(AtomicUsize::new(0));
+    io.ns("/lobby", move |socket: SocketRef| {
+        // The second socket leaves the namespace while it is being admitted
+        if connected.fetch_add(1, Ordering::SeqCst) == 1 {
+            socket.disconnect().ok();
+        }
+    });
+    let policy = MaxSocketsPolicy::Queue(Duration::from_secs(1));
+    io.set_max_sockets("/lobby", 1, policy).unwrap();
+
+    let (mut ws1, msg) = connect(2425, "/lobby").await;
+    assert!(msg.starts_with("40/lobby,{"));
+    let mut ws2 = connect_ws(2425, "/lobby", "").await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let mut ws3 = connect_ws(2425, "/lobby", "").await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    ws1.send(Message::Text("41/lobby,".into())).await.unwrap();
+    let msg = next(&mut ws2, TIMEOUT).await.unwrap();
+    assert!(msg.starts_with("40/lobby,{"));
+    assert_eq!(next(&mut ws2, TIMEOUT).await.unwrap(), "41/lobby,");
+    // The slot freed by the connect handler is given to the next queued connection
+    let msg = next(&mut ws3, TIMEOUT).await.unwrap();
+    assert!(msg.starts_with("40/lobby,{"));
+}
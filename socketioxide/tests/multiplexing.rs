@@ -0,0 +1,79 @@
+//! Tests for the namespaces multiplexed over a single connection
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::SocketRef;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn socket_namespaces() {
+    let io = create_server(2280).await;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<String>>(4);
+    io.ns("/", |_: SocketRef| {});
+    io.ns("/other", |_: SocketRef| {});
+    io.ns("/admin", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on("namespaces", move |socket: SocketRef| {
+            tx.try_send(socket.namespaces()).unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2280).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text("40/admin,".into())).await.unwrap();
+    ws.next().await.unwrap().unwrap();
+    ws.send(Message::Text(r#"42/admin,["namespaces"]"#.into()))
+        .await
+        .unwrap();
+    let namespaces = tokio::time::timeout(Duration::from_millis(200), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(namespaces, ["/", "/admin"]);
+}
+
+#[cfg(feature = "extensions")]
+#[tokio::test]
+pub async fn connection_data() {
+    let io = create_server(2360).await;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Option<String>>(4);
+    io.ns("/", |socket: SocketRef| {
+        socket.on("login", |socket: SocketRef| {
+            socket.connection_data().insert("alice".to_string());
+        });
+    });
+    io.ns("/game", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on("whoami", move |socket: SocketRef| {
+            let user = socket.connection_data().get::<String>().map(|u| u.clone());
+            // The socket extensions are not shared with the other namespaces
+            assert!(socket.extensions.get::<String>().is_none());
+            tx.try_send(user).unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(2360).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    ws.send(Message::Text("40/game,".into())).await.unwrap();
+    ws.next().await.unwrap().unwrap();
+    ws.send(Message::Text(r#"42["login"]"#.into()))
+        .await
+        .unwrap();
+    ws.send(Message::Text(r#"42/game,["whoami"]"#.into()))
+        .await
+        .unwrap();
+    let user = tokio::time::timeout(Duration::from_millis(200), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.as_deref(), Some("alice"));
+}
@@ -0,0 +1,37 @@
+//! Tests for the outbound middlewares
+mod fixture;
+
+use fixture::{create_server, create_ws_connection, recv_event};
+use serde_json::Value;
+use socketioxide::{
+    extract::SocketRef,
+    packet::{Packet, PacketData},
+};
+
+fn push_arg(packet: &mut Packet<'_>, arg: &str) {
+    if let PacketData::Event(_, Value::Array(args), _) = &mut packet.inner {
+        args.push(arg.into());
+    }
+}
+
+#[tokio::test]
+pub async fn outbound_middleware_order() {
+    let io = create_server(2190).await;
+    let io2 = io.clone();
+    io.ns("/", move |socket: SocketRef| {
+        socket.emit("emit", [1]).unwrap();
+        io2.emit("broadcast", [2]).unwrap();
+    });
+    io.use_outbound("/", |packet: &mut Packet<'_>| push_arg(packet, "first"))
+        .unwrap();
+    io.use_outbound("/", |packet: &mut Packet<'_>| push_arg(packet, "second"))
+        .unwrap();
+    assert!(io
+        .use_outbound("/unknown", |_: &mut Packet<'_>| {})
+        .is_err());
+
+    let mut ws = create_ws_connection(2190).await;
+    let msgs = [recv_event(&mut ws).await, recv_event(&mut ws).await];
+    assert_eq!(msgs[0], r#"42["emit",1,"first","second"]"#);
+    assert_eq!(msgs[1], r#"42["broadcast",2,"first","second"]"#);
+}
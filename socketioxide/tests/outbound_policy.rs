@@ -0,0 +1,103 @@
+//! Tests for the [`OutboundPolicy`] applied when the buffer of a connection is full
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_polling_connection, create_server_with_config, send_req};
+use socketioxide::{
+    extract::SocketRef, socket::DisconnectReason, OutboundPolicy, SendError, SocketError,
+};
+
+#[tokio::test]
+pub async fn drop_oldest_queued_events() {
+    let io = create_server_with_config(2422, |builder| {
+        builder
+            .ping_interval(Duration::from_secs(10))
+            .max_buffer_size(4)
+            .outbound_policy(OutboundPolicy::DropOldest)
+    })
+    .await;
+    io.ns("/", || {});
+
+    let sid = create_polling_connection(2422).await;
+    let socket = io.sockets().unwrap().pop().unwrap();
+    // The buffer holds the connect packet and the 3 first events.
+    // The 4 next events are queued and the 3 oldest queued events are dropped to make room for the last ones.
+    for i in 0..10 {
+        socket.emit("event", i).unwrap();
+    }
+    assert_eq!(socket.dropped_packets(), 3);
+
+    let mut events = Vec::new();
+    let polling = async {
+        while events.len() < 7 {
+            let body = send_req(
+                2422,
+                format!("transport=polling&sid={sid}"),
+                http::Method::GET,
+                None,
+            )
+            .await;
+            events.extend(
+                body.split('\x1e')
+                    .filter_map(|packet| packet.split_once(r#"["event","#))
+                    .map(|(_, i)| i.trim_end_matches(']').parse::<u32>().unwrap()),
+            );
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(1), polling)
+        .await
+        .expect("the queued events were not sent");
+    assert_eq!(events, [0, 1, 2, 6, 7, 8, 9]);
+}
+
+#[tokio::test]
+pub async fn disconnect_notifies_client() {
+    let io = create_server_with_config(2423, |builder| {
+        builder
+            .ping_interval(Duration::from_secs(10))
+            .max_buffer_size(2)
+            .outbound_policy(OutboundPolicy::Disconnect)
+    })
+    .await;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<DisconnectReason>(1);
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on_disconnect(move |reason: DisconnectReason| async move {
+            tx.try_send(reason).unwrap();
+        });
+    });
+
+    let sid = create_polling_connection(2423).await;
+    let socket = io.sockets().unwrap().pop().unwrap();
+    // The buffer holds the connect packet and the first event
+    socket.emit("event", 0).unwrap();
+    assert!(matches!(
+        socket.emit("event", 1),
+        Err(SendError::Socket(SocketError::InternalChannelFull(_)))
+    ));
+    // The disconnect handler is not called while emitting
+    assert!(rx.try_recv().is_err());
+    assert!(!socket.connected());
+
+    let reason = tokio::time::timeout(Duration::from_millis(100), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reason, DisconnectReason::ServerNSDisconnect);
+
+    let mut packets = Vec::new();
+    while packets.len() < 3 {
+        let body = send_req(
+            2423,
+            format!("transport=polling&sid={sid}"),
+            http::Method::GET,
+            None,
+        )
+        .await;
+        // The fixture strips the engine.io packet type of the first packet
+        packets.extend(format!("4{body}").split('\x1e').map(str::to_string));
+    }
+    assert!(packets[0].starts_with("40"));
+    assert_eq!(packets[1..], [r#"42["event",0]"#, "41"]);
+}
@@ -0,0 +1,74 @@
+//! Tests for the per-event payload size limits
+mod fixture;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use socketioxide::extract::{AckSender, Bin, Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn per_event_payload_limit() {
+    let io = create_server(2290).await;
+    io.ns("/", |socket: SocketRef| {
+        socket.on_with_max_payload("chat", 16, |Data::<Value>(data), ack: AckSender| {
+            ack.send(data).ok();
+        });
+        socket.on_with_max_payload("upload", 64, |ack: AckSender, Bin(bin)| {
+            ack.send(bin.len()).ok();
+        });
+        socket.on("unlimited", |Data::<Value>(data), ack: AckSender| {
+            ack.send(data).ok();
+        });
+    });
+
+    let mut ws = create_ws_connection(2290).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    // The payload `["chat","hello"]` is exactly at the limit
+    ws.send(Message::Text(r#"421["chat","hello"]"#.into()))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"431["hello"]"#.into()));
+
+    let long = "a".repeat(32);
+    ws.send(Message::Text(format!(r#"422["chat","{long}"]"#)))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"432[{"message":"payload too large"}]"#.into())
+    );
+
+    ws.send(Message::Text(format!(r#"423["unlimited","{long}"]"#)))
+        .await
+        .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(format!(r#"433["{long}"]"#)));
+
+    // The binary attachments are counted in the payload size
+    ws.send(Message::Text(
+        r#"451-4["upload",{"_placeholder":true,"num":0}]"#.into(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![1; 4])).await.unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Text(r#"434[1]"#.into()));
+
+    ws.send(Message::Text(
+        r#"451-5["upload",{"_placeholder":true,"num":0}]"#.into(),
+    ))
+    .await
+    .unwrap();
+    ws.send(Message::Binary(vec![1; 64])).await.unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"435[{"message":"payload too large"}]"#.into())
+    );
+}
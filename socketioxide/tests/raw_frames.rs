@@ -0,0 +1,31 @@
+//! Tests for the raw frames sent with `Socket::send_raw` and `Socket::send_raw_bin`
+mod fixture;
+
+use fixture::{create_server, create_ws_connection};
+use futures::StreamExt;
+use socketioxide::extract::SocketRef;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn send_raw_frames() {
+    let io = create_server(2220).await;
+    io.ns("/", |socket: SocketRef| {
+        socket
+            .send_raw(r#"51-["raw",{"_placeholder":true,"num":0}]"#.to_string())
+            .unwrap();
+        socket.send_raw_bin(vec![1, 2, 3]).unwrap();
+    });
+
+    let mut ws = create_ws_connection(2220).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(
+        msg,
+        Message::Text(r#"451-["raw",{"_placeholder":true,"num":0}]"#.into())
+    );
+    let msg = ws.next().await.unwrap().unwrap();
+    assert_eq!(msg, Message::Binary(vec![1, 2, 3]));
+}
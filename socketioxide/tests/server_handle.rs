@@ -0,0 +1,38 @@
+//! Tests for the server handle of a socket
+mod fixture;
+
+use fixture::{create_server, open_ws, recv};
+use futures::{SinkExt, StreamExt};
+use socketioxide::extract::{Data, SocketRef};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn broadcast_to_other_namespace() {
+    let io = create_server(2170).await;
+    io.ns("/notifications", |_: SocketRef| {});
+    io.ns("/chat", |socket: SocketRef| {
+        socket.on("message", |socket: SocketRef, Data::<String>(msg)| {
+            let io = socket.server();
+            io.of("/notifications")
+                .unwrap()
+                .emit("new message", msg)
+                .unwrap();
+        });
+    });
+
+    let mut ws = open_ws(2170, "").await;
+    // Skip the engine.io open packet
+    ws.next().await.unwrap().unwrap();
+    for ns in ["/notifications", "/chat"] {
+        ws.send(Message::Text(format!("40{ns},"))).await.unwrap();
+        assert!(recv(&mut ws).await.starts_with(&format!("40{ns},{{")));
+    }
+
+    ws.send(Message::Text(r#"42/chat,["message","hello"]"#.to_string()))
+        .await
+        .unwrap();
+    assert_eq!(
+        recv(&mut ws).await,
+        r#"42/notifications,["new message","hello"]"#
+    );
+}
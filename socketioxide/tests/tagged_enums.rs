@@ -0,0 +1,104 @@
+//! Tests for the deserialization of tagged enums sent as events data
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_server, create_ws_connection};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use socketioxide::extract::{Data, SocketRef, TryData};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+enum Internal {
+    Join { room: String },
+    Leave,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum External {
+    Join { room: String },
+    Leave,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "t", content = "c")]
+enum Adjacent {
+    Join { room: String },
+    Leave,
+}
+
+#[tokio::test]
+pub async fn tagged_enums() {
+    const PORT: u16 = 2380;
+    const TIMEOUT: Duration = Duration::from_millis(200);
+    let io = create_server(PORT).await;
+    let (tx, mut rx) = mpsc::channel::<String>(16);
+    io.ns("/", move |socket: SocketRef| {
+        let tx1 = tx.clone();
+        socket.on("internal", move |Data(data): Data<Internal>| {
+            tx1.try_send(format!("{:?}", data)).unwrap();
+        });
+        let tx1 = tx.clone();
+        socket.on("external", move |Data(data): Data<External>| {
+            tx1.try_send(format!("{:?}", data)).unwrap();
+        });
+        let tx1 = tx.clone();
+        socket.on("adjacent", move |TryData(data): TryData<Adjacent>| {
+            let data = data.map_or_else(|_| "error".to_string(), |d| format!("{:?}", d));
+            tx1.try_send(data).unwrap();
+        });
+    });
+
+    let mut ws = create_ws_connection(PORT).await;
+    // The engine.io open packet and the socket.io connect packet
+    ws.next().await.unwrap().unwrap();
+    ws.next().await.unwrap().unwrap();
+
+    let frames = [
+        // Sent as the argument
+        (
+            r#"42["internal",{"type":"Join","room":"a"}]"#,
+            r#"Join { room: "a" }"#,
+        ),
+        (r#"42["internal",{"type":"Leave"}]"#, "Leave"),
+        (
+            r#"42["external",{"Join":{"room":"a"}}]"#,
+            r#"Join { room: "a" }"#,
+        ),
+        (r#"42["external","Leave"]"#, "Leave"),
+        (
+            r#"42["adjacent",{"t":"Join","c":{"room":"a"}}]"#,
+            r#"Join { room: "a" }"#,
+        ),
+        (r#"42["adjacent",{"t":"Leave"}]"#, "Leave"),
+        // Wrapped in an array
+        (
+            r#"42["internal",[{"type":"Join","room":"b"}]]"#,
+            r#"Join { room: "b" }"#,
+        ),
+        (r#"42["internal",[{"type":"Leave"}]]"#, "Leave"),
+        (
+            r#"42["external",[{"Join":{"room":"b"}}]]"#,
+            r#"Join { room: "b" }"#,
+        ),
+        (r#"42["external",["Leave"]]"#, "Leave"),
+        (
+            r#"42["adjacent",[{"t":"Join","c":{"room":"b"}}]]"#,
+            r#"Join { room: "b" }"#,
+        ),
+        (r#"42["adjacent",[{"t":"Leave"}]]"#, "Leave"),
+        // Multiple arguments are not unwrapped
+        (r#"42["adjacent",{"t":"Leave"},{"t":"Leave"}]"#, "error"),
+    ];
+    for (frame, expected) in frames {
+        ws.send(Message::Text(frame.into())).await.unwrap();
+        let data = tokio::time::timeout(TIMEOUT, rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data, expected, "frame: {}", frame);
+    }
+}
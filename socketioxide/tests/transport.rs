@@ -0,0 +1,93 @@
+//! Tests for the transport type of a socket
+mod fixture;
+
+use std::time::Duration;
+
+use fixture::{create_polling_connection, create_server};
+use futures::{SinkExt, StreamExt};
+use socketioxide::{extract::SocketRef, TransportType};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+pub async fn transport_upgrade() {
+    let io = create_server(2320).await;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(TransportType, bool)>(4);
+    io.ns("/", move |socket: SocketRef| {
+        tx.try_send((socket.transport_type(), socket.upgraded()))
+            .unwrap();
+        let tx = tx.clone();
+        socket.on("transport", move |socket: SocketRef| {
+            tx.try_send((socket.transport_type(), socket.upgraded()))
+                .unwrap();
+        });
+    });
+
+    let sid = create_polling_connection(2320).await;
+    assert_eq!(
+        tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .unwrap()
+            .unwrap(),
+        (TransportType::Polling, false)
+    );
+
+    let mut ws = tokio_tungstenite::connect_async(format!(
+        "ws://127.0.0.1:2320/socket.io/?EIO=4&transport=websocket&sid={sid}"
+    ))
+    .await
+    .unwrap()
+    .0;
+    ws.send(Message::Text("2probe".into())).await.unwrap();
+    assert_eq!(
+        ws.next().await.unwrap().unwrap(),
+        Message::Text("3probe".into())
+    );
+    ws.send(Message::Text("5".into())).await.unwrap();
+
+    ws.send(Message::Text(r#"42["transport"]"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .unwrap()
+            .unwrap(),
+        (TransportType::Websocket, true)
+    );
+}
+
+#[tokio::test]
+pub async fn on_upgrade() {
+    let io = create_server(2421).await;
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<(TransportType, bool)>(4);
+    io.ns("/", move |socket: SocketRef| {
+        let tx = tx.clone();
+        socket.on_upgrade(move |socket: SocketRef| {
+            tx.try_send((socket.transport_type(), socket.upgraded()))
+                .unwrap();
+        });
+    });
+
+    let sid = create_polling_connection(2421).await;
+    let mut ws = tokio_tungstenite::connect_async(format!(
+        "ws://127.0.0.1:2421/socket.io/?EIO=4&transport=websocket&sid={sid}"
+    ))
+    .await
+    .unwrap()
+    .0;
+    ws.send(Message::Text("2probe".into())).await.unwrap();
+    assert_eq!(
+        ws.next().await.unwrap().unwrap(),
+        Message::Text("3probe".into())
+    );
+    assert!(rx.try_recv().is_err());
+    ws.send(Message::Text("5".into())).await.unwrap();
+
+    assert_eq!(
+        tokio::time::timeout(Duration::from_millis(200), rx.recv())
+            .await
+            .unwrap()
+            .unwrap(),
+        (TransportType::Websocket, true)
+    );
+}